@@ -0,0 +1,226 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed};
+
+use crate::errors::{CommonError, CommonResult};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Currency {
+    pub code: &'static str,
+    symbols: &'static [&'static str],
+    pub decimals: usize,
+}
+
+const fn currency(
+    code: &'static str,
+    symbols: &'static [&'static str],
+    decimals: usize,
+) -> Currency {
+    Currency {
+        code,
+        symbols,
+        decimals,
+    }
+}
+
+/// A symbol only belongs to one currency, so `$` is always US dollars
+static CURRENCIES: &[Currency] = &[
+    currency("USD", &["$"], 2),
+    currency("EUR", &["€"], 2),
+    currency("GBP", &["£"], 2),
+    currency("JPY", &["¥"], 0),
+    currency("CNY", &[], 2),
+    currency("CHF", &[], 2),
+    currency("SEK", &[], 2),
+    currency("NOK", &[], 2),
+    currency("DKK", &[], 2),
+    currency("ISK", &[], 0),
+    currency("PLN", &[], 2),
+    currency("CZK", &[], 2),
+    currency("HUF", &[], 2),
+    currency("CAD", &[], 2),
+    currency("AUD", &[], 2),
+    currency("NZD", &[], 2),
+    currency("HKD", &[], 2),
+    currency("SGD", &[], 2),
+    currency("INR", &["₹"], 2),
+    currency("KRW", &["₩"], 0),
+    currency("THB", &["฿"], 2),
+    currency("ILS", &["₪"], 2),
+    currency("TRY", &["₺"], 2),
+    currency("RUB", &["₽"], 2),
+    currency("BRL", &[], 2),
+    currency("MXN", &[], 2),
+    currency("ZAR", &[], 2),
+];
+
+impl Currency {
+    pub fn find(code: &str) -> Option<&'static Self> {
+        CURRENCIES.iter().find(|currency| currency.code == code)
+    }
+
+    pub fn find_symbol(input: &str) -> Option<(&'static Self, usize)> {
+        CURRENCIES.iter().find_map(|currency| {
+            currency
+                .symbols
+                .iter()
+                .find(|symbol| input.starts_with(**symbol))
+                .map(|symbol| (currency, symbol.len()))
+        })
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code)
+    }
+}
+
+/// Rates from a file the user maintains, with lines like `USD = 10.52` and `base = SEK`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExchangeRates {
+    rates: HashMap<&'static str, BigRational>,
+    unknown_currencies: Vec<(String, usize)>,
+}
+
+impl ExchangeRates {
+    pub fn load(path: &Path) -> CommonResult<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Lines starting with `#` are comments and lines with unknown currencies are skipped
+    pub fn parse(content: &str) -> CommonResult<Self> {
+        let mut rates = HashMap::new();
+        let mut unknown_currencies = vec![];
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || CommonError::InvalidExchangeRate(index + 1);
+            let (name, value) = line.split_once('=').ok_or_else(invalid)?;
+            let (code, rate) = match (name.trim(), value.trim()) {
+                ("base", code) => (code, Some(BigRational::one())),
+                (code, rate) => (code, parse_rate(rate)),
+            };
+            match (Currency::find(code), rate) {
+                (Some(currency), Some(rate)) => {
+                    rates.insert(currency.code, rate);
+                }
+                (None, Some(_)) if is_currency_code(code) => {
+                    unknown_currencies.push((code.to_owned(), index + 1));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Self {
+            rates,
+            unknown_currencies,
+        })
+    }
+
+    /// The codes and the lines they are on, counted from 1
+    pub fn unknown_currencies(&self) -> &[(String, usize)] {
+        &self.unknown_currencies
+    }
+
+    /// The factor from `from` to `to`, or the currency that is missing a rate
+    pub fn rate(
+        &self,
+        from: &'static Currency,
+        to: &'static Currency,
+    ) -> Result<BigRational, &'static Currency> {
+        if from == to {
+            return Ok(BigRational::one());
+        }
+        let from_rate = self.rates.get(from.code).ok_or(from)?;
+        let to_rate = self.rates.get(to.code).ok_or(to)?;
+        Ok(from_rate / to_rate)
+    }
+}
+
+fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+fn parse_rate(rate: &str) -> Option<BigRational> {
+    let (whole, fraction) = rate.split_once('.').unwrap_or((rate, ""));
+    if !(whole.chars().chain(fraction.chars())).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let digits = format!("{whole}{fraction}").parse::<BigInt>().ok()?;
+    let rate = BigRational::new(digits, BigInt::from(10).pow(fraction.len() as u32));
+    rate.is_positive().then_some(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn currency(code: &str) -> &'static Currency {
+        Currency::find(code).unwrap()
+    }
+
+    #[test]
+    fn find() {
+        assert_eq!(currency("EUR").code, "EUR");
+        assert!(Currency::find("eur").is_none());
+        assert_eq!(Currency::find_symbol("€20"), Some((currency("EUR"), 3)));
+        assert_eq!(Currency::find_symbol("$5"), Some((currency("USD"), 1)));
+        assert_eq!(Currency::find_symbol("5"), None);
+    }
+
+    #[test]
+    fn parse_rates() {
+        let rates =
+            ExchangeRates::parse("# Rates in SEK\n\nbase = SEK\nUSD = 10.5\n  EUR=11.25  \n")
+                .unwrap();
+
+        assert_eq!(
+            rates.rate(currency("USD"), currency("SEK")),
+            Ok(BigRational::new(21.into(), 2.into()))
+        );
+        assert_eq!(
+            rates.rate(currency("EUR"), currency("USD")),
+            Ok(BigRational::new(15.into(), 14.into()))
+        );
+        assert_eq!(
+            rates.rate(currency("USD"), currency("GBP")),
+            Err(currency("GBP"))
+        );
+        assert_eq!(
+            rates.rate(currency("GBP"), currency("GBP")),
+            Ok(BigRational::one())
+        );
+    }
+
+    #[test]
+    fn unknown_currencies_are_skipped() {
+        let rates = ExchangeRates::parse("base = SEK\nXAU = 5\nUSD = 10.5\n").unwrap();
+
+        assert_eq!(rates.unknown_currencies(), [("XAU".to_owned(), 2)]);
+        assert_eq!(
+            rates.rate(currency("USD"), currency("SEK")),
+            Ok(BigRational::new(21.into(), 2.into()))
+        );
+    }
+
+    #[test]
+    fn invalid_rates() {
+        let test_cases = [
+            ("USD = 10.5\nEUR 11", 2),
+            ("XYZ = -1", 1),
+            ("USD = -1", 1),
+            ("USD = 0", 1),
+            ("base = sek", 1),
+        ];
+        for (content, line) in test_cases {
+            assert!(matches!(
+                ExchangeRates::parse(content),
+                Err(CommonError::InvalidExchangeRate(actual)) if actual == line
+            ));
+        }
+    }
+}
@@ -4,4 +4,53 @@ pub type CommonResult<T> = Result<T, CommonError>;
 pub enum CommonError {
     UnknownFunctionName(String),
     OutOfBounds(usize),
+    /// Counted from 1
+    InvalidExchangeRate(usize),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for CommonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::UnknownFunctionName(name) => write!(f, "unknown function `{name}`"),
+            Self::OutOfBounds(index) => write!(f, "there is no line {}", index + 1),
+            Self::InvalidExchangeRate(line) => {
+                write!(f, "line {line} isn't a valid exchange rate")
+            }
+            Self::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for CommonError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let test_cases = [
+            (
+                CommonError::UnknownFunctionName("foo".to_owned()),
+                "unknown function `foo`",
+            ),
+            (CommonError::OutOfBounds(4), "there is no line 5"),
+            (
+                CommonError::InvalidExchangeRate(3),
+                "line 3 isn't a valid exchange rate",
+            ),
+            (
+                std::io::Error::from(std::io::ErrorKind::PermissionDenied).into(),
+                "permission denied",
+            ),
+        ];
+        for (error, expected) in test_cases {
+            assert_eq!(error.to_string(), expected);
+        }
+    }
 }
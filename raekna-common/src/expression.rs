@@ -1,23 +1,211 @@
-use crate::function_name::FunctionName;
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
 
-#[derive(Debug, Copy, Clone)]
+use crate::{currency::Currency, function_name::FunctionName, unit::Unit};
+
+/// The number of decimals shown for fractions that don't have an exact decimal representation
+const MAX_DECIMALS: usize = 16;
+
+#[derive(Debug, Clone)]
 pub enum Literal {
     Integer(i64),
+    /// Doesn't fit in an `i64`, create it with `Literal::from`
+    BigInteger(BigInt),
+    /// Never a whole number, create it with `Literal::from`
+    Rational(BigRational),
     Float(f64),
+    /// Always has an imaginary part, create it with `Literal::from`
+    Complex(Complex64),
+    /// Like `5 km`, create it with `Literal::quantity`
+    Quantity(Box<Literal>, Unit),
+    /// Like `20 EUR`, exact but displayed rounded to the decimals of the currency
+    Money(Box<Literal>, &'static Currency),
+    /// Like `15%`
+    Percentage(Box<Literal>),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    /// Local time if there is no offset
+    DateTime(NaiveDateTime, Option<FixedOffset>),
+    Boolean(bool),
+    /// The format is dropped when the value is used in a calculation
+    Formatted(Box<Literal>, Format),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Fraction,
+    Hexadecimal,
+    Binary,
+    Octal,
 }
 
 impl Literal {
     pub fn maybe_truncate(self) -> Self {
         match self {
-            Self::Float(value) if value.fract().abs() < f64::EPSILON => Self::Integer(value as i64),
+            Self::Float(value) if value.fract().abs() < f64::EPSILON => Self::from(value),
             _ => self,
         }
     }
 
-    pub fn as_f64(self) -> f64 {
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Self::Integer(i) => *i as f64,
+            Self::BigInteger(i) => i.to_f64().unwrap_or(f64::NAN),
+            Self::Rational(r) => r.to_f64().unwrap_or(f64::NAN),
+            Self::Float(f) => *f,
+            Self::Complex(_)
+            | Self::Quantity(_, _)
+            | Self::Money(_, _)
+            | Self::Percentage(_)
+            | Self::Date(_)
+            | Self::Time(_)
+            | Self::DateTime(_, _)
+            | Self::Boolean(_) => f64::NAN,
+            Self::Formatted(value, _) => value.as_f64(),
+        }
+    }
+
+    pub fn as_big_int(&self) -> Option<BigInt> {
+        match self {
+            Self::Integer(i) => Some(BigInt::from(*i)),
+            Self::BigInteger(i) => Some(i.clone()),
+            Self::Rational(_)
+            | Self::Float(_)
+            | Self::Complex(_)
+            | Self::Quantity(_, _)
+            | Self::Money(_, _)
+            | Self::Percentage(_)
+            | Self::Date(_)
+            | Self::Time(_)
+            | Self::DateTime(_, _)
+            | Self::Boolean(_) => None,
+            Self::Formatted(value, _) => value.as_big_int(),
+        }
+    }
+
+    pub fn as_rational(&self) -> Option<BigRational> {
         match self {
-            Self::Integer(i) => i as f64,
-            Self::Float(f) => f,
+            Self::Integer(i) => Some(BigRational::from_integer(BigInt::from(*i))),
+            Self::BigInteger(i) => Some(BigRational::from_integer(i.clone())),
+            Self::Rational(r) => Some(r.clone()),
+            Self::Float(_)
+            | Self::Complex(_)
+            | Self::Quantity(_, _)
+            | Self::Money(_, _)
+            | Self::Percentage(_)
+            | Self::Date(_)
+            | Self::Time(_)
+            | Self::DateTime(_, _)
+            | Self::Boolean(_) => None,
+            Self::Formatted(value, _) => value.as_rational(),
+        }
+    }
+
+    pub fn as_complex(&self) -> Complex64 {
+        match self {
+            Self::Complex(c) => *c,
+            Self::Formatted(value, _) => value.as_complex(),
+            _ => Complex64::new(self.as_f64(), 0.0),
+        }
+    }
+
+    pub fn is_complex(&self) -> bool {
+        match self {
+            Self::Complex(_) => true,
+            Self::Formatted(value, _) => value.is_complex(),
+            _ => false,
+        }
+    }
+
+    pub fn quantity(value: Literal, unit: Unit) -> Self {
+        if unit.is_empty() {
+            value
+        } else {
+            Self::Quantity(Box::new(value), unit)
+        }
+    }
+
+    pub fn split_unit(self) -> (Literal, Unit) {
+        match self {
+            Self::Quantity(value, unit) => (*value, unit),
+            Self::Formatted(value, _) => value.split_unit(),
+            _ => (self, Unit::default()),
+        }
+    }
+
+    pub fn has_unit(&self) -> bool {
+        match self {
+            Self::Quantity(_, _) => true,
+            Self::Formatted(value, _) => value.has_unit(),
+            _ => false,
+        }
+    }
+
+    pub fn money(value: Literal, currency: &'static Currency) -> Self {
+        Self::Money(Box::new(value), currency)
+    }
+
+    pub fn split_currency(self) -> (Literal, Option<&'static Currency>) {
+        match self {
+            Self::Money(value, currency) => (*value, Some(currency)),
+            Self::Formatted(value, _) => value.split_currency(),
+            _ => (self, None),
+        }
+    }
+
+    pub fn currency(&self) -> Option<&'static Currency> {
+        match self {
+            Self::Money(_, currency) => Some(currency),
+            Self::Formatted(value, _) => value.currency(),
+            _ => None,
+        }
+    }
+
+    pub fn is_percentage(&self) -> bool {
+        match self {
+            Self::Percentage(_) => true,
+            Self::Formatted(value, _) => value.is_percentage(),
+            _ => false,
+        }
+    }
+
+    pub fn is_date(&self) -> bool {
+        match self {
+            Self::Date(_) | Self::Time(_) | Self::DateTime(_, _) => true,
+            Self::Formatted(value, _) => value.is_date(),
+            _ => false,
+        }
+    }
+
+    pub fn is_boolean(&self) -> bool {
+        match self {
+            Self::Boolean(_) => true,
+            Self::Formatted(value, _) => value.is_boolean(),
+            _ => false,
+        }
+    }
+
+    /// Numbers are true unless they're zero, `None` for values that are neither booleans nor numbers
+    pub fn is_truthy(&self) -> Option<bool> {
+        match self {
+            Self::Boolean(value) => Some(*value),
+            Self::Integer(_) | Self::BigInteger(_) | Self::Rational(_) => {
+                self.as_rational().map(|value| !value.is_zero())
+            }
+            Self::Float(value) => Some(*value != 0.0),
+            Self::Complex(value) => Some(!value.is_zero()),
+            Self::Formatted(value, _) => value.is_truthy(),
+            _ => None,
+        }
+    }
+
+    pub fn unformatted(self) -> Self {
+        match self {
+            Self::Formatted(value, _) => value.unformatted(),
+            _ => self,
         }
     }
 }
@@ -26,9 +214,53 @@ impl Eq for Literal {}
 impl PartialEq for Literal {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (Self::Float(_), Self::Integer(_)) | (Self::Integer(_), Self::Float(_)) => false,
             (Self::Integer(left), Self::Integer(right)) => *left == *right,
+            (Self::BigInteger(left), Self::BigInteger(right)) => *left == *right,
+            (Self::Rational(left), Self::Rational(right)) => *left == *right,
             (Self::Float(left), Self::Float(right)) => (*left - *right).abs() <= f64::EPSILON,
+            (Self::Complex(left), Self::Complex(right)) => {
+                (left.re - right.re).abs() <= f64::EPSILON
+                    && (left.im - right.im).abs() <= f64::EPSILON
+            }
+            (Self::Quantity(left, left_unit), Self::Quantity(right, right_unit)) => {
+                left == right && left_unit == right_unit
+            }
+            (Self::Money(left, left_currency), Self::Money(right, right_currency)) => {
+                left == right && left_currency == right_currency
+            }
+            (Self::Percentage(left), Self::Percentage(right)) => left == right,
+            (Self::Date(left), Self::Date(right)) => left == right,
+            (Self::Time(left), Self::Time(right)) => left == right,
+            (Self::Boolean(left), Self::Boolean(right)) => left == right,
+            (Self::DateTime(left, left_offset), Self::DateTime(right, right_offset)) => {
+                left == right && left_offset == right_offset
+            }
+            (Self::Formatted(left, left_format), Self::Formatted(right, right_format)) => {
+                left == right && left_format == right_format
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::ops::Neg for Literal {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            Self::Integer(i) => match i.checked_neg() {
+                Some(i) => Self::Integer(i),
+                None => Self::from(-BigInt::from(i)),
+            },
+            Self::BigInteger(i) => Self::from(-i),
+            Self::Rational(r) => Self::Rational(-r),
+            Self::Float(f) => Self::Float(-f),
+            Self::Complex(c) => Self::Complex(-c),
+            Self::Quantity(value, unit) => Self::Quantity(Box::new(-*value), unit),
+            Self::Money(value, currency) => Self::Money(Box::new(-*value), currency),
+            Self::Percentage(value) => Self::Percentage(Box::new(-*value)),
+            Self::Date(_) | Self::Time(_) | Self::DateTime(_, _) | Self::Boolean(_) => self,
+            Self::Formatted(value, _) => -*value,
         }
     }
 }
@@ -37,21 +269,154 @@ impl std::fmt::Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let as_str = match self {
             Literal::Integer(value) => value.to_string(),
+            Literal::BigInteger(value) => value.to_string(),
+            Literal::Rational(value) => format_decimal(value, MAX_DECIMALS),
             Literal::Float(value) => value.to_string(),
+            Literal::Complex(value) => format_complex(value),
+            Literal::Quantity(value, unit) => {
+                let value = value.to_string();
+                match unit.plural() {
+                    Some(plural) if value.trim_start_matches('-') != "1" => {
+                        format!("{value} {plural}")
+                    }
+                    _ => format!("{value} {unit}"),
+                }
+            }
+            Literal::Money(value, currency) => {
+                format!("{} {currency}", format_money(value, currency))
+            }
+            Literal::Percentage(value) => format!("{value}%"),
+            Literal::Date(date) => date.format("%Y-%m-%d").to_string(),
+            Literal::Time(time) => format_time(time),
+            Literal::Boolean(value) => value.to_string(),
+            Literal::DateTime(date_time, offset) => {
+                let date_time = format!(
+                    "{} {}",
+                    date_time.date().format("%Y-%m-%d"),
+                    format_time(&date_time.time())
+                );
+                match offset {
+                    Some(offset) => format!("{date_time} {}", format_offset(offset)),
+                    None => date_time,
+                }
+            }
+            Literal::Formatted(value, Format::Fraction) => match value.as_ref() {
+                Literal::Rational(value) => format!("{}/{}", value.numer(), value.denom()),
+                value => value.to_string(),
+            },
+            Literal::Formatted(value, format) => match value.as_big_int() {
+                Some(value) => format_base(&value, *format),
+                None => value.to_string(),
+            },
         };
         write!(f, "{as_str}")
     }
 }
 
+fn format_decimal(value: &BigRational, max_decimals: usize) -> String {
+    let scale = BigInt::from(10).pow(max_decimals as u32);
+    let scaled = (value.abs() * &scale).round().to_integer();
+    let digits = scaled.to_string();
+    let digits = format!("{digits:0>width$}", width = max_decimals + 1);
+    let (whole, decimals) = digits.split_at(digits.len() - max_decimals);
+    let decimals = decimals.trim_end_matches('0');
+    let sign = if value.is_negative() && !scaled.is_zero() {
+        "-"
+    } else {
+        ""
+    };
+    if decimals.is_empty() {
+        format!("{sign}{whole}")
+    } else {
+        format!("{sign}{whole}.{decimals}")
+    }
+}
+
+fn format_money(value: &Literal, currency: &Currency) -> String {
+    let exact = match value {
+        Literal::Float(value) => BigRational::from_float(*value),
+        Literal::Formatted(_, _) => None,
+        value => value.as_rational(),
+    };
+    match exact {
+        Some(exact) => format_decimal(&exact, currency.decimals),
+        None => value.to_string(),
+    }
+}
+
+fn format_time(time: &NaiveTime) -> String {
+    if time.second() == 0 {
+        time.format("%H:%M").to_string()
+    } else {
+        time.format("%H:%M:%S").to_string()
+    }
+}
+
+/// Like `UTC+9` or `UTC-5:30`
+pub fn format_offset(offset: &FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let (hours, minutes) = (seconds.abs() / 3600, seconds.abs() % 3600 / 60);
+    match (hours, minutes) {
+        (0, 0) => "UTC".to_owned(),
+        (hours, 0) => format!("UTC{sign}{hours}"),
+        (hours, minutes) => format!("UTC{sign}{hours}:{minutes:02}"),
+    }
+}
+
+fn format_base(value: &BigInt, format: Format) -> String {
+    let (prefix, radix) = match format {
+        Format::Hexadecimal => ("0x", 16),
+        Format::Binary => ("0b", 2),
+        Format::Octal => ("0o", 8),
+        Format::Fraction => return value.to_string(),
+    };
+    let sign = if value.is_negative() { "-" } else { "" };
+    let digits = value.abs().to_str_radix(radix).to_uppercase();
+    format!("{sign}{prefix}{digits}")
+}
+
+fn format_complex(value: &Complex64) -> String {
+    let imaginary = match value.im.abs() {
+        1.0 => "i".to_owned(),
+        im => format!("{im}i"),
+    };
+    match (value.re, value.im.is_sign_negative()) {
+        (0.0, false) => imaginary,
+        (0.0, true) => format!("-{imaginary}"),
+        (re, false) => format!("{re} + {imaginary}"),
+        (re, true) => format!("{re} - {imaginary}"),
+    }
+}
+
 impl From<i64> for Literal {
     fn from(value: i64) -> Self {
         Self::Integer(value)
     }
 }
 
+impl From<BigInt> for Literal {
+    fn from(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(value) => Self::Integer(value),
+            None => Self::BigInteger(value),
+        }
+    }
+}
+
+impl From<BigRational> for Literal {
+    fn from(value: BigRational) -> Self {
+        if value.is_integer() {
+            Self::from(value.to_integer())
+        } else {
+            Self::Rational(value)
+        }
+    }
+}
+
 impl From<f64> for Literal {
     fn from(value: f64) -> Self {
-        if value > i64::MAX as f64 {
+        if value > i64::MAX as f64 || value < i64::MIN as f64 {
             Self::Float(value)
         } else if value.fract().abs() < f64::EPSILON {
             Self::Integer(value as i64)
@@ -61,10 +426,220 @@ impl From<f64> for Literal {
     }
 }
 
+impl From<Complex64> for Literal {
+    fn from(value: Complex64) -> Self {
+        if value.im == 0.0 {
+            Self::from(value.re)
+        } else {
+            Self::Complex(value)
+        }
+    }
+}
+
+/// What `in` or `to` converts to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionTarget {
+    Unit(Unit),
+    Currency(&'static Currency),
+    Format(Format),
+    TimeZone(FixedOffset),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Expression {
     Literal(Literal),
     Variable(String, Box<Expression>),
     VariableRef(String),
     Function(FunctionName, Vec<Expression>),
+    Conversion(Box<Expression>, ConversionTarget),
+    /// Like `area(w, h): w * h`
+    FunctionDefinition(String, Vec<String>, Box<Expression>),
+    /// A call of a function that isn't built in
+    UserFunction(String, Vec<Expression>),
+    LineRef(LineReference),
+}
+
+/// Lines are numbered from 1
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineReference {
+    /// `prev` or `ans`
+    Previous,
+    /// `line3` or `#3`
+    Line(usize),
+    /// `line?` or `#?`, a reference to a removed line
+    Removed,
+}
+
+impl LineReference {
+    pub fn find(text: &str) -> Option<Self> {
+        match text {
+            "prev" | "ans" => Some(Self::Previous),
+            "line?" | "#?" => Some(Self::Removed),
+            _ => {
+                let number = text.strip_prefix("line").or(text.strip_prefix('#'))?;
+                if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                number.parse().ok().map(Self::Line)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(numer: i64, denom: i64) -> Literal {
+        Literal::from(BigRational::new(numer.into(), denom.into()))
+    }
+
+    #[test]
+    fn find_line_reference() {
+        let test_cases = [
+            ("prev", Some(LineReference::Previous)),
+            ("ans", Some(LineReference::Previous)),
+            ("line3", Some(LineReference::Line(3))),
+            ("#12", Some(LineReference::Line(12))),
+            ("#?", Some(LineReference::Removed)),
+            ("line?", Some(LineReference::Removed)),
+            ("line", None),
+            ("#", None),
+            ("line+3", None),
+            ("lines3", None),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(LineReference::find(input), expected, "input: {input}");
+        }
+    }
+
+    #[test]
+    fn whole_fractions_become_integers() {
+        assert_eq!(ratio(6, 3), Literal::Integer(2));
+        assert_eq!(
+            ratio(-7, 2),
+            Literal::Rational(BigRational::new((-7).into(), 2.into()))
+        );
+    }
+
+    #[test]
+    fn display_rational() {
+        let test_cases = [
+            (ratio(1, 8), "0.125"),
+            (ratio(-3, 10), "-0.3"),
+            (ratio(1, 3), "0.3333333333333333"),
+            (ratio(2, 3), "0.6666666666666667"),
+            (ratio(-1, 3_000_000_000_000_000_000), "0"),
+            (ratio(10_000_000_000_000_001, 10), "1000000000000000.1"),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(value.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn display_fraction() {
+        let test_cases = [
+            (ratio(-1, 3), "-1/3"),
+            (Literal::Integer(4), "4"),
+            (Literal::Float(0.5), "0.5"),
+        ];
+        for (value, expected) in test_cases {
+            let value = Literal::Formatted(Box::new(value), Format::Fraction);
+            assert_eq!(value.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn display_bases() {
+        let test_cases = [
+            (Literal::Integer(255), Format::Hexadecimal, "0xFF"),
+            (Literal::Integer(-10), Format::Binary, "-0b1010"),
+            (Literal::Integer(511), Format::Octal, "0o777"),
+            (Literal::Integer(0), Format::Hexadecimal, "0x0"),
+            (ratio(1, 2), Format::Hexadecimal, "0.5"),
+        ];
+        for (value, format, expected) in test_cases {
+            let value = Literal::Formatted(Box::new(value), format);
+            assert_eq!(value.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn booleans() {
+        assert_eq!(Literal::Boolean(true).to_string(), "true");
+        assert_eq!(Literal::Boolean(false).is_truthy(), Some(false));
+        assert_eq!(ratio(1, 3).is_truthy(), Some(true));
+        assert_eq!(Literal::Integer(0).is_truthy(), Some(false));
+        assert_eq!(Literal::Time(NaiveTime::MIN).is_truthy(), None);
+    }
+
+    #[test]
+    fn display_money() {
+        let usd = Currency::find("USD").unwrap();
+        let jpy = Currency::find("JPY").unwrap();
+        let test_cases = [
+            (Literal::money(ratio(100, 3), usd), "33.33 USD"),
+            (Literal::money(ratio(5, 2), usd), "2.5 USD"),
+            (Literal::money(Literal::Float(-0.125), usd), "-0.13 USD"),
+            (Literal::money(ratio(1001, 2), jpy), "501 JPY"),
+            (
+                Literal::money(
+                    Literal::Formatted(Box::new(ratio(1, 3)), Format::Fraction),
+                    usd,
+                ),
+                "1/3 USD",
+            ),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(value.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn display_dates() {
+        let date = NaiveDate::from_ymd_opt(2026, 12, 24).unwrap();
+        let time = NaiveTime::from_hms_opt(9, 5, 0).unwrap();
+        let test_cases = [
+            (Literal::Date(date), "2026-12-24"),
+            (Literal::Time(time), "09:05"),
+            (
+                Literal::Time(NaiveTime::from_hms_opt(23, 59, 30).unwrap()),
+                "23:59:30",
+            ),
+            (
+                Literal::DateTime(date.and_time(time), None),
+                "2026-12-24 09:05",
+            ),
+            (
+                Literal::DateTime(date.and_time(time), FixedOffset::east_opt(9 * 3600)),
+                "2026-12-24 09:05 UTC+9",
+            ),
+            (
+                Literal::DateTime(date.and_time(time), FixedOffset::west_opt(5 * 3600 + 1800)),
+                "2026-12-24 09:05 UTC-5:30",
+            ),
+            (
+                Literal::DateTime(date.and_time(time), FixedOffset::east_opt(0)),
+                "2026-12-24 09:05 UTC",
+            ),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(value.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn display_complex() {
+        let test_cases = [
+            (Complex64::new(0.0, 1.0), "i"),
+            (Complex64::new(0.0, -2.5), "-2.5i"),
+            (Complex64::new(3.0, 4.0), "3 + 4i"),
+            (Complex64::new(-1.5, -1.0), "-1.5 - i"),
+            (Complex64::new(2.0, 0.0), "2"),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(Literal::from(value).to_string(), expected);
+        }
+    }
 }
@@ -46,6 +46,46 @@ pub enum FunctionName {
     // Comparisons
     Max,
     Min,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    // Logic
+    And,
+    Or,
+    Not,
+    /// Only the branch that is taken is evaluated
+    If,
+    // Formatting
+    Fraction,
+    ToHex,
+    ToBinary,
+    ToOctal,
+    // Complex numbers
+    RealPart,
+    ImaginaryPart,
+    Argument,
+    Conjugate,
+    // Percentages
+    Percent,
+    PercentOff,
+    PercentOn,
+    AsPercentOf,
+    // Bitwise
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
+    PopCount,
+    Bit,
+    /// `u8` etc.
+    Unsigned(u32),
+    /// `i32` etc.
+    Signed(u32),
 }
 
 impl FunctionName {
@@ -74,7 +114,21 @@ impl FunctionName {
             | Self::ArcTan
             | Self::ArcSinH
             | Self::ArcCosH
-            | Self::ArcTanH => 1,
+            | Self::ArcTanH
+            | Self::Fraction
+            | Self::ToHex
+            | Self::ToBinary
+            | Self::ToOctal
+            | Self::RealPart
+            | Self::ImaginaryPart
+            | Self::Argument
+            | Self::Conjugate
+            | Self::Percent
+            | Self::BitNot
+            | Self::PopCount
+            | Self::Unsigned(_)
+            | Self::Signed(_)
+            | Self::Not => 1,
             Self::Max
             | Self::Min
             | Self::Add
@@ -87,7 +141,25 @@ impl FunctionName {
             | Self::CeilPrec
             | Self::FloorPrec
             | Self::RoundPrec
-            | Self::TruncPrec => 2,
+            | Self::TruncPrec
+            | Self::PercentOff
+            | Self::PercentOn
+            | Self::AsPercentOf
+            | Self::BitAnd
+            | Self::BitOr
+            | Self::BitXor
+            | Self::ShiftLeft
+            | Self::ShiftRight
+            | Self::Bit
+            | Self::Less
+            | Self::LessEqual
+            | Self::Greater
+            | Self::GreaterEqual
+            | Self::Equal
+            | Self::NotEqual
+            | Self::And
+            | Self::Or => 2,
+            Self::If => 3,
         }
     }
 }
@@ -102,6 +174,13 @@ impl FromStr for FunctionName {
             "neg" | "negate" => Ok(Self::Negate),
             "min" | "minimum" => Ok(Self::Min),
             "max" | "maximum" => Ok(Self::Max),
+            "lt" => Ok(Self::Less),
+            "le" => Ok(Self::LessEqual),
+            "gt" => Ok(Self::Greater),
+            "ge" => Ok(Self::GreaterEqual),
+            "eq" => Ok(Self::Equal),
+            "ne" => Ok(Self::NotEqual),
+            "if" => Ok(Self::If),
             "add" => Ok(Self::Add),
             "sub" | "subtract" => Ok(Self::Subtract),
             "mul" | "multiply" => Ok(Self::Multiply),
@@ -130,6 +209,31 @@ impl FromStr for FunctionName {
             "floor" => Ok(Self::Floor),
             "round" => Ok(Self::Round),
             "trunc" => Ok(Self::Trunc),
+            "frac" | "fraction" => Ok(Self::Fraction),
+            "to_hex" | "hex" => Ok(Self::ToHex),
+            "to_bin" | "bin" => Ok(Self::ToBinary),
+            "to_oct" | "oct" => Ok(Self::ToOctal),
+            "re" | "real" => Ok(Self::RealPart),
+            "im" | "imag" => Ok(Self::ImaginaryPart),
+            "arg" => Ok(Self::Argument),
+            "conj" | "conjugate" => Ok(Self::Conjugate),
+            "percent" => Ok(Self::Percent),
+            "bitand" => Ok(Self::BitAnd),
+            "bitor" => Ok(Self::BitOr),
+            "bitxor" => Ok(Self::BitXor),
+            "bitnot" => Ok(Self::BitNot),
+            "shl" => Ok(Self::ShiftLeft),
+            "shr" => Ok(Self::ShiftRight),
+            "popcount" => Ok(Self::PopCount),
+            "bit" => Ok(Self::Bit),
+            "u8" => Ok(Self::Unsigned(8)),
+            "u16" => Ok(Self::Unsigned(16)),
+            "u32" => Ok(Self::Unsigned(32)),
+            "u64" => Ok(Self::Unsigned(64)),
+            "i8" => Ok(Self::Signed(8)),
+            "i16" => Ok(Self::Signed(16)),
+            "i32" => Ok(Self::Signed(32)),
+            "i64" => Ok(Self::Signed(64)),
             _ => Err(CommonError::UnknownFunctionName(arg.to_owned())),
         }
     }
@@ -144,6 +248,16 @@ impl fmt::Display for FunctionName {
             Self::Negate => write!(fmt, "negate"),
             Self::Max => write!(fmt, "max"),
             Self::Min => write!(fmt, "min"),
+            Self::Less => write!(fmt, "lt"),
+            Self::LessEqual => write!(fmt, "le"),
+            Self::Greater => write!(fmt, "gt"),
+            Self::GreaterEqual => write!(fmt, "ge"),
+            Self::Equal => write!(fmt, "eq"),
+            Self::NotEqual => write!(fmt, "ne"),
+            Self::And => write!(fmt, "and"),
+            Self::Or => write!(fmt, "or"),
+            Self::Not => write!(fmt, "not"),
+            Self::If => write!(fmt, "if"),
             Self::Add => write!(fmt, "add"),
             Self::Subtract => write!(fmt, "sub"),
             Self::Multiply => write!(fmt, "mul"),
@@ -175,6 +289,28 @@ impl fmt::Display for FunctionName {
             Self::RoundPrec => write!(fmt, "round with precision"),
             Self::Trunc => write!(fmt, "trunc"),
             Self::TruncPrec => write!(fmt, "trunc with precision"),
+            Self::Fraction => write!(fmt, "frac"),
+            Self::ToHex => write!(fmt, "to_hex"),
+            Self::ToBinary => write!(fmt, "to_bin"),
+            Self::ToOctal => write!(fmt, "to_oct"),
+            Self::RealPart => write!(fmt, "re"),
+            Self::ImaginaryPart => write!(fmt, "im"),
+            Self::Argument => write!(fmt, "arg"),
+            Self::Conjugate => write!(fmt, "conj"),
+            Self::Percent => write!(fmt, "percent"),
+            Self::PercentOff => write!(fmt, "off"),
+            Self::PercentOn => write!(fmt, "on"),
+            Self::AsPercentOf => write!(fmt, "as % of"),
+            Self::BitAnd => write!(fmt, "bitand"),
+            Self::BitOr => write!(fmt, "bitor"),
+            Self::BitXor => write!(fmt, "bitxor"),
+            Self::BitNot => write!(fmt, "bitnot"),
+            Self::ShiftLeft => write!(fmt, "shl"),
+            Self::ShiftRight => write!(fmt, "shr"),
+            Self::PopCount => write!(fmt, "popcount"),
+            Self::Bit => write!(fmt, "bit"),
+            Self::Unsigned(bits) => write!(fmt, "u{bits}"),
+            Self::Signed(bits) => write!(fmt, "i{bits}"),
         }
     }
 }
@@ -1,19 +1,35 @@
+use std::path::Path;
+
 use errors::CommonResult;
 
+pub mod currency;
 pub mod errors;
 pub mod expression;
 pub mod function_name;
+pub mod span;
+pub mod unit;
 
 pub trait RCalculator {
     fn get_all_lines(&self) -> (&[String], &[String]);
     fn get_line(&self, index: usize) -> CommonResult<(&str, &str)>;
+    fn get_line_kinds(&self) -> &[LineKind];
+    fn get_line_warnings(&self) -> &[Option<String>];
+    fn get_variables(&self) -> &[Variable];
     fn update_line(&mut self, actions: Vec<EditAction>);
+    fn undo(&mut self) -> Option<EditPosition>;
+    fn redo(&mut self) -> Option<EditPosition>;
     fn get_selection(&self, selection_start: EditPosition, selection_end: EditPosition) -> String;
     fn get_word_boundaries(
         &self,
         origin: EditPosition,
         priority: BoundaryPriority,
     ) -> Option<(EditPosition, EditPosition)>;
+    fn load(&mut self, path: &Path) -> CommonResult<()>;
+    fn save(&mut self, path: &Path) -> CommonResult<()>;
+    fn file_path(&self) -> Option<&Path>;
+    fn has_recovered_session(&self) -> bool;
+    fn restore_recovered_session(&mut self);
+    fn dismiss_recovered_session(&mut self);
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -28,6 +44,25 @@ impl EditPosition {
     }
 }
 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LineKind {
+    #[default]
+    Blank,
+    /// Like `Groceries:`
+    Heading,
+    Comment,
+    /// The byte offset of the comment after it, if there is one
+    Expression(Option<usize>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    /// The last line that defines it
+    pub line: usize,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum BoundaryPriority {
     None,
@@ -0,0 +1,456 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Pow, Zero};
+
+/// The exponents of length, mass, time, temperature, electric current and data
+pub type Dimensions = [i32; 6];
+
+const fn dims(
+    length: i32,
+    mass: i32,
+    time: i32,
+    temperature: i32,
+    current: i32,
+    data: i32,
+) -> Dimensions {
+    [length, mass, time, temperature, current, data]
+}
+
+const LENGTH: Dimensions = dims(1, 0, 0, 0, 0, 0);
+const AREA: Dimensions = dims(2, 0, 0, 0, 0, 0);
+const VOLUME: Dimensions = dims(3, 0, 0, 0, 0, 0);
+const MASS: Dimensions = dims(0, 1, 0, 0, 0, 0);
+const TIME: Dimensions = dims(0, 0, 1, 0, 0, 0);
+const FREQUENCY: Dimensions = dims(0, 0, -1, 0, 0, 0);
+const SPEED: Dimensions = dims(1, 0, -1, 0, 0, 0);
+const FORCE: Dimensions = dims(1, 1, -2, 0, 0, 0);
+const PRESSURE: Dimensions = dims(-1, 1, -2, 0, 0, 0);
+const ENERGY: Dimensions = dims(2, 1, -2, 0, 0, 0);
+const POWER: Dimensions = dims(2, 1, -3, 0, 0, 0);
+const TEMPERATURE: Dimensions = dims(0, 0, 0, 1, 0, 0);
+const CURRENT: Dimensions = dims(0, 0, 0, 0, 1, 0);
+const VOLTAGE: Dimensions = dims(2, 1, -3, 0, -1, 0);
+const DATA: Dimensions = dims(0, 0, 0, 0, 0, 1);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct NamedUnit {
+    pub symbol: &'static str,
+    aliases: &'static [&'static str],
+    dimensions: Dimensions,
+    factor: (i128, i128),
+    /// Added before scaling to the base unit, for temperatures like `°C`
+    offset: (i128, i128),
+    /// Shown instead of the symbol for amounts other than one, for symbols that are words
+    plural: Option<&'static str>,
+}
+
+const fn unit(
+    symbol: &'static str,
+    aliases: &'static [&'static str],
+    dimensions: Dimensions,
+    factor: (i128, i128),
+) -> NamedUnit {
+    NamedUnit {
+        symbol,
+        aliases,
+        dimensions,
+        factor,
+        offset: (0, 1),
+        plural: None,
+    }
+}
+
+const fn word(
+    symbol: &'static str,
+    plural: &'static str,
+    aliases: &'static [&'static str],
+    dimensions: Dimensions,
+    factor: (i128, i128),
+) -> NamedUnit {
+    NamedUnit {
+        plural: Some(plural),
+        ..unit(symbol, aliases, dimensions, factor)
+    }
+}
+
+/// The base units are the SI units, and bytes for data
+static UNITS: &[NamedUnit] = &[
+    // Length
+    unit("m", &["meter", "meters", "metre", "metres"], LENGTH, (1, 1)),
+    unit("km", &["kilometer", "kilometers"], LENGTH, (1_000, 1)),
+    unit("cm", &["centimeter", "centimeters"], LENGTH, (1, 100)),
+    unit("mm", &["millimeter", "millimeters"], LENGTH, (1, 1_000)),
+    unit("um", &["micrometer", "micrometers"], LENGTH, (1, 1_000_000)),
+    unit(
+        "nm",
+        &["nanometer", "nanometers"],
+        LENGTH,
+        (1, 1_000_000_000),
+    ),
+    unit("mi", &["mile", "miles"], LENGTH, (1_609_344, 1_000)),
+    unit("yd", &["yard", "yards"], LENGTH, (9_144, 10_000)),
+    unit("ft", &["foot", "feet"], LENGTH, (3_048, 10_000)),
+    word("inch", "inches", &[], LENGTH, (254, 10_000)),
+    // Area
+    unit("ha", &["hectare", "hectares"], AREA, (10_000, 1)),
+    word("acre", "acres", &[], AREA, (40_468_564_224, 10_000_000)),
+    // Volume
+    unit(
+        "l",
+        &["L", "liter", "liters", "litre", "litres"],
+        VOLUME,
+        (1, 1_000),
+    ),
+    unit(
+        "ml",
+        &["mL", "milliliter", "milliliters"],
+        VOLUME,
+        (1, 1_000_000),
+    ),
+    unit(
+        "gal",
+        &["gallon", "gallons"],
+        VOLUME,
+        (3_785_411_784, 1_000_000_000_000),
+    ),
+    // Mass
+    unit("kg", &["kilogram", "kilograms"], MASS, (1, 1)),
+    unit("g", &["gram", "grams"], MASS, (1, 1_000)),
+    unit("mg", &["milligram", "milligrams"], MASS, (1, 1_000_000)),
+    unit("t", &["tonne", "tonnes"], MASS, (1_000, 1)),
+    unit(
+        "lb",
+        &["lbs", "pound", "pounds"],
+        MASS,
+        (45_359_237, 100_000_000),
+    ),
+    unit(
+        "oz",
+        &["ounce", "ounces"],
+        MASS,
+        (45_359_237, 1_600_000_000),
+    ),
+    // Time
+    unit("s", &["sec", "second", "seconds"], TIME, (1, 1)),
+    unit("ms", &["millisecond", "milliseconds"], TIME, (1, 1_000)),
+    unit("us", &["microsecond", "microseconds"], TIME, (1, 1_000_000)),
+    unit(
+        "ns",
+        &["nanosecond", "nanoseconds"],
+        TIME,
+        (1, 1_000_000_000),
+    ),
+    unit("min", &["minute", "minutes"], TIME, (60, 1)),
+    unit("h", &["hr", "hour", "hours"], TIME, (3_600, 1)),
+    word("day", "days", &[], TIME, (86_400, 1)),
+    word("week", "weeks", &[], TIME, (604_800, 1)),
+    // An average month, a twelfth of a year. Added to a date it's a calendar month.
+    word("month", "months", &[], TIME, (2_629_800, 1)),
+    word("year", "years", &[], TIME, (31_557_600, 1)),
+    // Frequency
+    unit("Hz", &["hertz"], FREQUENCY, (1, 1)),
+    unit("kHz", &[], FREQUENCY, (1_000, 1)),
+    unit("MHz", &[], FREQUENCY, (1_000_000, 1)),
+    unit("GHz", &[], FREQUENCY, (1_000_000_000, 1)),
+    // Speed
+    unit("mph", &[], SPEED, (44_704, 100_000)),
+    unit("kph", &["kmh"], SPEED, (5, 18)),
+    word("knot", "knots", &["kn"], SPEED, (463, 900)),
+    // Force, pressure, energy and power
+    unit("N", &["newton", "newtons"], FORCE, (1, 1)),
+    unit("Pa", &["pascal", "pascals"], PRESSURE, (1, 1)),
+    unit("kPa", &[], PRESSURE, (1_000, 1)),
+    unit("bar", &[], PRESSURE, (100_000, 1)),
+    unit("atm", &[], PRESSURE, (101_325, 1)),
+    unit("psi", &[], PRESSURE, (44_482_216_152_605, 6_451_600_000)),
+    unit("J", &["joule", "joules"], ENERGY, (1, 1)),
+    unit("kJ", &[], ENERGY, (1_000, 1)),
+    unit("cal", &["calorie", "calories"], ENERGY, (4_184, 1_000)),
+    unit("kcal", &[], ENERGY, (4_184, 1)),
+    unit("Wh", &[], ENERGY, (3_600, 1)),
+    unit("kWh", &[], ENERGY, (3_600_000, 1)),
+    unit(
+        "eV",
+        &[],
+        ENERGY,
+        (1_602_176_634, 10_000_000_000_000_000_000_000_000_000),
+    ),
+    unit("W", &["watt", "watts"], POWER, (1, 1)),
+    unit("kW", &[], POWER, (1_000, 1)),
+    unit("MW", &[], POWER, (1_000_000, 1)),
+    unit(
+        "hp",
+        &["horsepower"],
+        POWER,
+        (74_569_987_158_227_022, 100_000_000_000_000),
+    ),
+    // Temperature
+    unit("K", &["kelvin"], TEMPERATURE, (1, 1)),
+    NamedUnit {
+        symbol: "°C",
+        aliases: &["degC", "celsius"],
+        dimensions: TEMPERATURE,
+        factor: (1, 1),
+        offset: (27_315, 100),
+        plural: None,
+    },
+    NamedUnit {
+        symbol: "°F",
+        aliases: &["degF", "fahrenheit"],
+        dimensions: TEMPERATURE,
+        factor: (5, 9),
+        offset: (45_967, 100),
+        plural: None,
+    },
+    // Electricity
+    unit("A", &["amp", "amps", "ampere", "amperes"], CURRENT, (1, 1)),
+    unit("mA", &[], CURRENT, (1, 1_000)),
+    unit("V", &["volt", "volts"], VOLTAGE, (1, 1)),
+    // Data
+    unit("B", &["byte", "bytes"], DATA, (1, 1)),
+    word("bit", "bits", &[], DATA, (1, 8)),
+    unit("kB", &[], DATA, (1_000, 1)),
+    unit("MB", &[], DATA, (1_000_000, 1)),
+    unit("GB", &[], DATA, (1_000_000_000, 1)),
+    unit("TB", &[], DATA, (1_000_000_000_000, 1)),
+    unit("KiB", &[], DATA, (1 << 10, 1)),
+    unit("MiB", &[], DATA, (1 << 20, 1)),
+    unit("GiB", &[], DATA, (1 << 30, 1)),
+    unit("TiB", &[], DATA, (1 << 40, 1)),
+    unit("kbit", &[], DATA, (125, 1)),
+    unit("Mbit", &[], DATA, (125_000, 1)),
+    unit("Gbit", &[], DATA, (125_000_000, 1)),
+];
+
+fn ratio((numer, denom): (i128, i128)) -> BigRational {
+    BigRational::new(BigInt::from(numer), BigInt::from(denom))
+}
+
+impl NamedUnit {
+    pub fn find(name: &str) -> Option<&'static Self> {
+        UNITS.iter().find(|unit| {
+            unit.symbol == name || unit.plural == Some(name) || unit.aliases.contains(&name)
+        })
+    }
+
+    fn factor(&self) -> BigRational {
+        ratio(self.factor)
+    }
+}
+
+/// A product of named units raised to integer powers, like `m/s^2`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Unit {
+    terms: Vec<(&'static NamedUnit, i32)>,
+}
+
+impl Unit {
+    pub fn new(unit: &'static NamedUnit, power: i32) -> Self {
+        Self {
+            terms: vec![(unit, power)],
+        }
+    }
+
+    pub fn with_term(mut self, unit: &'static NamedUnit, power: i32) -> Self {
+        match self
+            .terms
+            .iter_mut()
+            .find(|(existing, _)| *existing == unit)
+        {
+            Some((_, existing_power)) => *existing_power += power,
+            None => self.terms.push((unit, power)),
+        }
+        self.terms.retain(|(_, power)| *power != 0);
+        self
+    }
+
+    pub fn named(&self) -> Option<&'static NamedUnit> {
+        match self.terms.as_slice() {
+            [(unit, 1)] => Some(unit),
+            _ => None,
+        }
+    }
+
+    pub fn plural(&self) -> Option<&'static str> {
+        self.named()?.plural
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        let mut dimensions = Dimensions::default();
+        for (unit, power) in self.terms.iter() {
+            for (total, dimension) in dimensions.iter_mut().zip(unit.dimensions) {
+                *total += dimension * power;
+            }
+        }
+        dimensions
+    }
+
+    pub fn factor(&self) -> BigRational {
+        self.terms
+            .iter()
+            .map(|(unit, power)| Pow::pow(unit.factor(), *power))
+            .fold(BigRational::one(), |acc, factor| acc * factor)
+    }
+
+    pub fn offset(&self) -> Option<BigRational> {
+        match self.terms.as_slice() {
+            [(unit, 1)] if unit.offset.0 != 0 => Some(ratio(unit.offset)),
+            _ => None,
+        }
+    }
+
+    pub fn pow(&self, exponent: i32) -> Self {
+        let terms = self
+            .terms
+            .iter()
+            .filter(|_| exponent != 0)
+            .map(|(unit, power)| (*unit, power * exponent))
+            .collect();
+        Self { terms }
+    }
+
+    pub fn root(&self, n: i32) -> Option<Self> {
+        let terms = self
+            .terms
+            .iter()
+            .map(|(unit, power)| (power % n == 0).then_some((*unit, power / n)))
+            .collect::<Option<_>>()?;
+        Some(Self { terms })
+    }
+
+    /// Also returns the factor for the value, units are converted to the left one so `km * m` is `km^2`
+    pub fn mul(&self, other: &Self) -> (Self, BigRational) {
+        let mut terms = self.terms.clone();
+        let mut factor = BigRational::one();
+        for (unit, power) in other.terms.iter() {
+            match terms
+                .iter_mut()
+                .find(|(existing, _)| existing.dimensions == unit.dimensions)
+            {
+                Some((existing, existing_power)) => {
+                    factor *= Pow::pow(unit.factor() / existing.factor(), *power);
+                    *existing_power += power;
+                }
+                None => terms.push((*unit, *power)),
+            }
+        }
+        terms.retain(|(_, power)| *power != 0);
+        let mut unit = Self { terms };
+        if unit.dimensions() == Dimensions::default() {
+            factor *= unit.factor();
+            unit = Self::default();
+        }
+        (unit, factor)
+    }
+
+    pub fn conversion_factor(&self, other: &Self) -> Option<BigRational> {
+        let other_factor = other.factor();
+        if self.dimensions() != other.dimensions() || other_factor.is_zero() {
+            return None;
+        }
+        Some(self.factor() / other_factor)
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format_term = |unit: &NamedUnit, power: i32| match power {
+            1 => unit.symbol.to_owned(),
+            power => format!("{}^{power}", unit.symbol),
+        };
+        let positive = self
+            .terms
+            .iter()
+            .filter(|(_, power)| *power > 0)
+            .map(|(unit, power)| format_term(unit, *power))
+            .collect::<Vec<_>>();
+        let negative = self.terms.iter().filter(|(_, power)| *power < 0);
+        if positive.is_empty() {
+            let negative = negative
+                .map(|(unit, power)| format_term(unit, *power))
+                .collect::<Vec<_>>();
+            return write!(f, "{}", negative.join("*"));
+        }
+        write!(f, "{}", positive.join("*"))?;
+        for (unit, power) in negative {
+            write!(f, "/{}", format_term(unit, -power))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(name: &str) -> Unit {
+        Unit::new(NamedUnit::find(name).unwrap(), 1)
+    }
+
+    #[test]
+    fn find() {
+        assert_eq!(NamedUnit::find("km").unwrap().symbol, "km");
+        assert_eq!(NamedUnit::find("feet").unwrap().symbol, "ft");
+        assert_eq!(NamedUnit::find("celsius").unwrap().symbol, "°C");
+        assert!(NamedUnit::find("KM").is_none());
+        assert!(NamedUnit::find("in").is_none());
+        assert_eq!(NamedUnit::find("weeks").unwrap().symbol, "week");
+        assert_eq!(unit("day").plural(), Some("days"));
+        assert_eq!(unit("h").plural(), None);
+        assert_eq!(unit("day").pow(2).plural(), None);
+    }
+
+    #[test]
+    fn display() {
+        let (speed, _) = unit("km").mul(&unit("h").pow(-1));
+        let (acceleration, _) = speed.mul(&unit("s").pow(-1));
+        let (force, _) = unit("kg").mul(&acceleration);
+        assert_eq!(speed.to_string(), "km/h");
+        assert_eq!(force.to_string(), "kg*km/h^2");
+        assert_eq!(unit("m").pow(2).to_string(), "m^2");
+        assert_eq!(unit("s").pow(-1).to_string(), "s^-1");
+    }
+
+    #[test]
+    fn multiplication_merges_dimensions() {
+        let (area, factor) = unit("km").mul(&unit("m"));
+        assert_eq!(area, unit("km").pow(2));
+        assert_eq!(factor, BigRational::new(1.into(), 1_000.into()));
+
+        let (ratio, factor) = unit("km").mul(&unit("m").pow(-1));
+        assert!(ratio.is_empty());
+        assert_eq!(factor, BigRational::from_integer(1_000.into()));
+
+        let (time, factor) = unit("GiB").mul(&unit("MB").pow(-1).mul(&unit("s")).0);
+        assert_eq!(time, unit("s"));
+        assert_eq!(
+            factor,
+            BigRational::new(1_073_741_824.into(), 1_000_000.into())
+        );
+    }
+
+    #[test]
+    fn conversion() {
+        let (mps, _) = unit("m").mul(&unit("s").pow(-1));
+        let factor = unit("mph").conversion_factor(&mps).unwrap();
+        assert_eq!(factor, BigRational::new(44_704.into(), 100_000.into()));
+        assert!(unit("m").conversion_factor(&unit("s")).is_none());
+        assert_eq!(
+            unit("°C").offset(),
+            Some(BigRational::new(27_315.into(), 100.into()))
+        );
+        assert_eq!(unit("K").offset(), None);
+    }
+
+    #[test]
+    fn roots() {
+        assert_eq!(unit("m").pow(2).root(2), Some(unit("m")));
+        assert_eq!(unit("m").root(2), None);
+    }
+}
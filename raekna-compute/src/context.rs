@@ -0,0 +1,30 @@
+use chrono::{DateTime, FixedOffset, Local, Timelike};
+use raekna_common::currency::ExchangeRates;
+
+/// What evaluating an expression can depend on besides the variables defined before it
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub rates: ExchangeRates,
+    /// Why the rates file couldn't be loaded, if it couldn't
+    pub rates_error: Option<String>,
+    pub clock: Clock,
+}
+
+/// Where `today` and `now` come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Clock {
+    #[default]
+    System,
+    /// For reproducible results
+    Fixed(DateTime<FixedOffset>),
+}
+
+impl Clock {
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        let now = match self {
+            Self::System => Local::now().fixed_offset(),
+            Self::Fixed(now) => *now,
+        };
+        now.with_nanosecond(0).unwrap_or(now)
+    }
+}
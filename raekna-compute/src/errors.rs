@@ -1,19 +1,233 @@
-use raekna_common::{expression::Literal, function_name::FunctionName};
+use raekna_common::{
+    currency::Currency, expression::Literal, function_name::FunctionName, unit::Unit,
+};
+
+use crate::evaluator::MAX_CALL_DEPTH;
 
 pub type ComputeResult<T> = Result<T, ComputeError>;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ComputeError {
     UnknownVariable(String),
     VariableNameTaken(String),
+    UnknownFunction(String),
+    /// A name that is only defined on the line with this number below
+    DefinedBelow(String, usize),
+    /// A definition that needs its own value through the names in between
+    CircularDefinition(String, Vec<String>),
+    FunctionNameTaken(String),
+    DuplicateParameter(String),
+    CallDepthExceeded(String),
     FunctionArgumentCount {
         function_name: String,
         expected_argument_count: usize,
         supplied_argument_count: usize,
     },
+    /// `prev` on the first line
+    NoLineAbove,
+    LineNotAbove(usize),
+    LineWithoutResult(usize),
+    RemovedLine,
+    NothingToAverage,
     ResultTooBig(FunctionName, Vec<Literal>),
     InvalidFactorialArgument(Literal),
-    InvalidSquareRoot(Literal),
     DivisionByZero,
     InvalidTruncatePrecision(Literal),
+    InvalidBaseArgument(Literal),
+    IntegerArgument(FunctionName),
+    NegativeBitCount(FunctionName),
+    BooleanArgument(FunctionName),
+    LogicArgument(FunctionName),
+    ConditionArgument(Literal),
+    ComplexArgument(FunctionName),
+    IncompatibleUnits(Unit, Unit),
+    UnitArgument(FunctionName),
+    /// `None` is a plain number
+    CurrencyMismatch(Option<&'static Currency>, Option<&'static Currency>),
+    CurrencyArgument(FunctionName),
+    MissingExchangeRate(&'static Currency),
+    RatesNotLoaded(&'static Currency, String),
+    MissingPercentage(FunctionName),
+    DateArgument(FunctionName),
+    ExpectedDuration(Literal),
+    DateConversion,
+    TimeZoneConversion,
+}
+
+impl std::fmt::Display for ComputeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::UnknownVariable(name) => write!(f, "unknown variable `{name}`"),
+            Self::VariableNameTaken(name) => write!(f, "`{name}` is a constant"),
+            Self::UnknownFunction(name) => write!(f, "unknown function `{name}`"),
+            Self::DefinedBelow(name, line) => write!(f, "`{name}` isn't defined until line {line}"),
+            Self::CircularDefinition(name, through) if through.is_empty() => {
+                write!(f, "`{name}` depends on itself")
+            }
+            Self::CircularDefinition(name, through) => {
+                let through = through
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "`{name}` depends on itself through {through}")
+            }
+            Self::FunctionNameTaken(name) => write!(f, "`{name}` is a built-in function"),
+            Self::DuplicateParameter(name) => write!(f, "parameter `{name}` is repeated"),
+            Self::CallDepthExceeded(name) => write!(
+                f,
+                "too many nested calls of `{name}`, at most {MAX_CALL_DEPTH} are allowed"
+            ),
+            Self::NoLineAbove => write!(f, "there is no line above"),
+            Self::LineNotAbove(line) => write!(f, "line {line} isn't above this line"),
+            Self::LineWithoutResult(line) => write!(f, "line {line} has no result"),
+            Self::RemovedLine => write!(f, "the referenced line was removed"),
+            Self::NothingToAverage => write!(f, "there are no results above to average"),
+            Self::FunctionArgumentCount {
+                function_name,
+                expected_argument_count,
+                supplied_argument_count,
+            } => write!(
+                f,
+                "`{function_name}` takes {expected_argument_count} {} but got {supplied_argument_count}",
+                if *expected_argument_count == 1 {
+                    "argument"
+                } else {
+                    "arguments"
+                }
+            ),
+            Self::ResultTooBig(function_name, _) => {
+                write!(f, "result of `{function_name}` is too big")
+            }
+            Self::InvalidFactorialArgument(_) => write!(f, "factorial of non-natural number"),
+            Self::DivisionByZero => write!(f, "division by zero"),
+            Self::InvalidTruncatePrecision(_) => write!(f, "trunc precision must be an integer"),
+            Self::InvalidBaseArgument(_) => {
+                write!(f, "only integers can be shown in another base")
+            }
+            Self::IntegerArgument(function_name) => {
+                write!(f, "`{function_name}` only supports integers")
+            }
+            Self::NegativeBitCount(function_name) => {
+                write!(f, "`{function_name}` needs a non-negative number of bits")
+            }
+            Self::BooleanArgument(function_name) => {
+                write!(f, "`{function_name}` doesn't support booleans")
+            }
+            Self::LogicArgument(function_name) => {
+                write!(f, "`{function_name}` needs booleans or plain numbers")
+            }
+            Self::ConditionArgument(value) => {
+                write!(f, "expected a condition like `x > 0` but got `{value}`")
+            }
+            Self::ComplexArgument(function_name) => {
+                write!(f, "`{function_name}` doesn't support complex numbers")
+            }
+            Self::IncompatibleUnits(left, right) => match (left.is_empty(), right.is_empty()) {
+                (true, _) => write!(f, "expected a value in `{right}` but got a plain number"),
+                (_, true) => write!(f, "expected a plain number but got a value in `{left}`"),
+                _ => write!(f, "can't convert `{left}` to `{right}`"),
+            },
+            Self::UnitArgument(function_name) => {
+                write!(f, "`{function_name}` doesn't support units")
+            }
+            Self::CurrencyMismatch(_, Some(expected)) => {
+                write!(f, "expected an amount in {expected} but got a plain number")
+            }
+            Self::CurrencyMismatch(actual, None) => match actual {
+                Some(actual) => write!(f, "expected a plain number but got an amount in {actual}"),
+                None => write!(f, "expected an amount but got a plain number"),
+            },
+            Self::CurrencyArgument(function_name) => {
+                write!(f, "`{function_name}` doesn't support currencies")
+            }
+            Self::MissingExchangeRate(currency) => {
+                write!(f, "no exchange rate for {currency}")
+            }
+            Self::RatesNotLoaded(currency, reason) => write!(
+                f,
+                "no exchange rate for {currency}, the rates file couldn't be loaded: {reason}"
+            ),
+            Self::MissingPercentage(function_name) => {
+                write!(f, "`{function_name}` needs a percentage on the left, like `10% {function_name} 120`")
+            }
+            Self::DateArgument(function_name) => {
+                write!(f, "`{function_name}` doesn't support dates and times")
+            }
+            Self::ExpectedDuration(value) => {
+                write!(f, "expected a duration like `3 days` but got `{value}`")
+            }
+            Self::DateConversion => {
+                write!(f, "dates and times can only be converted to a time zone")
+            }
+            Self::TimeZoneConversion => {
+                write!(f, "only dates and times can be converted to a time zone")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let test_cases = [
+            (
+                ComputeError::UnknownVariable("foo".to_owned()),
+                "unknown variable `foo`",
+            ),
+            (
+                ComputeError::FunctionArgumentCount {
+                    function_name: "max".to_owned(),
+                    expected_argument_count: 2,
+                    supplied_argument_count: 1,
+                },
+                "`max` takes 2 arguments but got 1",
+            ),
+            (
+                ComputeError::ComplexArgument(FunctionName::Max),
+                "`max` doesn't support complex numbers",
+            ),
+            (ComputeError::DivisionByZero, "division by zero"),
+            (
+                ComputeError::DefinedBelow("rate".to_owned(), 7),
+                "`rate` isn't defined until line 7",
+            ),
+            (
+                ComputeError::CircularDefinition(
+                    "a".to_owned(),
+                    vec!["b".to_owned(), "c".to_owned()],
+                ),
+                "`a` depends on itself through `b`, `c`",
+            ),
+            (
+                ComputeError::LineNotAbove(4),
+                "line 4 isn't above this line",
+            ),
+            (
+                ComputeError::BooleanArgument(FunctionName::Add),
+                "`add` doesn't support booleans",
+            ),
+            (
+                ComputeError::MissingExchangeRate(Currency::find("SEK").unwrap()),
+                "no exchange rate for SEK",
+            ),
+            (
+                ComputeError::RatesNotLoaded(
+                    Currency::find("SEK").unwrap(),
+                    "line 3 isn't a valid exchange rate".to_owned(),
+                ),
+                "no exchange rate for SEK, the rates file couldn't be loaded: line 3 isn't a valid exchange rate",
+            ),
+            (
+                ComputeError::ExpectedDuration(Literal::Integer(3)),
+                "expected a duration like `3 days` but got `3`",
+            ),
+        ];
+        for (error, expected) in test_cases {
+            assert_eq!(error.to_string(), expected);
+        }
+    }
 }
@@ -1,61 +1,184 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr};
 
-use raekna_common::expression::{Expression, Literal};
+use raekna_common::{
+    expression::{Expression, LineReference, Literal},
+    function_name::FunctionName,
+};
 
 use crate::{
+    context::Context,
     errors::{ComputeError, ComputeResult},
-    ops::{constants, evaluate_fn},
+    ops::{aggregates, check_argument_count, constants, convert, evaluate_fn},
 };
 
+pub const MAX_CALL_DEPTH: usize = 100;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserFunction {
+    pub parameters: Vec<String>,
+    pub body: Expression,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinesAbove<'a> {
+    pub results: &'a [Option<Literal>],
+    /// Where the block that aggregates like `sum` use starts
+    pub block_start: usize,
+}
+
+struct Scope<'a> {
+    variables: &'a HashMap<String, Literal>,
+    functions: &'a HashMap<String, UserFunction>,
+    lines: LinesAbove<'a>,
+    /// Shadow variables
+    arguments: HashMap<&'a str, Literal>,
+    depth: usize,
+    context: &'a Context,
+}
+
+/// Evaluates a line and defines its variable or function, function definitions have no value
 pub fn evaluate(
     expression: Expression,
     variables: &mut HashMap<String, Literal>,
-) -> ComputeResult<Literal> {
+    functions: &mut HashMap<String, UserFunction>,
+    lines: LinesAbove,
+    context: &Context,
+) -> ComputeResult<Option<Literal>> {
+    let scope = |variables, functions| Scope {
+        variables,
+        functions,
+        lines,
+        arguments: HashMap::new(),
+        depth: 0,
+        context,
+    };
     match expression {
-        Expression::Variable(name, expr) => match constants::evaluate(&name) {
-            Some(_) => Err(ComputeError::VariableNameTaken(name)),
-            None => {
-                let res = evaluate_to_literal(&expr, variables)?;
-                variables.insert(name, res);
-                Ok(res)
+        Expression::Variable(name, _) if constants::is_reserved(&name) => {
+            Err(ComputeError::VariableNameTaken(name))
+        }
+        Expression::Variable(name, expr) => {
+            let res = evaluate_to_literal(&expr, &scope(variables, functions))?;
+            variables.insert(name, res.clone());
+            Ok(Some(res))
+        }
+        Expression::FunctionDefinition(name, parameters, body) => {
+            if FunctionName::from_str(&name).is_ok() {
+                return Err(ComputeError::FunctionNameTaken(name));
+            }
+            for (i, parameter) in parameters.iter().enumerate() {
+                if constants::is_reserved(parameter) {
+                    return Err(ComputeError::VariableNameTaken(parameter.clone()));
+                }
+                if parameters[..i].contains(parameter) {
+                    return Err(ComputeError::DuplicateParameter(parameter.clone()));
+                }
             }
-        },
+            let body = *body;
+            functions.insert(name, UserFunction { parameters, body });
+            Ok(None)
+        }
         expr => {
-            let res = evaluate_to_literal(&expr, variables)?;
-            Ok(res)
+            let res = evaluate_to_literal(&expr, &scope(variables, functions))?;
+            Ok(Some(res))
         }
     }
 }
 
-fn evaluate_to_literal(
-    expression: &Expression,
-    variables: &HashMap<String, Literal>,
-) -> ComputeResult<Literal> {
+fn evaluate_to_literal(expression: &Expression, scope: &Scope) -> ComputeResult<Literal> {
     match expression {
-        Expression::Literal(literal) => Ok(*literal),
-        Expression::Variable(_, _) => unreachable!(),
+        Expression::Literal(literal) => Ok(literal.clone()),
+        Expression::Variable(_, _) | Expression::FunctionDefinition(_, _, _) => unreachable!(),
         Expression::VariableRef(var_name) => {
-            let value = constants::evaluate(var_name).map(Ok).unwrap_or_else(|| {
-                variables
-                    .get(var_name.as_str())
-                    .copied()
-                    .ok_or_else(|| ComputeError::UnknownVariable(var_name.clone()))
-            })?;
-            Ok(value)
+            let value = scope
+                .arguments
+                .get(var_name.as_str())
+                .or_else(|| scope.variables.get(var_name.as_str()))
+                .cloned()
+                .or_else(|| constants::evaluate(var_name, scope.context));
+            if let Some(value) = value {
+                return Ok(value);
+            }
+            // Variables can be named like aggregates, e.g. `total: sum`
+            let block = &scope.lines.results[scope.lines.block_start..];
+            aggregates::evaluate(var_name, block, scope.context)
+                .unwrap_or_else(|| Err(ComputeError::UnknownVariable(var_name.clone())))
+        }
+        Expression::LineRef(reference) => {
+            let results = scope.lines.results;
+            let line = match *reference {
+                LineReference::Previous if results.is_empty() => {
+                    return Err(ComputeError::NoLineAbove)
+                }
+                LineReference::Previous => results.len(),
+                LineReference::Line(line) => line,
+                LineReference::Removed => return Err(ComputeError::RemovedLine),
+            };
+            match line.checked_sub(1).and_then(|index| results.get(index)) {
+                Some(Some(value)) => Ok(value.clone()),
+                Some(None) => Err(ComputeError::LineWithoutResult(line)),
+                None => Err(ComputeError::LineNotAbove(line)),
+            }
+        }
+        // Only the branch that is taken is evaluated, so the other one is free to fail, like a
+        // division by zero that the condition guards against
+        Expression::Function(FunctionName::If, args) => {
+            check_argument_count(FunctionName::If, args.len())?;
+            let condition = evaluate_to_literal(&args[0], scope)?;
+            let branch = match condition.is_truthy() {
+                Some(true) => &args[1],
+                Some(false) => &args[2],
+                None => return Err(ComputeError::ConditionArgument(condition)),
+            };
+            evaluate_to_literal(branch, scope)
         }
         Expression::Function(fn_name, args) => {
             let args = args
                 .iter()
-                .map(|a| evaluate_to_literal(a, variables))
+                .map(|a| evaluate_to_literal(a, scope))
+                .collect::<ComputeResult<Vec<_>>>()?;
+            evaluate_fn(*fn_name, args, scope.context)
+        }
+        Expression::UserFunction(name, args) => {
+            let function = scope
+                .functions
+                .get(name)
+                .ok_or_else(|| ComputeError::UnknownFunction(name.clone()))?;
+            if args.len() != function.parameters.len() {
+                return Err(ComputeError::FunctionArgumentCount {
+                    function_name: name.clone(),
+                    expected_argument_count: function.parameters.len(),
+                    supplied_argument_count: args.len(),
+                });
+            }
+            if scope.depth == MAX_CALL_DEPTH {
+                return Err(ComputeError::CallDepthExceeded(name.clone()));
+            }
+            let args = args
+                .iter()
+                .map(|a| evaluate_to_literal(a, scope))
                 .collect::<ComputeResult<Vec<_>>>()?;
-            evaluate_fn(*fn_name, args)
+            let scope = Scope {
+                arguments: function
+                    .parameters
+                    .iter()
+                    .map(String::as_str)
+                    .zip(args)
+                    .collect(),
+                depth: scope.depth + 1,
+                ..*scope
+            };
+            evaluate_to_literal(&function.body, &scope)
+        }
+        Expression::Conversion(expr, target) => {
+            let value = evaluate_to_literal(expr, scope)?;
+            convert(value, target, scope.context)
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use raekna_common::function_name::FunctionName;
+    use num_complex::Complex64;
 
     use super::*;
 
@@ -68,9 +191,16 @@ mod tests {
         let expression = Expression::VariableRef("var2".to_owned());
 
         let expected = Literal::Integer(10);
-        let actual = evaluate(expression, &mut variables).unwrap();
+        let actual = evaluate(
+            expression,
+            &mut variables,
+            &mut HashMap::new(),
+            LinesAbove::default(),
+            &Context::default(),
+        )
+        .unwrap();
 
-        assert_eq!(actual, expected);
+        assert_eq!(actual, Some(expected));
     }
 
     #[test]
@@ -81,7 +211,14 @@ mod tests {
 
         let expression = Expression::VariableRef("var2".to_owned());
 
-        evaluate(expression, &mut variables).unwrap();
+        evaluate(
+            expression,
+            &mut variables,
+            &mut HashMap::new(),
+            LinesAbove::default(),
+            &Context::default(),
+        )
+        .unwrap();
     }
 
     #[test]
@@ -97,7 +234,14 @@ mod tests {
             )),
         );
 
-        evaluate(expression, &mut variables).unwrap();
+        evaluate(
+            expression,
+            &mut variables,
+            &mut HashMap::new(),
+            LinesAbove::default(),
+            &Context::default(),
+        )
+        .unwrap();
     }
 
     #[test]
@@ -125,9 +269,16 @@ mod tests {
         );
 
         let expected = Literal::Integer(3);
-        let actual = evaluate(expression, &mut variables).unwrap();
+        let actual = evaluate(
+            expression,
+            &mut variables,
+            &mut HashMap::new(),
+            LinesAbove::default(),
+            &Context::default(),
+        )
+        .unwrap();
 
-        assert_eq!(actual, expected);
+        assert_eq!(actual, Some(expected));
     }
 
     #[test]
@@ -135,11 +286,340 @@ mod tests {
         let mut variables = HashMap::new();
 
         let literal = Literal::Float(12.345);
-        let expression = Expression::Literal(literal);
+        let expression = Expression::Literal(literal.clone());
 
         let expected = literal;
-        let actual = evaluate(expression, &mut variables).unwrap();
+        let actual = evaluate(
+            expression,
+            &mut variables,
+            &mut HashMap::new(),
+            LinesAbove::default(),
+            &Context::default(),
+        )
+        .unwrap();
+
+        assert_eq!(actual, Some(expected));
+    }
+
+    #[test]
+    fn test_only_the_taken_branch_is_evaluated() {
+        let mut variables = HashMap::new();
+        variables.insert("x".to_owned(), Literal::Integer(0));
+
+        let division = Expression::Function(
+            FunctionName::Divide,
+            vec![
+                Expression::Literal(Literal::Integer(1)),
+                Expression::VariableRef("x".to_owned()),
+            ],
+        );
+        let expression = |condition: Literal| {
+            Expression::Function(
+                FunctionName::If,
+                vec![
+                    Expression::Literal(condition),
+                    division.clone(),
+                    Expression::Literal(Literal::Integer(0)),
+                ],
+            )
+        };
+
+        let actual = evaluate(
+            expression(Literal::Boolean(false)),
+            &mut variables,
+            &mut HashMap::new(),
+            LinesAbove::default(),
+            &Context::default(),
+        );
+        assert_eq!(actual, Ok(Some(Literal::Integer(0))));
+
+        let actual = evaluate(
+            expression(Literal::Integer(2)),
+            &mut variables,
+            &mut HashMap::new(),
+            LinesAbove::default(),
+            &Context::default(),
+        );
+        assert_eq!(actual, Err(ComputeError::DivisionByZero));
+
+        let actual = evaluate(
+            expression(Literal::Time(chrono::NaiveTime::MIN)),
+            &mut variables,
+            &mut HashMap::new(),
+            LinesAbove::default(),
+            &Context::default(),
+        );
+        assert_eq!(
+            actual,
+            Err(ComputeError::ConditionArgument(Literal::Time(
+                chrono::NaiveTime::MIN
+            )))
+        );
+    }
+
+    #[test]
+    fn test_user_functions() {
+        let mut variables = HashMap::new();
+        let mut functions = HashMap::new();
+        let context = Context::default();
+        let var = |name: &str| Expression::VariableRef(name.to_owned());
+        let int = |value: i64| Expression::Literal(Literal::Integer(value));
+        variables.insert("w".to_owned(), Literal::Integer(100));
+
+        // area(w, h): w * h
+        let definition = Expression::FunctionDefinition(
+            "area".to_owned(),
+            vec!["w".to_owned(), "h".to_owned()],
+            Box::new(Expression::Function(
+                FunctionName::Multiply,
+                vec![var("w"), var("h")],
+            )),
+        );
+        let actual = evaluate(
+            definition,
+            &mut variables,
+            &mut functions,
+            LinesAbove::default(),
+            &context,
+        );
+        assert_eq!(actual, Ok(None));
+
+        let call = Expression::UserFunction("area".to_owned(), vec![int(2), int(3)]);
+        let actual = evaluate(
+            call,
+            &mut variables,
+            &mut functions,
+            LinesAbove::default(),
+            &context,
+        );
+        assert_eq!(actual, Ok(Some(Literal::Integer(6))));
+
+        let call = Expression::UserFunction("area".to_owned(), vec![int(2)]);
+        let actual = evaluate(
+            call,
+            &mut variables,
+            &mut functions,
+            LinesAbove::default(),
+            &context,
+        );
+        assert_eq!(
+            actual,
+            Err(ComputeError::FunctionArgumentCount {
+                function_name: "area".to_owned(),
+                expected_argument_count: 2,
+                supplied_argument_count: 1,
+            })
+        );
+
+        let call = Expression::UserFunction("volume".to_owned(), vec![]);
+        let actual = evaluate(
+            call,
+            &mut variables,
+            &mut functions,
+            LinesAbove::default(),
+            &context,
+        );
+        assert_eq!(
+            actual,
+            Err(ComputeError::UnknownFunction("volume".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_recursion_is_limited() {
+        let mut functions = HashMap::new();
+        let context = Context::default();
+        let var = |name: &str| Expression::VariableRef(name.to_owned());
+        let int = |value: i64| Expression::Literal(Literal::Integer(value));
+
+        // count(n): if(n, 1 + count(n - 1), 0)
+        let recursion = Expression::UserFunction(
+            "count".to_owned(),
+            vec![Expression::Function(
+                FunctionName::Subtract,
+                vec![var("n"), int(1)],
+            )],
+        );
+        let definition = Expression::FunctionDefinition(
+            "count".to_owned(),
+            vec!["n".to_owned()],
+            Box::new(Expression::Function(
+                FunctionName::If,
+                vec![
+                    var("n"),
+                    Expression::Function(FunctionName::Add, vec![int(1), recursion]),
+                    int(0),
+                ],
+            )),
+        );
+        evaluate(
+            definition,
+            &mut HashMap::new(),
+            &mut functions,
+            LinesAbove::default(),
+            &context,
+        )
+        .unwrap();
+
+        let call = |n: i64| Expression::UserFunction("count".to_owned(), vec![int(n)]);
+        let actual = evaluate(
+            call(99),
+            &mut HashMap::new(),
+            &mut functions,
+            LinesAbove::default(),
+            &context,
+        );
+        assert_eq!(actual, Ok(Some(Literal::Integer(99))));
+
+        let actual = evaluate(
+            call(100),
+            &mut HashMap::new(),
+            &mut functions,
+            LinesAbove::default(),
+            &context,
+        );
+        assert_eq!(
+            actual,
+            Err(ComputeError::CallDepthExceeded("count".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_invalid_function_definitions() {
+        let context = Context::default();
+        let definition = |name: &str, parameters: &[&str]| {
+            Expression::FunctionDefinition(
+                name.to_owned(),
+                parameters.iter().map(|p| (*p).to_owned()).collect(),
+                Box::new(Expression::Literal(Literal::Integer(1))),
+            )
+        };
+        let test_cases = [
+            (
+                definition("sqrt", &["x"]),
+                ComputeError::FunctionNameTaken("sqrt".to_owned()),
+            ),
+            (
+                definition("f", &["pi"]),
+                ComputeError::VariableNameTaken("pi".to_owned()),
+            ),
+            (
+                definition("f", &["x", "x"]),
+                ComputeError::DuplicateParameter("x".to_owned()),
+            ),
+        ];
+        for (expression, expected) in test_cases {
+            let actual = evaluate(
+                expression,
+                &mut HashMap::new(),
+                &mut HashMap::new(),
+                LinesAbove::default(),
+                &context,
+            );
+            assert_eq!(actual, Err(expected));
+        }
+    }
+
+    #[test]
+    fn test_definitions_shadow_newer_constants() {
+        let context = Context::default();
+        let mut variables = HashMap::new();
+        let mut functions = HashMap::new();
+        let mut eval = |expression| {
+            evaluate(
+                expression,
+                &mut variables,
+                &mut functions,
+                LinesAbove::default(),
+                &context,
+            )
+        };
+        let reference = |name: &str| Expression::VariableRef(name.to_owned());
+        let define = |name: &str| {
+            Expression::Variable(
+                name.to_owned(),
+                Box::new(Expression::Literal(Literal::Integer(3))),
+            )
+        };
+
+        for name in ["i", "today", "now", "true", "false"] {
+            assert_eq!(eval(define(name)), Ok(Some(Literal::Integer(3))));
+            assert_eq!(eval(reference(name)), Ok(Some(Literal::Integer(3))));
+        }
+        assert_eq!(
+            eval(reference("I")),
+            Ok(Some(Literal::Complex(Complex64::i())))
+        );
+        assert_eq!(
+            eval(define("PI")),
+            Err(ComputeError::VariableNameTaken("PI".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_line_references() {
+        let lines = [Some(Literal::Integer(5)), None, Some(Literal::Integer(7))];
+        let test_cases = [
+            (&lines[..], LineReference::Previous, Ok(Literal::Integer(7))),
+            (&lines[..], LineReference::Line(1), Ok(Literal::Integer(5))),
+            (
+                &lines[..],
+                LineReference::Line(2),
+                Err(ComputeError::LineWithoutResult(2)),
+            ),
+            (
+                &lines[..],
+                LineReference::Line(4),
+                Err(ComputeError::LineNotAbove(4)),
+            ),
+            (
+                &lines[..],
+                LineReference::Line(0),
+                Err(ComputeError::LineNotAbove(0)),
+            ),
+            (
+                &lines[..],
+                LineReference::Removed,
+                Err(ComputeError::RemovedLine),
+            ),
+            (&[], LineReference::Previous, Err(ComputeError::NoLineAbove)),
+        ];
+        for (lines, reference, expected) in test_cases {
+            let actual = evaluate(
+                Expression::LineRef(reference),
+                &mut HashMap::new(),
+                &mut HashMap::new(),
+                LinesAbove {
+                    results: lines,
+                    block_start: 0,
+                },
+                &Context::default(),
+            );
+            assert_eq!(actual, expected.map(Some), "reference: {reference:?}");
+        }
+    }
+
+    #[test]
+    fn test_aggregates_use_the_block_above() {
+        let results = [
+            Some(Literal::Integer(100)),
+            None,
+            Some(Literal::Integer(20)),
+            Some(Literal::Integer(30)),
+        ];
+        let lines = LinesAbove {
+            results: &results,
+            block_start: 2,
+        };
+        let sum = || Expression::VariableRef("sum".to_owned());
+        let context = Context::default();
+        let mut variables = HashMap::new();
+
+        let actual = evaluate(sum(), &mut variables, &mut HashMap::new(), lines, &context);
+        assert_eq!(actual, Ok(Some(Literal::Integer(50))));
 
-        assert_eq!(actual, expected);
+        variables.insert("sum".to_owned(), Literal::Integer(1));
+        let actual = evaluate(sum(), &mut variables, &mut HashMap::new(), lines, &context);
+        assert_eq!(actual, Ok(Some(Literal::Integer(1))));
     }
 }
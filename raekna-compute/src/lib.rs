@@ -1,6 +1,9 @@
+mod context;
 mod errors;
 mod evaluator;
 mod ops;
 
+pub use context::{Clock, Context};
 pub use errors::ComputeError;
-pub use evaluator::evaluate;
+pub use evaluator::{evaluate, LinesAbove, UserFunction};
+pub use ops::{aggregates::is_aggregate, constants::is_time_dependent};
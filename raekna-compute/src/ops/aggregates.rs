@@ -0,0 +1,110 @@
+use raekna_common::{expression::Literal, function_name::FunctionName};
+
+use crate::{
+    context::Context,
+    errors::{ComputeError, ComputeResult},
+    ops::evaluate_fn,
+};
+
+const NAMES: [&str; 4] = ["sum", "total", "avg", "count"];
+
+pub fn is_aggregate(name: &str) -> bool {
+    NAMES.contains(&name.to_lowercase().as_str())
+}
+
+/// Adds results like `+` does, skipping booleans, dates and lines without a result
+pub fn evaluate(
+    name: &str,
+    block: &[Option<Literal>],
+    context: &Context,
+) -> Option<ComputeResult<Literal>> {
+    let numbers = block
+        .iter()
+        .flatten()
+        .filter(|value| !value.is_boolean() && !value.is_date())
+        .cloned();
+    let result = match name.to_lowercase().as_str() {
+        "sum" | "total" => sum(numbers, context),
+        "avg" => {
+            let count = numbers.clone().count();
+            if count == 0 {
+                return Some(Err(ComputeError::NothingToAverage));
+            }
+            sum(numbers, context).and_then(|sum| {
+                let count = Literal::Integer(count as i64);
+                evaluate_fn(FunctionName::Divide, vec![sum, count], context)
+            })
+        }
+        "count" => Ok(Literal::Integer(numbers.count() as i64)),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn sum(mut numbers: impl Iterator<Item = Literal>, context: &Context) -> ComputeResult<Literal> {
+    let Some(first) = numbers.next() else {
+        return Ok(Literal::Integer(0));
+    };
+    numbers.try_fold(first, |sum, value| {
+        evaluate_fn(FunctionName::Add, vec![sum, value], context)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use raekna_common::unit::{NamedUnit, Unit};
+
+    use super::*;
+
+    fn aggregate(name: &str, block: &[Option<Literal>]) -> Option<ComputeResult<Literal>> {
+        evaluate(name, block, &Context::default())
+    }
+
+    #[test]
+    fn aggregates() {
+        let block = [
+            Some(Literal::Integer(4)),
+            None,
+            Some(Literal::Boolean(true)),
+            Some(Literal::Float(2.5)),
+            Some(Literal::Integer(-1)),
+        ];
+        let test_cases = [
+            ("sum", Literal::Float(5.5)),
+            ("Total", Literal::Float(5.5)),
+            ("avg", Literal::Float(5.5 / 3.0)),
+            ("count", Literal::Integer(3)),
+        ];
+        for (name, expected) in test_cases {
+            assert_eq!(aggregate(name, &block), Some(Ok(expected)), "{name}");
+        }
+    }
+
+    #[test]
+    fn units_are_converted() {
+        let km = Unit::new(NamedUnit::find("km").unwrap(), 1);
+        let m = Unit::new(NamedUnit::find("m").unwrap(), 1);
+        let block = [
+            Some(Literal::quantity(Literal::Integer(2), km)),
+            Some(Literal::quantity(Literal::Integer(500), m)),
+        ];
+
+        let actual = aggregate("sum", &block).unwrap().unwrap();
+        assert_eq!(actual.to_string(), "2.5 km");
+    }
+
+    #[test]
+    fn empty_block() {
+        assert_eq!(aggregate("sum", &[None]), Some(Ok(Literal::Integer(0))));
+        assert_eq!(aggregate("count", &[]), Some(Ok(Literal::Integer(0))));
+        assert_eq!(
+            aggregate("avg", &[]),
+            Some(Err(ComputeError::NothingToAverage))
+        );
+    }
+
+    #[test]
+    fn other_names() {
+        assert_eq!(aggregate("summary", &[]), None);
+    }
+}
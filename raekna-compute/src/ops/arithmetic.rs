@@ -1,19 +1,33 @@
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::{Pow, Zero};
 use raekna_common::expression::Literal;
 
 use crate::{
     errors::{ComputeError, ComputeResult},
-    ops::validate_and_wrap,
+    ops::{
+        as_complex_pair, as_rationals, validate_and_wrap, validate_and_wrap_complex,
+        validate_and_wrap_exact, MAX_BITS,
+    },
 };
 
 pub fn negate(value: Literal) -> Option<Literal> {
-    let res = match value {
-        Literal::Integer(i) => Literal::Integer(-i),
-        Literal::Float(f) => Literal::Float(-f),
-    };
-    Some(res)
+    Some(-value)
 }
 
 pub fn add(left: Literal, right: Literal) -> Option<Literal> {
+    if let (Literal::Integer(l), Literal::Integer(r)) = (&left, &right) {
+        if let Some(sum) = l.checked_add(*r) {
+            return Some(Literal::Integer(sum));
+        }
+    }
+    if let Some((left, right)) = as_rationals(&left, &right) {
+        return validate_and_wrap_exact(left + right);
+    }
+    if let Some((left, right)) = as_complex_pair(&left, &right) {
+        return validate_and_wrap_complex(left + right);
+    }
     let left = left.as_f64();
     let right = right.as_f64();
     let sum = left + right;
@@ -21,6 +35,17 @@ pub fn add(left: Literal, right: Literal) -> Option<Literal> {
 }
 
 pub fn sub(left: Literal, right: Literal) -> Option<Literal> {
+    if let (Literal::Integer(l), Literal::Integer(r)) = (&left, &right) {
+        if let Some(difference) = l.checked_sub(*r) {
+            return Some(Literal::Integer(difference));
+        }
+    }
+    if let Some((left, right)) = as_rationals(&left, &right) {
+        return validate_and_wrap_exact(left - right);
+    }
+    if let Some((left, right)) = as_complex_pair(&left, &right) {
+        return validate_and_wrap_complex(left - right);
+    }
     let left = left.as_f64();
     let right = right.as_f64();
     let difference = left - right;
@@ -28,6 +53,17 @@ pub fn sub(left: Literal, right: Literal) -> Option<Literal> {
 }
 
 pub fn mul(left: Literal, right: Literal) -> Option<Literal> {
+    if let (Literal::Integer(l), Literal::Integer(r)) = (&left, &right) {
+        if let Some(product) = l.checked_mul(*r) {
+            return Some(Literal::Integer(product));
+        }
+    }
+    if let Some((left, right)) = as_rationals(&left, &right) {
+        return validate_and_wrap_exact(left * right);
+    }
+    if let Some((left, right)) = as_complex_pair(&left, &right) {
+        return validate_and_wrap_complex(left * right);
+    }
     let left = left.as_f64();
     let right = right.as_f64();
     let product = left * right;
@@ -35,6 +71,18 @@ pub fn mul(left: Literal, right: Literal) -> Option<Literal> {
 }
 
 pub fn div(dividend: Literal, divisor: Literal) -> ComputeResult<Option<Literal>> {
+    if let Some((dividend, divisor)) = as_rationals(&dividend, &divisor) {
+        if divisor.is_zero() {
+            return Err(ComputeError::DivisionByZero);
+        }
+        return Ok(validate_and_wrap_exact(dividend / divisor));
+    }
+    if let Some((dividend, divisor)) = as_complex_pair(&dividend, &divisor) {
+        if divisor.is_zero() {
+            return Err(ComputeError::DivisionByZero);
+        }
+        return Ok(validate_and_wrap_complex(dividend / divisor));
+    }
     let dividend = dividend.as_f64();
     let divisor = divisor.as_f64();
     if divisor == 0.0 {
@@ -46,6 +94,12 @@ pub fn div(dividend: Literal, divisor: Literal) -> ComputeResult<Option<Literal>
 }
 
 pub fn mod0(dividend: Literal, divisor: Literal) -> ComputeResult<Option<Literal>> {
+    if let Some((dividend, divisor)) = as_rationals(&dividend, &divisor) {
+        if divisor.is_zero() {
+            return Err(ComputeError::DivisionByZero);
+        }
+        return Ok(validate_and_wrap_exact(dividend % divisor));
+    }
     let dividend = dividend.as_f64();
     let divisor = divisor.as_f64();
     if divisor == 0.0 {
@@ -56,17 +110,68 @@ pub fn mod0(dividend: Literal, divisor: Literal) -> ComputeResult<Option<Literal
     }
 }
 
+/// Powers without a real result are complex
 pub fn pow(base: Literal, exponent: Literal) -> Option<Literal> {
-    let base = base.as_f64();
-    let exponent = exponent.as_f64();
-    let power = base.powf(exponent);
+    if let (Some(base), Literal::Integer(exponent)) = (base.as_rational(), &exponent) {
+        return exact_pow(base, *exponent);
+    }
+    if let Some((base, complex_exponent)) = as_complex_pair(&base, &exponent) {
+        return complex_pow(base, complex_exponent, &exponent);
+    }
+    let power = base.as_f64().powf(exponent.as_f64());
+    if power.is_nan() {
+        return complex_pow(base.as_complex(), exponent.as_complex(), &exponent);
+    }
     validate_and_wrap(power)
 }
 
+fn complex_pow(
+    base: Complex64,
+    exponent: Complex64,
+    literal_exponent: &Literal,
+) -> Option<Literal> {
+    // Repeated multiplication avoids rounding errors like i^2 being -1 + 0.0000000000000001i
+    let power = match literal_exponent {
+        Literal::Integer(e) if i32::try_from(*e).is_ok() => base.powi(*e as i32),
+        _ => base.powc(exponent),
+    };
+    validate_and_wrap_complex(power)
+}
+
+fn exact_pow(base: BigRational, exponent: i64) -> Option<Literal> {
+    let base = if exponent < 0 {
+        if base.is_zero() {
+            return None;
+        }
+        base.recip()
+    } else {
+        base
+    };
+    let exponent = exponent.unsigned_abs();
+    let (numer, denom) = (
+        integer_pow(base.numer(), exponent)?,
+        integer_pow(base.denom(), exponent)?,
+    );
+    validate_and_wrap_exact(BigRational::new(numer, denom))
+}
+
+fn integer_pow(base: &BigInt, exponent: u64) -> Option<BigInt> {
+    // 0, 1 and -1 never grow so any exponent can be reduced to 1 or 2 while keeping the sign
+    let exponent = if exponent > 2 && base.bits() <= 1 {
+        2 - exponent % 2
+    } else {
+        exponent
+    };
+    if base.bits().saturating_mul(exponent) > MAX_BITS {
+        return None;
+    }
+    Some(Pow::pow(base, exponent))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ops::test_utils::{float, int};
+    use crate::ops::test_utils::{big, complex, float, int, ratio};
 
     #[test]
     fn addition() {
@@ -163,7 +268,7 @@ mod tests {
             (int(20), int(-5), int(-4)),
             (int(-20), int(5), int(-4)),
             (int(-20), int(-5), int(4)),
-            (int(8), int(5), float(1.6)),
+            (int(8), int(5), ratio(8, 5)),
             // Mixed integers and floats
             (int(20), float(5.0), int(4)),
             (int(20), float(-5.0), int(-4)),
@@ -219,9 +324,9 @@ mod tests {
         let test_cases = [
             // Integers on both sides
             (int(4), int(2), int(16)),
-            (int(4), int(-2), float(0.0625)),
+            (int(4), int(-2), ratio(1, 16)),
             (int(-4), int(3), int(-64)),
-            (int(-4), int(-3), float(-0.015625)),
+            (int(-4), int(-3), ratio(-1, 64)),
             // Mixed integers and floats
             (int(4), float(2.1), float(18.37917367995256)),
             (int(4), float(-2.0), float(0.0625)),
@@ -242,9 +347,86 @@ mod tests {
             assert_eq!(actual, expected);
         }
 
-        let test_cases = [(int(-1), float(-0.1))];
+        let test_cases = [(int(-1), float(-0.1)), (float(-2.5), ratio(1, 3))];
         for (left, right) in test_cases.into_iter() {
-            assert!(pow(left, right).is_none());
+            assert!(pow(left, right).unwrap().is_complex());
+        }
+    }
+
+    #[test]
+    fn big_integers() {
+        let max = i64::MAX;
+        let test_cases = [
+            (add(int(max), int(1)), big("9223372036854775808")),
+            (sub(int(-max), int(2)), big("-9223372036854775809")),
+            (add(big("9223372036854775808"), int(-1)), int(max)),
+            (
+                mul(int(max), int(max)),
+                big("85070591730234615847396907784232501249"),
+            ),
+            (
+                pow(int(2), int(100)),
+                big("1267650600228229401496703205376"),
+            ),
+            (pow(int(-1), int(1_000_001)), int(-1)),
+            (pow(int(0), int(0)), int(1)),
+            (
+                div(big("1267650600228229401496703205376"), int(1 << 50)).unwrap(),
+                big("1125899906842624"),
+            ),
+            (
+                mod0(big("1267650600228229401496703205377"), int(10)).unwrap(),
+                int(7),
+            ),
+        ];
+        for (actual, expected) in test_cases.into_iter() {
+            assert_eq!(actual, Some(expected));
         }
+
+        assert!(pow(int(2), int(1_000_000)).is_none());
+        assert_eq!(
+            div(big("1267650600228229401496703205376"), int(0)),
+            Err(ComputeError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn rationals() {
+        let third = || div(int(1), int(3)).unwrap().unwrap();
+        let test_cases = [
+            (mul(third(), int(3)), int(1)),
+            (add(third(), third()), ratio(2, 3)),
+            (sub(ratio(1, 10), ratio(3, 10)), ratio(-1, 5)),
+            (add(ratio(1, 10), ratio(2, 10)), ratio(3, 10)),
+            (pow(ratio(2, 3), int(3)), ratio(8, 27)),
+            (pow(ratio(2, 3), int(-2)), ratio(9, 4)),
+            (mod0(ratio(7, 2), int(2)).unwrap(), ratio(3, 2)),
+            (add(ratio(1, 2), float(0.25)), float(0.75)),
+        ];
+        for (actual, expected) in test_cases.into_iter() {
+            assert_eq!(actual, Some(expected));
+        }
+
+        assert!(pow(int(0), int(-1)).is_none());
+        assert!(pow(ratio(1, 3), int(1_000_000)).is_none());
+    }
+
+    #[test]
+    fn complex_numbers() {
+        let i = complex(0.0, 1.0);
+        assert_eq!(add(int(2), i.clone()), Some(complex(2.0, 1.0)));
+        assert_eq!(sub(i.clone(), i.clone()), Some(int(0)));
+        assert_eq!(mul(i.clone(), i.clone()), Some(int(-1)));
+        assert_eq!(
+            div(complex(1.0, 1.0), ratio(1, 2)),
+            Ok(Some(complex(2.0, 2.0)))
+        );
+        assert_eq!(div(i.clone(), int(0)), Err(ComputeError::DivisionByZero));
+        assert_eq!(pow(i.clone(), int(2)), Some(int(-1)));
+        assert_eq!(pow(int(-4), ratio(1, 2)), Some(complex(0.0, 2.0)));
+        assert_eq!(
+            pow(i.clone(), i),
+            Some(float((-std::f64::consts::FRAC_PI_2).exp()))
+        );
     }
 }
@@ -0,0 +1,150 @@
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive};
+use raekna_common::{expression::Literal, function_name::FunctionName};
+
+use crate::{
+    errors::{ComputeError, ComputeResult},
+    ops::{validate_and_wrap_big, MAX_BITS},
+};
+
+/// Integers are treated as two's complement with infinitely many bits, so `~5` is `-6`
+pub fn evaluate(fn_name: FunctionName, args: &[Literal]) -> ComputeResult<Option<Literal>> {
+    let values = args
+        .iter()
+        .map(|arg| {
+            arg.as_big_int()
+                .ok_or(ComputeError::IntegerArgument(fn_name))
+        })
+        .collect::<ComputeResult<Vec<_>>>()?;
+    let result = match (fn_name, values.as_slice()) {
+        (FunctionName::BitAnd, [left, right]) => left & right,
+        (FunctionName::BitOr, [left, right]) => left | right,
+        (FunctionName::BitXor, [left, right]) => left ^ right,
+        (FunctionName::BitNot, [value]) => !value,
+        (FunctionName::ShiftLeft, [value, bits]) => match bit_count(fn_name, bits)? {
+            bits if bits > MAX_BITS => return Ok(None),
+            bits => value << bits,
+        },
+        (FunctionName::ShiftRight, [value, bits]) => shift_right(value, bit_count(fn_name, bits)?),
+        (FunctionName::PopCount, [value]) => BigInt::from(value.magnitude().count_ones()),
+        (FunctionName::Bit, [value, index]) => {
+            shift_right(value, bit_count(fn_name, index)?) & BigInt::one()
+        }
+        (FunctionName::Unsigned(bits), [value]) => wrap_unsigned(value, bits),
+        (FunctionName::Signed(bits), [value]) => {
+            let value = wrap_unsigned(value, bits);
+            if value.bit(u64::from(bits - 1)) {
+                value - (BigInt::one() << bits)
+            } else {
+                value
+            }
+        }
+        _ => unreachable!("`{fn_name}` isn't a bitwise function"),
+    };
+    Ok(validate_and_wrap_big(result))
+}
+
+fn bit_count(fn_name: FunctionName, bits: &BigInt) -> ComputeResult<u64> {
+    if bits.is_negative() {
+        return Err(ComputeError::NegativeBitCount(fn_name));
+    }
+    Ok(bits.to_u64().unwrap_or(u64::MAX))
+}
+
+/// Rounds down, so shifting out every bit leaves `0` or `-1`
+fn shift_right(value: &BigInt, bits: u64) -> BigInt {
+    if bits >= value.bits() {
+        if value.is_negative() {
+            -BigInt::one()
+        } else {
+            BigInt::from(0)
+        }
+    } else {
+        value >> bits
+    }
+}
+
+fn wrap_unsigned(value: &BigInt, bits: u32) -> BigInt {
+    let modulus = BigInt::one() << bits;
+    ((value % &modulus) + &modulus) % &modulus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::test_utils::{big, float, int};
+
+    fn eval(fn_name: FunctionName, args: Vec<Literal>) -> ComputeResult<Option<Literal>> {
+        evaluate(fn_name, &args)
+    }
+
+    #[test]
+    fn operators() {
+        let test_cases = [
+            (FunctionName::BitAnd, vec![int(0b1100), int(0b1010)], 0b1000),
+            (FunctionName::BitOr, vec![int(0b1100), int(0b1010)], 0b1110),
+            (FunctionName::BitXor, vec![int(0b1100), int(0b1010)], 0b0110),
+            (FunctionName::BitAnd, vec![int(-1), int(0xFF)], 0xFF),
+            (FunctionName::BitNot, vec![int(5)], -6),
+            (FunctionName::ShiftLeft, vec![int(1), int(10)], 1024),
+            (FunctionName::ShiftRight, vec![int(1024), int(3)], 128),
+            (FunctionName::ShiftRight, vec![int(-5), int(1)], -3),
+            (FunctionName::ShiftRight, vec![int(-5), int(100)], -1),
+        ];
+        for (fn_name, args, expected) in test_cases {
+            assert_eq!(eval(fn_name, args), Ok(Some(int(expected))));
+        }
+        assert_eq!(
+            eval(FunctionName::ShiftLeft, vec![int(1), int(64)]),
+            Ok(Some(big("18446744073709551616")))
+        );
+    }
+
+    #[test]
+    fn helpers() {
+        let test_cases = [
+            (FunctionName::PopCount, vec![int(0xFF)], 8),
+            (FunctionName::PopCount, vec![int(-7)], 3),
+            (FunctionName::Bit, vec![int(0b100), int(2)], 1),
+            (FunctionName::Bit, vec![int(0b100), int(1)], 0),
+            (FunctionName::Bit, vec![int(-1), int(1000)], 1),
+        ];
+        for (fn_name, args, expected) in test_cases {
+            assert_eq!(eval(fn_name, args), Ok(Some(int(expected))));
+        }
+    }
+
+    #[test]
+    fn fixed_widths() {
+        let test_cases = [
+            (FunctionName::Unsigned(8), int(300), 44),
+            (FunctionName::Unsigned(8), int(-1), 255),
+            (FunctionName::Signed(8), int(200), -56),
+            (FunctionName::Signed(8), int(127), 127),
+            (FunctionName::Signed(32), int(0xFFFF_FFFF), -1),
+        ];
+        for (fn_name, value, expected) in test_cases {
+            assert_eq!(eval(fn_name, vec![value]), Ok(Some(int(expected))));
+        }
+        assert_eq!(
+            eval(FunctionName::Unsigned(64), vec![int(-1)]),
+            Ok(Some(big("18446744073709551615")))
+        );
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert_eq!(
+            eval(FunctionName::BitAnd, vec![float(1.5), int(1)]),
+            Err(ComputeError::IntegerArgument(FunctionName::BitAnd))
+        );
+        assert_eq!(
+            eval(FunctionName::ShiftLeft, vec![int(1), int(-1)]),
+            Err(ComputeError::NegativeBitCount(FunctionName::ShiftLeft))
+        );
+        assert_eq!(
+            eval(FunctionName::ShiftLeft, vec![int(1), int(1_000_000)]),
+            Ok(None)
+        );
+    }
+}
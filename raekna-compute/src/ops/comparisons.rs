@@ -1,13 +1,23 @@
-use raekna_common::expression::Literal;
+use std::cmp::Ordering;
+
+use raekna_common::{expression::Literal, function_name::FunctionName};
+
+/// `None` if either value is NaN
+pub fn compare(left: &Literal, right: &Literal) -> Option<Ordering> {
+    match (left, right) {
+        (Literal::Integer(left), Literal::Integer(right)) => Some(left.cmp(right)),
+        (Literal::Float(_), _) | (_, Literal::Float(_)) => {
+            left.as_f64().partial_cmp(&right.as_f64())
+        }
+        _ => Some(left.as_rational().cmp(&right.as_rational())),
+    }
+}
 
 pub fn min(left: Literal, right: Literal) -> Option<Literal> {
-    use Literal::*;
-    let right_is_bigger = match (left, right) {
-        (Integer(left), Integer(right)) => left <= right,
-        (Integer(i), Float(f)) => (i as f64) <= f,
-        (Float(f), Integer(i)) => f <= (i as f64),
-        (Float(left), Float(right)) => left <= right,
-    };
+    let right_is_bigger = matches!(
+        compare(&left, &right),
+        Some(Ordering::Less | Ordering::Equal)
+    );
     let result = if right_is_bigger {
         left.maybe_truncate()
     } else {
@@ -17,13 +27,10 @@ pub fn min(left: Literal, right: Literal) -> Option<Literal> {
 }
 
 pub fn max(left: Literal, right: Literal) -> Option<Literal> {
-    use Literal::*;
-    let right_is_smaller = match (left, right) {
-        (Integer(left), Integer(right)) => left >= right,
-        (Integer(i), Float(f)) => (i as f64) >= f,
-        (Float(f), Integer(i)) => f >= (i as f64),
-        (Float(left), Float(right)) => left >= right,
-    };
+    let right_is_smaller = matches!(
+        compare(&left, &right),
+        Some(Ordering::Greater | Ordering::Equal)
+    );
     let result = if right_is_smaller {
         left.maybe_truncate()
     } else {
@@ -32,10 +39,31 @@ pub fn max(left: Literal, right: Literal) -> Option<Literal> {
     Some(result)
 }
 
+/// Complex numbers can only be compared for equality
+pub fn test(fn_name: FunctionName, left: &Literal, right: &Literal) -> Option<Literal> {
+    let ordering = if left.is_complex() || right.is_complex() {
+        (left.as_complex() == right.as_complex()).then_some(Ordering::Equal)
+    } else {
+        compare(left, right)
+    };
+    let result = match fn_name {
+        FunctionName::Less => ordering == Some(Ordering::Less),
+        FunctionName::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        FunctionName::Greater => ordering == Some(Ordering::Greater),
+        FunctionName::GreaterEqual => {
+            matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+        }
+        FunctionName::Equal => ordering == Some(Ordering::Equal),
+        FunctionName::NotEqual => ordering != Some(Ordering::Equal),
+        _ => unreachable!("`{fn_name}` isn't a comparison"),
+    };
+    Some(Literal::Boolean(result))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ops::test_utils::{float, int};
+    use crate::ops::test_utils::{big, complex, float, int, ratio};
 
     #[test]
     fn minimum() {
@@ -94,4 +122,58 @@ mod tests {
             assert_eq!(actual, expected);
         }
     }
+
+    #[test]
+    fn big_integers() {
+        let huge = big("100000000000000000000");
+        assert_eq!(max(huge.clone(), int(5)), Some(huge.clone()));
+        assert_eq!(
+            min(huge.clone(), big("-100000000000000000000")),
+            Some(big("-100000000000000000000"))
+        );
+        assert_eq!(min(huge.clone(), float(1e21)), Some(huge));
+    }
+
+    #[test]
+    fn comparison_operators() {
+        let test_cases = [
+            (FunctionName::Less, int(1), int(2), true),
+            (FunctionName::Less, int(2), int(2), false),
+            (FunctionName::LessEqual, int(2), float(2.0), true),
+            (FunctionName::Greater, ratio(1, 3), float(0.3), true),
+            (
+                FunctionName::GreaterEqual,
+                big("100000000000000000000"),
+                int(5),
+                true,
+            ),
+            (FunctionName::Equal, ratio(1, 2), float(0.5), true),
+            (FunctionName::NotEqual, int(1), int(1), false),
+            (
+                FunctionName::Equal,
+                complex(1.0, 2.0),
+                complex(1.0, 2.0),
+                true,
+            ),
+            (FunctionName::NotEqual, complex(1.0, 2.0), int(1), true),
+            (FunctionName::Equal, float(f64::NAN), float(f64::NAN), false),
+            (
+                FunctionName::NotEqual,
+                float(f64::NAN),
+                float(f64::NAN),
+                true,
+            ),
+        ];
+        for (fn_name, left, right, expected) in test_cases.into_iter() {
+            let actual = test(fn_name, &left, &right).unwrap();
+            assert_eq!(actual, Literal::Boolean(expected));
+        }
+    }
+
+    #[test]
+    fn rationals() {
+        assert_eq!(min(ratio(1, 3), ratio(1, 2)), Some(ratio(1, 3)));
+        assert_eq!(max(ratio(-1, 3), int(0)), Some(int(0)));
+        assert_eq!(max(ratio(7, 2), float(3.4)), Some(ratio(7, 2)));
+    }
 }
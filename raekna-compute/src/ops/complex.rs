@@ -0,0 +1,66 @@
+use raekna_common::expression::Literal;
+
+use crate::ops::validate_and_wrap;
+
+pub fn re(value: Literal) -> Option<Literal> {
+    match value {
+        Literal::Complex(c) => validate_and_wrap(c.re),
+        value => Some(value),
+    }
+}
+
+pub fn im(value: Literal) -> Option<Literal> {
+    match value {
+        Literal::Complex(c) => validate_and_wrap(c.im),
+        _ => Some(Literal::Integer(0)),
+    }
+}
+
+pub fn arg(value: Literal) -> Option<Literal> {
+    validate_and_wrap(value.as_complex().arg())
+}
+
+pub fn conj(value: Literal) -> Option<Literal> {
+    match value {
+        Literal::Complex(c) => Some(Literal::Complex(c.conj())),
+        value => Some(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::test_utils::{complex, float, int, ratio};
+
+    #[test]
+    fn parts() {
+        let test_cases = [
+            (complex(3.0, -4.0), int(3), int(-4)),
+            (ratio(1, 2), ratio(1, 2), int(0)),
+            (int(-7), int(-7), int(0)),
+        ];
+        for (value, expected_re, expected_im) in test_cases {
+            assert_eq!(re(value.clone()), Some(expected_re));
+            assert_eq!(im(value), Some(expected_im));
+        }
+    }
+
+    #[test]
+    fn argument() {
+        let test_cases = [
+            (complex(0.0, 1.0), float(std::f64::consts::FRAC_PI_2)),
+            (complex(1.0, -1.0), float(-std::f64::consts::FRAC_PI_4)),
+            (int(-2), float(std::f64::consts::PI)),
+            (int(5), int(0)),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(arg(value), Some(expected));
+        }
+    }
+
+    #[test]
+    fn conjugate() {
+        assert_eq!(conj(complex(1.5, 2.0)), Some(complex(1.5, -2.0)));
+        assert_eq!(conj(int(3)), Some(int(3)));
+    }
+}
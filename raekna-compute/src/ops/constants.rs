@@ -1,10 +1,28 @@
+use num_complex::Complex64;
 use raekna_common::expression::Literal;
 
-pub fn evaluate(name: &str) -> Option<Literal> {
+use crate::context::Context;
+
+/// Only these can't be defined by users, the later constants are shadowed by definitions so sheets
+/// that already used their names keep working
+pub fn is_reserved(name: &str) -> bool {
+    matches!(name.to_lowercase().as_str(), "pi" | "tau" | "e")
+}
+
+pub fn is_time_dependent(name: &str) -> bool {
+    matches!(name.to_lowercase().as_str(), "today" | "now")
+}
+
+pub fn evaluate(name: &str, context: &Context) -> Option<Literal> {
     match name.to_lowercase().as_str() {
         "pi" => Some(Literal::Float(std::f64::consts::PI)),
         "tau" => Some(Literal::Float(std::f64::consts::TAU)),
         "e" => Some(Literal::Float(std::f64::consts::E)),
+        "i" => Some(Literal::Complex(Complex64::i())),
+        "true" => Some(Literal::Boolean(true)),
+        "false" => Some(Literal::Boolean(false)),
+        "today" => Some(Literal::Date(context.clock.now().date_naive())),
+        "now" => Some(Literal::DateTime(context.clock.now().naive_local(), None)),
         _ => None,
     }
 }
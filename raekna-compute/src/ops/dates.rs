@@ -0,0 +1,425 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, FixedOffset, Months, NaiveDateTime, NaiveTime, TimeDelta, TimeZone};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+use raekna_common::{
+    expression::Literal,
+    function_name::FunctionName,
+    unit::{NamedUnit, Unit},
+};
+
+use crate::{
+    context::Context,
+    errors::{ComputeError, ComputeResult},
+    ops::check_argument_count,
+};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+enum Shift {
+    /// A month after Jan 31 is Feb 28
+    Months(i64),
+    Exact(TimeDelta),
+}
+
+/// Evaluates a function with a date or time argument, which can be moved by a duration like `3 weeks`
+pub fn evaluate_fn(
+    fn_name: FunctionName,
+    args: Vec<Literal>,
+    context: &Context,
+) -> ComputeResult<Literal> {
+    let args = args
+        .into_iter()
+        .map(Literal::unformatted)
+        .collect::<Vec<_>>();
+    check_argument_count(fn_name, args.len())?;
+    let result = match (fn_name, args.as_slice()) {
+        (FunctionName::Add, [date, duration]) | (FunctionName::Add, [duration, date])
+            if !duration.is_date() =>
+        {
+            shift(date, to_shift(duration)?)
+        }
+        (FunctionName::Subtract, [date, duration]) if date.is_date() && !duration.is_date() => {
+            let backwards = match to_shift(duration)? {
+                Shift::Months(months) => Shift::Months(-months),
+                Shift::Exact(delta) => Shift::Exact(-delta),
+            };
+            shift(date, backwards)
+        }
+        (FunctionName::Subtract, [left, right]) if left.is_date() && right.is_date() => {
+            Some(difference(left, right, context)?)
+        }
+        (FunctionName::Max | FunctionName::Min, [left, right])
+            if left.is_date() && right.is_date() =>
+        {
+            let ordering =
+                compare(left, right, context).ok_or(ComputeError::DateArgument(fn_name))?;
+            let left_wins = match fn_name {
+                FunctionName::Max => ordering != Ordering::Less,
+                _ => ordering != Ordering::Greater,
+            };
+            Some(if left_wins { left } else { right }.clone())
+        }
+        (
+            FunctionName::Less
+            | FunctionName::LessEqual
+            | FunctionName::Greater
+            | FunctionName::GreaterEqual
+            | FunctionName::Equal
+            | FunctionName::NotEqual,
+            [left, right],
+        ) if left.is_date() && right.is_date() => {
+            let ordering =
+                compare(left, right, context).ok_or(ComputeError::DateArgument(fn_name))?;
+            let result = match fn_name {
+                FunctionName::Less => ordering == Ordering::Less,
+                FunctionName::LessEqual => ordering != Ordering::Greater,
+                FunctionName::Greater => ordering == Ordering::Greater,
+                FunctionName::GreaterEqual => ordering != Ordering::Less,
+                FunctionName::Equal => ordering == Ordering::Equal,
+                _ => ordering != Ordering::Equal,
+            };
+            Some(Literal::Boolean(result))
+        }
+        _ => return Err(ComputeError::DateArgument(fn_name)),
+    };
+    match result {
+        Some(result) => Ok(result),
+        None => Err(ComputeError::ResultTooBig(fn_name, args)),
+    }
+}
+
+pub fn convert(value: Literal, to: FixedOffset, context: &Context) -> ComputeResult<Literal> {
+    match value.unformatted() {
+        Literal::Time(time) => {
+            let today = context.clock.now().date_naive();
+            let instant = local_instant(today.and_time(time), context);
+            Ok(Literal::Time(instant.with_timezone(&to).time()))
+        }
+        value => match instant(&value, context) {
+            Some(instant) => Ok(Literal::DateTime(
+                instant.with_timezone(&to).naive_local(),
+                Some(to),
+            )),
+            None => Err(ComputeError::TimeZoneConversion),
+        },
+    }
+}
+
+fn to_shift(duration: &Literal) -> ComputeResult<Shift> {
+    let (value, unit) = duration.clone().split_unit();
+    let seconds = Unit::new(NamedUnit::find("s").expect("seconds exist"), 1);
+    let (factor, exact) = match (unit.conversion_factor(&seconds), exact(&value)) {
+        (Some(factor), Some(exact)) => (factor, exact),
+        _ => return Err(ComputeError::ExpectedDuration(duration.clone())),
+    };
+    let months_per_unit = match unit.named().map(|named| named.symbol) {
+        Some("month") => Some(1),
+        Some("year") => Some(12),
+        _ => None,
+    };
+    if let (Some(months_per_unit), true) = (months_per_unit, exact.is_integer()) {
+        if let Some(months) = exact.to_integer().to_i64() {
+            return Ok(Shift::Months(months.saturating_mul(months_per_unit)));
+        }
+    }
+    let nanos = (exact * factor * BigInt::from(NANOS_PER_SECOND))
+        .round()
+        .to_integer();
+    let delta = nanos
+        .to_i64()
+        .map(TimeDelta::nanoseconds)
+        .unwrap_or(TimeDelta::MAX);
+    Ok(Shift::Exact(delta))
+}
+
+/// A time of day wraps around midnight, `None` if the result overflows
+fn shift(value: &Literal, shift: Shift) -> Option<Literal> {
+    let shift_date_time = |date_time: NaiveDateTime| match shift {
+        Shift::Months(months) if months < 0 => {
+            date_time.checked_sub_months(Months::new(u32::try_from(-months).ok()?))
+        }
+        Shift::Months(months) => {
+            date_time.checked_add_months(Months::new(u32::try_from(months).ok()?))
+        }
+        Shift::Exact(delta) => date_time.checked_add_signed(delta),
+    };
+    match value {
+        // A date stays a date unless it's moved by a part of a day
+        Literal::Date(date) => {
+            let date_time = shift_date_time(date.and_time(NaiveTime::MIN))?;
+            if date_time.time() == NaiveTime::MIN {
+                Some(Literal::Date(date_time.date()))
+            } else {
+                Some(Literal::DateTime(date_time, None))
+            }
+        }
+        Literal::DateTime(date_time, offset) => {
+            Some(Literal::DateTime(shift_date_time(*date_time)?, *offset))
+        }
+        Literal::Time(time) => match shift {
+            Shift::Months(_) => None,
+            Shift::Exact(delta) => Some(Literal::Time(time.overflowing_add_signed(delta).0)),
+        },
+        _ => None,
+    }
+}
+
+/// Dates are days apart, other differences are in the largest unit that gives a whole number
+fn difference(left: &Literal, right: &Literal, context: &Context) -> ComputeResult<Literal> {
+    let delta = match (left, right) {
+        (Literal::Date(left), Literal::Date(right)) => left.signed_duration_since(*right),
+        (Literal::Time(left), Literal::Time(right)) => left.signed_duration_since(*right),
+        _ => match (instant(left, context), instant(right, context)) {
+            (Some(left), Some(right)) => left.signed_duration_since(right),
+            _ => return Err(ComputeError::DateArgument(FunctionName::Subtract)),
+        },
+    };
+    let seconds = delta.num_seconds();
+    let nanos = delta.subsec_nanos();
+    let symbol = match seconds {
+        _ if nanos != 0 => "s",
+        s if s % 86_400 == 0 => "day",
+        s if s % 3_600 == 0 => "h",
+        s if s % 60 == 0 => "min",
+        _ => "s",
+    };
+    let unit = Unit::new(NamedUnit::find(symbol).expect("unit exists"), 1);
+    let seconds = BigRational::from_integer(seconds.into())
+        + BigRational::new(nanos.into(), NANOS_PER_SECOND.into());
+    let factor = Unit::new(NamedUnit::find("s").expect("seconds exist"), 1)
+        .conversion_factor(&unit)
+        .expect("both are units of time");
+    Ok(Literal::quantity(Literal::from(seconds * factor), unit))
+}
+
+fn compare(left: &Literal, right: &Literal, context: &Context) -> Option<Ordering> {
+    match (left, right) {
+        (Literal::Time(left), Literal::Time(right)) => Some(left.cmp(right)),
+        _ => Some(instant(left, context)?.cmp(&instant(right, context)?)),
+    }
+}
+
+/// Dates start at midnight, times without an offset are in the time zone of the clock
+fn instant(value: &Literal, context: &Context) -> Option<DateTime<FixedOffset>> {
+    match value {
+        Literal::Date(date) => Some(local_instant(date.and_time(NaiveTime::MIN), context)),
+        Literal::DateTime(date_time, None) => Some(local_instant(*date_time, context)),
+        Literal::DateTime(date_time, Some(offset)) => {
+            offset.from_local_datetime(date_time).single()
+        }
+        _ => None,
+    }
+}
+
+fn local_instant(date_time: NaiveDateTime, context: &Context) -> DateTime<FixedOffset> {
+    let offset = *context.clock.now().offset();
+    offset
+        .from_local_datetime(&date_time)
+        .single()
+        .expect("fixed offsets are never ambiguous")
+}
+
+fn exact(value: &Literal) -> Option<BigRational> {
+    match value {
+        Literal::Float(value) => BigRational::from_float(*value),
+        value => value.as_rational(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::{
+        context::Clock,
+        ops::test_utils::{int, ratio},
+    };
+
+    fn context() -> Context {
+        let now = FixedOffset::east_opt(2 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2026, 10, 17, 14, 30, 0)
+            .unwrap();
+        Context {
+            clock: Clock::Fixed(now),
+            ..Default::default()
+        }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> Literal {
+        Literal::Date(NaiveDate::from_ymd_opt(year, month, day).unwrap())
+    }
+
+    fn date_time(date: &Literal, hour: u32, minute: u32, offset: Option<i32>) -> Literal {
+        let Literal::Date(date) = date else {
+            unreachable!()
+        };
+        Literal::DateTime(
+            date.and_hms_opt(hour, minute, 0).unwrap(),
+            offset.map(|hours| FixedOffset::east_opt(hours * 3600).unwrap()),
+        )
+    }
+
+    fn time(hour: u32, minute: u32) -> Literal {
+        Literal::Time(NaiveTime::from_hms_opt(hour, minute, 0).unwrap())
+    }
+
+    fn duration(value: Literal, unit: &str) -> Literal {
+        Literal::quantity(value, Unit::new(NamedUnit::find(unit).unwrap(), 1))
+    }
+
+    fn eval(fn_name: FunctionName, args: Vec<Literal>) -> ComputeResult<Literal> {
+        evaluate_fn(fn_name, args, &context())
+    }
+
+    #[test]
+    fn adding_durations() {
+        let test_cases = [
+            (
+                vec![date(2026, 10, 17), duration(int(3), "week")],
+                date(2026, 11, 7),
+            ),
+            (
+                vec![duration(int(1), "month"), date(2026, 1, 31)],
+                date(2026, 2, 28),
+            ),
+            (
+                vec![date(2026, 10, 17), duration(ratio(3, 2), "day")],
+                date_time(&date(2026, 10, 18), 12, 0, None),
+            ),
+            (
+                vec![
+                    date_time(&date(2026, 12, 31), 23, 0, Some(9)),
+                    duration(int(90), "min"),
+                ],
+                date_time(&date(2027, 1, 1), 0, 30, Some(9)),
+            ),
+            (vec![time(23, 30), duration(int(1), "h")], time(0, 30)),
+        ];
+        for (args, expected) in test_cases {
+            assert_eq!(eval(FunctionName::Add, args), Ok(expected));
+        }
+        assert_eq!(
+            eval(
+                FunctionName::Subtract,
+                vec![date(2024, 2, 29), duration(int(1), "year")]
+            ),
+            Ok(date(2023, 2, 28))
+        );
+    }
+
+    #[test]
+    fn differences() {
+        let christmas = date(2026, 12, 24);
+        let test_cases = [
+            (
+                vec![christmas.clone(), date(2026, 10, 17)],
+                duration(int(68), "day"),
+            ),
+            (vec![time(9, 0), time(17, 30)], duration(int(-510), "min")),
+            (
+                vec![
+                    date_time(&christmas, 12, 0, None),
+                    date_time(&christmas, 12, 0, Some(0)),
+                ],
+                duration(int(-2), "h"),
+            ),
+            (
+                vec![date_time(&christmas, 0, 45, None), christmas.clone()],
+                duration(int(45), "min"),
+            ),
+        ];
+        for (args, expected) in test_cases {
+            assert_eq!(eval(FunctionName::Subtract, args), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn comparisons() {
+        let day = date(2026, 10, 17);
+        assert_eq!(
+            eval(
+                FunctionName::Max,
+                vec![
+                    date_time(&day, 14, 0, None),
+                    date_time(&day, 13, 0, Some(0))
+                ]
+            ),
+            Ok(date_time(&day, 13, 0, Some(0)))
+        );
+        assert_eq!(
+            eval(FunctionName::Min, vec![time(8, 0), time(7, 59)]),
+            Ok(time(7, 59))
+        );
+        assert_eq!(
+            eval(FunctionName::Less, vec![day.clone(), date(2026, 10, 18)]),
+            Ok(Literal::Boolean(true))
+        );
+        assert_eq!(
+            eval(FunctionName::Equal, vec![day, time(8, 0)]),
+            Err(ComputeError::DateArgument(FunctionName::Equal))
+        );
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let test_cases = [
+            (
+                FunctionName::Add,
+                vec![date(2026, 1, 1), int(3)],
+                ComputeError::ExpectedDuration(int(3)),
+            ),
+            (
+                FunctionName::Add,
+                vec![date(2026, 1, 1), duration(int(3), "km")],
+                ComputeError::ExpectedDuration(duration(int(3), "km")),
+            ),
+            (
+                FunctionName::Add,
+                vec![date(2026, 1, 1), date(2026, 1, 1)],
+                ComputeError::DateArgument(FunctionName::Add),
+            ),
+            (
+                FunctionName::Subtract,
+                vec![time(12, 0), date(2026, 1, 1)],
+                ComputeError::DateArgument(FunctionName::Subtract),
+            ),
+            (
+                FunctionName::Multiply,
+                vec![date(2026, 1, 1), int(2)],
+                ComputeError::DateArgument(FunctionName::Multiply),
+            ),
+            (
+                FunctionName::Negate,
+                vec![date(2026, 1, 1)],
+                ComputeError::DateArgument(FunctionName::Negate),
+            ),
+        ];
+        for (fn_name, args, expected) in test_cases {
+            assert_eq!(eval(fn_name, args), Err(expected));
+        }
+    }
+
+    #[test]
+    fn time_zones() {
+        let utc_plus_9 = FixedOffset::east_opt(9 * 3600).unwrap();
+        let day = date(2026, 10, 17);
+        assert_eq!(
+            convert(date_time(&day, 14, 30, None), utc_plus_9, &context()),
+            Ok(date_time(&day, 21, 30, Some(9)))
+        );
+        assert_eq!(
+            convert(day.clone(), utc_plus_9, &context()),
+            Ok(date_time(&day, 7, 0, Some(9)))
+        );
+        assert_eq!(convert(time(20, 0), utc_plus_9, &context()), Ok(time(3, 0)));
+        assert_eq!(
+            convert(int(5), utc_plus_9, &context()),
+            Err(ComputeError::TimeZoneConversion)
+        );
+    }
+}
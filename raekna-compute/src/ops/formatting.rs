@@ -0,0 +1,66 @@
+use num_rational::{BigRational, Ratio};
+use raekna_common::expression::{Format, Literal};
+
+use crate::errors::{ComputeError, ComputeResult};
+
+/// Floats are approximated by the closest simple fraction
+pub fn fraction(value: Literal) -> Option<Literal> {
+    let value = match value {
+        Literal::Float(f) => {
+            let ratio = Ratio::<i64>::approximate_float(f)?;
+            let (numer, denom) = ratio.into_raw();
+            Literal::from(BigRational::new(numer.into(), denom.into()))
+        }
+        value => value,
+    };
+    Some(Literal::Formatted(Box::new(value), Format::Fraction))
+}
+
+pub fn base(value: Literal, format: Format) -> ComputeResult<Option<Literal>> {
+    match value.as_big_int() {
+        Some(_) => Ok(Some(Literal::Formatted(Box::new(value), format))),
+        None => Err(ComputeError::InvalidBaseArgument(value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::test_utils::{big, float, int, ratio};
+
+    #[test]
+    fn test_fraction() {
+        let test_cases = [
+            (ratio(1, 3), "1/3"),
+            (ratio(-7, 4), "-7/4"),
+            (int(5), "5"),
+            (float(0.75), "3/4"),
+        ];
+        for (value, expected) in test_cases.into_iter() {
+            let actual = fraction(value).unwrap();
+            assert_eq!(actual.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_base() {
+        let test_cases = [
+            (int(255), Format::Hexadecimal, "0xFF"),
+            (int(10), Format::Binary, "0b1010"),
+            (int(-8), Format::Octal, "-0o10"),
+            (
+                big("18446744073709551616"),
+                Format::Hexadecimal,
+                "0x10000000000000000",
+            ),
+        ];
+        for (value, format, expected) in test_cases.into_iter() {
+            let actual = base(value, format).unwrap().unwrap();
+            assert_eq!(actual.to_string(), expected);
+        }
+        assert_eq!(
+            base(float(2.5), Format::Binary),
+            Err(ComputeError::InvalidBaseArgument(float(2.5)))
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use raekna_common::{expression::Literal, function_name::FunctionName};
+
+use crate::{
+    errors::{ComputeError, ComputeResult},
+    ops::check_argument_count,
+};
+
+pub fn evaluate_fn(fn_name: FunctionName, args: Vec<Literal>) -> ComputeResult<Literal> {
+    let args = args
+        .into_iter()
+        .map(Literal::unformatted)
+        .collect::<Vec<_>>();
+    check_argument_count(fn_name, args.len())?;
+    match fn_name {
+        FunctionName::And | FunctionName::Or | FunctionName::Not => {
+            let result = evaluate(fn_name, &args)?;
+            Ok(result.expect("logic can't overflow"))
+        }
+        // Values of different kinds are never equal, so `true == 1` is false
+        FunctionName::Equal => Ok(Literal::Boolean(args[0] == args[1])),
+        FunctionName::NotEqual => Ok(Literal::Boolean(args[0] != args[1])),
+        _ => Err(ComputeError::BooleanArgument(fn_name)),
+    }
+}
+
+/// Numbers are true unless they're zero
+pub fn evaluate(fn_name: FunctionName, args: &[Literal]) -> ComputeResult<Option<Literal>> {
+    let values = args
+        .iter()
+        .map(|arg| arg.is_truthy().ok_or(ComputeError::LogicArgument(fn_name)))
+        .collect::<ComputeResult<Vec<_>>>()?;
+    let result = match (fn_name, values.as_slice()) {
+        (FunctionName::And, [left, right]) => *left && *right,
+        (FunctionName::Or, [left, right]) => *left || *right,
+        (FunctionName::Not, [value]) => !value,
+        _ => unreachable!("`{fn_name}` isn't a logic function"),
+    };
+    Ok(Some(Literal::Boolean(result)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::test_utils::{int, ratio};
+
+    fn boolean(value: bool) -> Literal {
+        Literal::Boolean(value)
+    }
+
+    #[test]
+    fn logic() {
+        let test_cases = [
+            (
+                FunctionName::And,
+                vec![boolean(true), boolean(false)],
+                false,
+            ),
+            (FunctionName::Or, vec![boolean(true), boolean(false)], true),
+            (FunctionName::Not, vec![boolean(false)], true),
+            (FunctionName::And, vec![boolean(true), int(2)], true),
+            (FunctionName::Or, vec![int(0), boolean(false)], false),
+            (
+                FunctionName::Equal,
+                vec![boolean(true), boolean(true)],
+                true,
+            ),
+            (FunctionName::Equal, vec![boolean(true), int(1)], false),
+            (
+                FunctionName::NotEqual,
+                vec![boolean(true), boolean(false)],
+                true,
+            ),
+        ];
+        for (fn_name, args, expected) in test_cases {
+            assert_eq!(evaluate_fn(fn_name, args), Ok(boolean(expected)));
+        }
+        assert_eq!(
+            evaluate(FunctionName::Not, &[ratio(1, 1_000_000)]),
+            Ok(Some(boolean(false)))
+        );
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert_eq!(
+            evaluate_fn(FunctionName::Add, vec![boolean(true), int(1)]),
+            Err(ComputeError::BooleanArgument(FunctionName::Add))
+        );
+        assert_eq!(
+            evaluate_fn(FunctionName::Less, vec![boolean(true), boolean(false)]),
+            Err(ComputeError::BooleanArgument(FunctionName::Less))
+        );
+        assert_eq!(
+            evaluate_fn(FunctionName::Not, vec![boolean(true), boolean(false)]),
+            Err(ComputeError::FunctionArgumentCount {
+                function_name: "not".to_owned(),
+                expected_argument_count: 1,
+                supplied_argument_count: 2,
+            })
+        );
+    }
+}
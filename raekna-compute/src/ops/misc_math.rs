@@ -1,22 +1,21 @@
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_traits::Signed;
 use raekna_common::expression::Literal;
 
-use super::validate_and_wrap;
+use super::{real_or_complex, validate_and_wrap, validate_and_wrap_big, validate_and_wrap_complex};
 use crate::errors::{ComputeError, ComputeResult};
 
-pub fn sqrt(value: Literal) -> ComputeResult<Option<Literal>> {
-    let raw = value.as_f64();
-    if raw < 0.0 {
-        Err(ComputeError::InvalidSquareRoot(value))
-    } else {
-        let root = raw.sqrt();
-        Ok(validate_and_wrap(root))
-    }
+/// About 16 000 digits
+const MAX_FACTORIAL: i64 = 5_000;
+
+pub fn sqrt(value: Literal) -> Option<Literal> {
+    real_or_complex(value, f64::sqrt, Complex64::sqrt)
 }
 
+/// The real root, `cbrt(-8)` is `-2`
 pub fn cbrt(value: Literal) -> Option<Literal> {
-    let value = value.as_f64();
-    let root = value.cbrt();
-    validate_and_wrap(root)
+    real_or_complex(value, f64::cbrt, Complex64::cbrt)
 }
 
 pub fn factorial(value: Literal) -> ComputeResult<Option<Literal>> {
@@ -32,6 +31,15 @@ pub fn factorial(value: Literal) -> ComputeResult<Option<Literal>> {
             }
             Ok(Some(Literal::Integer(result)))
         }
+        Literal::Integer(value) if value <= MAX_FACTORIAL => {
+            let result = (21..=value)
+                .fold(BigInt::from(2_432_902_008_176_640_000_i64), |acc, i| {
+                    acc * i
+                });
+            Ok(validate_and_wrap_big(result))
+        }
+        Literal::Integer(_) => Ok(None),
+        Literal::BigInteger(ref i) if i.is_positive() => Ok(None),
         _ => Err(ComputeError::InvalidFactorialArgument(value)),
     }
 }
@@ -45,32 +53,44 @@ pub fn log(value: Literal, base: Literal) -> Option<Literal> {
     } else {
         let v = value.as_f64();
         let result = v.log(b);
+        if result.is_nan() {
+            // Negative and complex values have complex logarithms
+            let result = value.as_complex().ln() / base.as_complex().ln();
+            return validate_and_wrap_complex(result);
+        }
         validate_and_wrap(result)
     }
 }
 
 pub fn log2(value: Literal) -> Option<Literal> {
-    let v = value.as_f64();
-    let result = v.log2();
-    validate_and_wrap(result)
+    real_or_complex(value, f64::log2, Complex64::log2)
 }
 
 pub fn log10(value: Literal) -> Option<Literal> {
-    let v = value.as_f64();
-    let result = v.log10();
-    validate_and_wrap(result)
+    real_or_complex(value, f64::log10, Complex64::log10)
 }
 
 pub fn ln(value: Literal) -> Option<Literal> {
-    let v = value.as_f64();
-    let result = v.ln();
-    validate_and_wrap(result)
+    real_or_complex(value, f64::ln, Complex64::ln)
 }
 
 pub fn abs(value: Literal) -> Option<Literal> {
     let result = match value {
-        Literal::Integer(i) => Literal::Integer(i.abs()),
+        Literal::Integer(i) => match i.checked_abs() {
+            Some(i) => Literal::Integer(i),
+            None => Literal::from(BigInt::from(i).abs()),
+        },
+        Literal::BigInteger(i) => Literal::from(i.abs()),
+        Literal::Rational(r) => Literal::Rational(r.abs()),
         Literal::Float(f) => Literal::from(f.abs()),
+        Literal::Complex(c) => return validate_and_wrap(c.norm()),
+        Literal::Quantity(value, unit) => Literal::quantity(abs(*value)?, unit),
+        Literal::Money(value, currency) => Literal::money(abs(*value)?, currency),
+        Literal::Percentage(value) => Literal::Percentage(Box::new(abs(*value)?)),
+        Literal::Date(_) | Literal::Time(_) | Literal::DateTime(_, _) | Literal::Boolean(_) => {
+            return None
+        }
+        Literal::Formatted(value, _) => return abs(*value),
     };
     Some(result)
 }
@@ -80,7 +100,20 @@ mod tests {
     use proptest::prelude::*;
 
     use super::*;
-    use crate::ops::test_utils::{float, int};
+    use crate::ops::test_utils::{big, complex, float, int};
+
+    #[test]
+    fn complex_results() {
+        use std::f64::consts::{LN_10, PI};
+
+        assert_eq!(sqrt(int(-4)), Some(complex(0.0, 2.0)));
+        assert_eq!(sqrt(complex(3.0, 4.0)), Some(complex(2.0, 1.0)));
+        assert_eq!(ln(int(-1)), Some(complex(0.0, PI)));
+        assert_eq!(log10(int(-100)), Some(complex(2.0, PI / LN_10)));
+        assert_eq!(log(int(-8), int(2)), Some(complex(3.0, PI / 2_f64.ln())));
+        assert_eq!(abs(complex(3.0, -4.0)), Some(int(5)));
+        assert_eq!(cbrt(int(-8)), Some(int(-2)));
+    }
 
     mod test_sqrt {
         use core::f64;
@@ -98,22 +131,20 @@ mod tests {
                 (float(3.5), float(1.8708286933869707)),
             ];
             for (value, expected) in test_cases.into_iter() {
-                let actual = sqrt(value).unwrap().unwrap();
+                let actual = sqrt(value).unwrap();
                 assert_eq!(actual, expected);
             }
         }
 
         #[test]
         fn negative_cases() {
-            let test_cases = [float(f64::NAN), float(f64::INFINITY)];
-            for value in test_cases.into_iter() {
-                assert!(sqrt(value).unwrap().is_none());
-            }
-
-            let test_cases = [int(-2), float(f64::NEG_INFINITY)];
+            let test_cases = [
+                float(f64::NAN),
+                float(f64::INFINITY),
+                float(f64::NEG_INFINITY),
+            ];
             for value in test_cases.into_iter() {
-                let err = sqrt(value).unwrap_err();
-                assert!(matches!(err, ComputeError::InvalidSquareRoot(_)));
+                assert!(sqrt(value).is_none());
             }
         }
 
@@ -127,16 +158,12 @@ mod tests {
                 let actual = sqrt(actual);
 
                 if f >= 0.0 {
-                    let actual = actual.unwrap().unwrap();
+                    let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
                 } else {
-                    let err = actual.unwrap_err();
-                    prop_assert!(
-                        matches!(
-                            err,
-                            ComputeError::InvalidSquareRoot(arg) if arg == Literal::Float(f)
-                        )
-                    );
+                    let expected = Literal::from(Complex64::new(0.0, (-f).sqrt()));
+                    let actual = actual.unwrap();
+                    prop_assert_eq!(actual, expected);
                 }
             }
 
@@ -149,16 +176,12 @@ mod tests {
                 let actual = sqrt(actual);
 
                 if i >= 0 {
-                    let actual = actual.unwrap().unwrap();
+                    let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
                 } else {
-                    let err = actual.unwrap_err();
-                    prop_assert!(
-                        matches!(
-                            err,
-                            ComputeError::InvalidSquareRoot(arg) if arg == Literal::Integer(i)
-                        )
-                    );
+                    let expected = Literal::from(Complex64::new(0.0, (-(i as f64)).sqrt()));
+                    let actual = actual.unwrap();
+                    prop_assert_eq!(actual, expected);
                 }
             }
         }
@@ -229,17 +252,25 @@ mod tests {
 
         #[test]
         fn negative_cases() {
-            let test_cases = [int(-2), int(21), float(1.1)];
+            let test_cases = [int(-2), big("-100000000000000000000"), float(1.1)];
             for value in test_cases.into_iter() {
                 factorial(value).unwrap_err();
             }
         }
 
+        #[test]
+        fn big_cases() {
+            let expected = big("30414093201713378043612608166064768844377641568960512000000000000");
+            assert_eq!(factorial(int(50)), Ok(Some(expected)));
+            assert_eq!(factorial(int(MAX_FACTORIAL + 1)), Ok(None));
+            assert_eq!(factorial(big("100000000000000000000")), Ok(None));
+        }
+
         proptest! {
             #[test]
             fn proptest_f64(value: f64) {
                 let value = Literal::Float(value);
-                let err = factorial(value).unwrap_err();
+                let err = factorial(value.clone()).unwrap_err();
                 assert!(matches!(
                     err,
                     ComputeError::InvalidFactorialArgument(v) if v == value
@@ -248,11 +279,12 @@ mod tests {
 
             #[test]
             fn proptest_i64(value: i64) {
+                prop_assume!(!(21..=MAX_FACTORIAL).contains(&value));
                 let raw = value;
                 let value = Literal::from(value);
                 let actual = factorial(value);
                 let expected = match raw {
-                     0 => Ok(Some(Literal::Integer(                        0))),
+                     0 => Ok(Some(Literal::Integer(                        1))),
                      1 => Ok(Some(Literal::Integer(                        1))),
                      2 => Ok(Some(Literal::Integer(                        2))),
                      3 => Ok(Some(Literal::Integer(                        6))),
@@ -273,6 +305,7 @@ mod tests {
                     18 => Ok(Some(Literal::Integer(    6_402_373_705_728_000))),
                     19 => Ok(Some(Literal::Integer(  121_645_100_408_832_000))),
                     20 => Ok(Some(Literal::Integer(2_432_902_008_176_640_000))),
+                    21.. => Ok(None),
                     _ => Err(ComputeError::InvalidFactorialArgument(Literal::Integer(raw)))
                 };
                 assert_eq!(actual, expected);
@@ -283,6 +316,11 @@ mod tests {
     mod test_log {
         use super::*;
 
+        fn complex_log(value: f64, base: f64) -> Option<Literal> {
+            let result = Complex64::new(value, 0.0).ln() / Complex64::new(base, 0.0).ln();
+            validate_and_wrap_complex(result)
+        }
+
         #[test]
         fn positive_cases() {
             let test_cases = [
@@ -306,11 +344,20 @@ mod tests {
         #[test]
         fn negative_cases() {
             let test_cases = [
-                // Value == 0
                 (int(0), int(5)),
                 (int(0), float(2.2)),
                 (float(0.0), int(6)),
                 (float(0.0), float(10.01)),
+            ];
+            for (value, base) in test_cases.into_iter() {
+                let result = log(value, base);
+                assert!(result.is_none());
+            }
+        }
+
+        #[test]
+        fn complex_cases() {
+            let test_cases = [
                 // Value < 0
                 (int(-1), int(7)),
                 (int(-2), float(8.9)),
@@ -323,8 +370,8 @@ mod tests {
                 (float(8.7), float(-2.1)),
             ];
             for (value, base) in test_cases.into_iter() {
-                let result = log(value, base);
-                assert!(result.is_none());
+                let result = log(value, base).unwrap();
+                assert!(result.is_complex());
             }
         }
 
@@ -336,8 +383,10 @@ mod tests {
 
                 let actual = log(Literal::Float(value), Literal::Float(base));
 
-                if value <= 0.0 || base < 0.0 {
+                if value == 0.0 {
                     assert!(actual.is_none());
+                } else if value < 0.0 || base < 0.0 {
+                    prop_assert_eq!(actual, complex_log(value, base));
                 } else {
                     let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
@@ -351,8 +400,10 @@ mod tests {
 
                 let actual = log(Literal::Float(value), Literal::Integer(base));
 
-                if value <= 0.0 || base < 0 {
+                if value == 0.0 {
                     assert!(actual.is_none());
+                } else if value < 0.0 || base < 0 {
+                    prop_assert_eq!(actual, complex_log(value, base as f64));
                 } else {
                     let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
@@ -366,8 +417,10 @@ mod tests {
 
                 let actual = log(Literal::Integer(value), Literal::Float(base));
 
-                if value <= 0 || base < 0.0 {
+                if value == 0 {
                     assert!(actual.is_none());
+                } else if value < 0 || base < 0.0 {
+                    prop_assert_eq!(actual, complex_log(value as f64, base));
                 } else {
                     let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
@@ -381,8 +434,10 @@ mod tests {
 
                 let actual = log(Literal::Integer(value), Literal::Integer(base));
 
-                if value <= 0 || base < 0 {
+                if value == 0 {
                     assert!(actual.is_none());
+                } else if value < 0 || base < 0 {
+                    prop_assert_eq!(actual, complex_log(value as f64, base as f64));
                 } else {
                     let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
@@ -410,18 +465,17 @@ mod tests {
 
         #[test]
         fn negative_cases() {
-            let test_cases = [
-                // Integers
-                int(-8),
-                int(0),
-                // Floats
-                float(-6.5),
-                float(0.0),
-            ];
+            let test_cases = [int(0), float(0.0)];
             for value in test_cases.into_iter() {
                 let result = log2(value);
                 assert!(result.is_none());
             }
+
+            let test_cases = [int(-8), float(-6.5)];
+            for value in test_cases.into_iter() {
+                let result = log2(value).unwrap();
+                assert!(result.is_complex());
+            }
         }
 
         proptest! {
@@ -432,8 +486,11 @@ mod tests {
 
                 let actual = log2(Literal::Float(value));
 
-                if value <= 0.0 {
+                if value == 0.0 {
                     assert!(actual.is_none());
+                } else if value < 0.0 {
+                    let expected = validate_and_wrap_complex(Complex64::new(value, 0.0).log2());
+                    prop_assert_eq!(actual, expected);
                 } else {
                     let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
@@ -447,8 +504,11 @@ mod tests {
 
                 let actual = log2(Literal::Integer(value));
 
-                if value <= 0 {
+                if value == 0 {
                     assert!(actual.is_none());
+                } else if value < 0 {
+                    let expected = validate_and_wrap_complex(Complex64::new(value as f64, 0.0).log2());
+                    prop_assert_eq!(actual, expected);
                 } else {
                     let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
@@ -476,18 +536,17 @@ mod tests {
 
         #[test]
         fn negative_cases() {
-            let test_cases = [
-                // Integers
-                int(-1000),
-                int(0),
-                // Floats
-                float(-100.55),
-                float(0.0),
-            ];
+            let test_cases = [int(0), float(0.0)];
             for value in test_cases.into_iter() {
                 let result = log10(value);
                 assert!(result.is_none());
             }
+
+            let test_cases = [int(-1000), float(-100.55)];
+            for value in test_cases.into_iter() {
+                let result = log10(value).unwrap();
+                assert!(result.is_complex());
+            }
         }
 
         proptest! {
@@ -498,8 +557,11 @@ mod tests {
 
                 let actual = log10(Literal::Float(value));
 
-                if value <= 0.0 {
+                if value == 0.0 {
                     assert!(actual.is_none());
+                } else if value < 0.0 {
+                    let expected = validate_and_wrap_complex(Complex64::new(value, 0.0).log10());
+                    prop_assert_eq!(actual, expected);
                 } else {
                     let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
@@ -513,8 +575,11 @@ mod tests {
 
                 let actual = log10(Literal::Integer(value));
 
-                if value <= 0 {
+                if value == 0 {
                     assert!(actual.is_none());
+                } else if value < 0 {
+                    let expected = validate_and_wrap_complex(Complex64::new(value as f64, 0.0).log10());
+                    prop_assert_eq!(actual, expected);
                 } else {
                     let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
@@ -542,18 +607,17 @@ mod tests {
 
         #[test]
         fn negative_cases() {
-            let test_cases = [
-                // Integers
-                int(-43),
-                int(0),
-                // Floats
-                float(-22.33),
-                float(0.0),
-            ];
+            let test_cases = [int(0), float(0.0)];
             for value in test_cases.into_iter() {
                 let result = ln(value);
                 assert!(result.is_none());
             }
+
+            let test_cases = [int(-43), float(-22.33)];
+            for value in test_cases.into_iter() {
+                let result = ln(value).unwrap();
+                assert!(result.is_complex());
+            }
         }
 
         proptest! {
@@ -564,8 +628,11 @@ mod tests {
 
                 let actual = ln(Literal::Float(value));
 
-                if value <= 0.0 {
+                if value == 0.0 {
                     assert!(actual.is_none());
+                } else if value < 0.0 {
+                    let expected = validate_and_wrap_complex(Complex64::new(value, 0.0).ln());
+                    prop_assert_eq!(actual, expected);
                 } else {
                     let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
@@ -579,8 +646,11 @@ mod tests {
 
                 let actual = ln(Literal::Integer(value));
 
-                if value <= 0 {
+                if value == 0 {
                     assert!(actual.is_none());
+                } else if value < 0 {
+                    let expected = validate_and_wrap_complex(Complex64::new(value as f64, 0.0).ln());
+                    prop_assert_eq!(actual, expected);
                 } else {
                     let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
@@ -1,69 +1,170 @@
-use raekna_common::{expression::Literal, function_name::FunctionName};
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use raekna_common::{
+    expression::{ConversionTarget, Format, Literal},
+    function_name::FunctionName,
+};
 
-use crate::errors::{ComputeError, ComputeResult};
+use crate::{
+    context::Context,
+    errors::{ComputeError, ComputeResult},
+};
 
+pub mod aggregates;
 mod arithmetic;
+mod bitwise;
 mod comparisons;
+mod complex;
 pub mod constants;
+mod dates;
+mod formatting;
+mod logic;
 mod misc_math;
+mod money;
+mod percentages;
 mod rounding;
 mod trigonometry;
+mod units;
 
-pub fn evaluate_fn(fn_name: FunctionName, args: Vec<Literal>) -> ComputeResult<Literal> {
-    if args.len() != fn_name.num_arguments() {
-        return Err(ComputeError::FunctionArgumentCount {
-            function_name: fn_name.to_string(),
-            expected_argument_count: fn_name.num_arguments(),
-            supplied_argument_count: args.len(),
-        });
+/// Roughly 30 000 digits
+const MAX_BITS: u64 = 100_000;
+
+pub fn evaluate_fn(
+    fn_name: FunctionName,
+    args: Vec<Literal>,
+    context: &Context,
+) -> ComputeResult<Literal> {
+    if args.iter().any(Literal::is_boolean) {
+        return logic::evaluate_fn(fn_name, args);
+    }
+    if args.iter().any(Literal::is_date) {
+        return dates::evaluate_fn(fn_name, args, context);
+    }
+    if fn_name == FunctionName::AsPercentOf || args.iter().any(Literal::is_percentage) {
+        return percentages::evaluate_fn(fn_name, args, context);
+    }
+    if args.iter().any(|arg| arg.currency().is_some()) {
+        let result = money::evaluate_fn(fn_name, args, &context.rates);
+        return explain_missing_rate(result, context);
+    }
+    evaluate_plain(fn_name, args)
+}
+
+fn explain_missing_rate(
+    result: ComputeResult<Literal>,
+    context: &Context,
+) -> ComputeResult<Literal> {
+    match (result, &context.rates_error) {
+        (Err(ComputeError::MissingExchangeRate(currency)), Some(reason)) => {
+            Err(ComputeError::RatesNotLoaded(currency, reason.clone()))
+        }
+        (result, _) => result,
+    }
+}
+
+fn evaluate_plain(fn_name: FunctionName, args: Vec<Literal>) -> ComputeResult<Literal> {
+    let args = args
+        .into_iter()
+        .map(Literal::unformatted)
+        .collect::<Vec<_>>();
+    check_argument_count(fn_name, args.len())?;
+    if args.iter().any(Literal::has_unit) {
+        return units::evaluate_fn(fn_name, args);
+    }
+    if !accepts_complex(fn_name) && args.iter().any(Literal::is_complex) {
+        return Err(ComputeError::ComplexArgument(fn_name));
     }
     let result = match fn_name {
         // Arithmetic
-        FunctionName::Negate => arithmetic::negate(args[0]),
-        FunctionName::Add => arithmetic::add(args[0], args[1]),
-        FunctionName::Subtract => arithmetic::sub(args[0], args[1]),
-        FunctionName::Multiply => arithmetic::mul(args[0], args[1]),
-        FunctionName::Divide => arithmetic::div(args[0], args[1])?,
-        FunctionName::Modulus => arithmetic::mod0(args[0], args[1])?,
-        FunctionName::Power => arithmetic::pow(args[0], args[1]),
+        FunctionName::Negate => arithmetic::negate(args[0].clone()),
+        FunctionName::Add => arithmetic::add(args[0].clone(), args[1].clone()),
+        FunctionName::Subtract => arithmetic::sub(args[0].clone(), args[1].clone()),
+        FunctionName::Multiply => arithmetic::mul(args[0].clone(), args[1].clone()),
+        FunctionName::Divide => arithmetic::div(args[0].clone(), args[1].clone())?,
+        FunctionName::Modulus => arithmetic::mod0(args[0].clone(), args[1].clone())?,
+        FunctionName::Power => arithmetic::pow(args[0].clone(), args[1].clone()),
 
         // Trigonometry
-        FunctionName::Sin => trigonometry::sin(args[0]),
-        FunctionName::Cos => trigonometry::cos(args[0]),
-        FunctionName::Tan => trigonometry::tan(args[0]),
-        FunctionName::SinH => trigonometry::sinh(args[0]),
-        FunctionName::CosH => trigonometry::cosh(args[0]),
-        FunctionName::TanH => trigonometry::tanh(args[0]),
-        FunctionName::ArcSin => trigonometry::asin(args[0]),
-        FunctionName::ArcCos => trigonometry::acos(args[0]),
-        FunctionName::ArcTan => trigonometry::atan(args[0]),
-        FunctionName::ArcSinH => trigonometry::asinh(args[0]),
-        FunctionName::ArcCosH => trigonometry::acosh(args[0]),
-        FunctionName::ArcTanH => trigonometry::atanh(args[0]),
+        FunctionName::Sin => trigonometry::sin(args[0].clone()),
+        FunctionName::Cos => trigonometry::cos(args[0].clone()),
+        FunctionName::Tan => trigonometry::tan(args[0].clone()),
+        FunctionName::SinH => trigonometry::sinh(args[0].clone()),
+        FunctionName::CosH => trigonometry::cosh(args[0].clone()),
+        FunctionName::TanH => trigonometry::tanh(args[0].clone()),
+        FunctionName::ArcSin => trigonometry::asin(args[0].clone()),
+        FunctionName::ArcCos => trigonometry::acos(args[0].clone()),
+        FunctionName::ArcTan => trigonometry::atan(args[0].clone()),
+        FunctionName::ArcSinH => trigonometry::asinh(args[0].clone()),
+        FunctionName::ArcCosH => trigonometry::acosh(args[0].clone()),
+        FunctionName::ArcTanH => trigonometry::atanh(args[0].clone()),
 
         // Misc math
-        FunctionName::SquareRoot => misc_math::sqrt(args[0])?,
-        FunctionName::CubeRoot => misc_math::cbrt(args[0]),
-        FunctionName::Factorial => misc_math::factorial(args[0])?,
-        FunctionName::Log => misc_math::log(args[0], args[1]),
-        FunctionName::Log2 => misc_math::log2(args[0]),
-        FunctionName::Log10 => misc_math::log10(args[0]),
-        FunctionName::Ln => misc_math::ln(args[0]),
-        FunctionName::Abs => misc_math::abs(args[0]),
+        FunctionName::SquareRoot => misc_math::sqrt(args[0].clone()),
+        FunctionName::CubeRoot => misc_math::cbrt(args[0].clone()),
+        FunctionName::Factorial => misc_math::factorial(args[0].clone())?,
+        FunctionName::Log => misc_math::log(args[0].clone(), args[1].clone()),
+        FunctionName::Log2 => misc_math::log2(args[0].clone()),
+        FunctionName::Log10 => misc_math::log10(args[0].clone()),
+        FunctionName::Ln => misc_math::ln(args[0].clone()),
+        FunctionName::Abs => misc_math::abs(args[0].clone()),
 
         // Rounding
-        FunctionName::Ceil => rounding::ceil(args[0]),
-        FunctionName::CeilPrec => rounding::ceilprec(args[0], args[1]),
-        FunctionName::Floor => rounding::floor(args[0]),
-        FunctionName::FloorPrec => rounding::floorprec(args[0], args[1]),
-        FunctionName::Round => rounding::round(args[0]),
-        FunctionName::RoundPrec => rounding::roundprec(args[0], args[1]),
-        FunctionName::Trunc => rounding::trunc(args[0]),
-        FunctionName::TruncPrec => rounding::truncprec(args[0], args[1])?,
+        FunctionName::Ceil => rounding::ceil(args[0].clone()),
+        FunctionName::CeilPrec => rounding::ceilprec(args[0].clone(), args[1].clone()),
+        FunctionName::Floor => rounding::floor(args[0].clone()),
+        FunctionName::FloorPrec => rounding::floorprec(args[0].clone(), args[1].clone()),
+        FunctionName::Round => rounding::round(args[0].clone()),
+        FunctionName::RoundPrec => rounding::roundprec(args[0].clone(), args[1].clone()),
+        FunctionName::Trunc => rounding::trunc(args[0].clone()),
+        FunctionName::TruncPrec => rounding::truncprec(args[0].clone(), args[1].clone())?,
 
         // Comparisons
-        FunctionName::Max => comparisons::max(args[0], args[1]),
-        FunctionName::Min => comparisons::min(args[0], args[1]),
+        FunctionName::Max => comparisons::max(args[0].clone(), args[1].clone()),
+        FunctionName::Min => comparisons::min(args[0].clone(), args[1].clone()),
+        FunctionName::Less
+        | FunctionName::LessEqual
+        | FunctionName::Greater
+        | FunctionName::GreaterEqual
+        | FunctionName::Equal
+        | FunctionName::NotEqual => comparisons::test(fn_name, &args[0], &args[1]),
+
+        // Logic
+        FunctionName::And | FunctionName::Or | FunctionName::Not => {
+            logic::evaluate(fn_name, &args)?
+        }
+        FunctionName::If => unreachable!("evaluated lazily by the evaluator"),
+
+        // Formatting
+        FunctionName::Fraction => formatting::fraction(args[0].clone()),
+        FunctionName::ToHex => formatting::base(args[0].clone(), Format::Hexadecimal)?,
+        FunctionName::ToBinary => formatting::base(args[0].clone(), Format::Binary)?,
+        FunctionName::ToOctal => formatting::base(args[0].clone(), Format::Octal)?,
+
+        // Complex numbers
+        FunctionName::RealPart => complex::re(args[0].clone()),
+        FunctionName::ImaginaryPart => complex::im(args[0].clone()),
+        FunctionName::Argument => complex::arg(args[0].clone()),
+        FunctionName::Conjugate => complex::conj(args[0].clone()),
+
+        // Percentages
+        FunctionName::Percent => Some(Literal::Percentage(Box::new(args[0].clone()))),
+        FunctionName::PercentOff | FunctionName::PercentOn => {
+            return Err(ComputeError::MissingPercentage(fn_name))
+        }
+        FunctionName::AsPercentOf => unreachable!("evaluated as a division"),
+
+        // Bitwise
+        FunctionName::BitAnd
+        | FunctionName::BitOr
+        | FunctionName::BitXor
+        | FunctionName::BitNot
+        | FunctionName::ShiftLeft
+        | FunctionName::ShiftRight
+        | FunctionName::PopCount
+        | FunctionName::Bit
+        | FunctionName::Unsigned(_)
+        | FunctionName::Signed(_) => bitwise::evaluate(fn_name, &args)?,
     };
     match result {
         Some(result) => Ok(result),
@@ -71,6 +172,72 @@ pub fn evaluate_fn(fn_name: FunctionName, args: Vec<Literal>) -> ComputeResult<L
     }
 }
 
+pub fn check_argument_count(fn_name: FunctionName, supplied: usize) -> ComputeResult<()> {
+    if supplied != fn_name.num_arguments() {
+        return Err(ComputeError::FunctionArgumentCount {
+            function_name: fn_name.to_string(),
+            expected_argument_count: fn_name.num_arguments(),
+            supplied_argument_count: supplied,
+        });
+    }
+    Ok(())
+}
+
+pub fn convert(
+    value: Literal,
+    target: &ConversionTarget,
+    context: &Context,
+) -> ComputeResult<Literal> {
+    if value.is_date() && !matches!(target, ConversionTarget::TimeZone(_)) {
+        return Err(ComputeError::DateConversion);
+    }
+    match target {
+        ConversionTarget::Unit(unit) => match value.currency() {
+            Some(currency) => Err(ComputeError::CurrencyMismatch(Some(currency), None)),
+            None => units::convert(value, unit),
+        },
+        ConversionTarget::Currency(currency) => {
+            explain_missing_rate(money::convert(value, currency, &context.rates), context)
+        }
+        ConversionTarget::TimeZone(offset) => dates::convert(value, *offset, context),
+        ConversionTarget::Format(format) => {
+            let fn_name = match format {
+                Format::Fraction => FunctionName::Fraction,
+                Format::Hexadecimal => FunctionName::ToHex,
+                Format::Binary => FunctionName::ToBinary,
+                Format::Octal => FunctionName::ToOctal,
+            };
+            evaluate_fn(fn_name, vec![value], context)
+        }
+    }
+}
+
+fn accepts_complex(fn_name: FunctionName) -> bool {
+    !matches!(
+        fn_name,
+        FunctionName::Modulus
+            | FunctionName::Factorial
+            | FunctionName::Ceil
+            | FunctionName::CeilPrec
+            | FunctionName::Floor
+            | FunctionName::FloorPrec
+            | FunctionName::Round
+            | FunctionName::RoundPrec
+            | FunctionName::Trunc
+            | FunctionName::TruncPrec
+            | FunctionName::Max
+            | FunctionName::Min
+            | FunctionName::Less
+            | FunctionName::LessEqual
+            | FunctionName::Greater
+            | FunctionName::GreaterEqual
+            | FunctionName::Percent
+            | FunctionName::ToHex
+            | FunctionName::ToBinary
+            | FunctionName::ToOctal
+    )
+}
+
 fn validate_and_wrap(value: f64) -> Option<Literal> {
     if value.is_normal() || value == 0.0 {
         Some(Literal::from(value))
@@ -79,6 +246,58 @@ fn validate_and_wrap(value: f64) -> Option<Literal> {
     }
 }
 
+fn validate_and_wrap_big(value: BigInt) -> Option<Literal> {
+    if value.bits() <= MAX_BITS {
+        Some(Literal::from(value))
+    } else {
+        None
+    }
+}
+
+fn validate_and_wrap_exact(value: BigRational) -> Option<Literal> {
+    if value.numer().bits() + value.denom().bits() <= MAX_BITS {
+        Some(Literal::from(value))
+    } else {
+        None
+    }
+}
+
+fn validate_and_wrap_complex(value: Complex64) -> Option<Literal> {
+    let is_valid = |part: f64| part.is_normal() || part == 0.0;
+    if is_valid(value.re) && is_valid(value.im) {
+        Some(Literal::from(value))
+    } else {
+        None
+    }
+}
+
+/// Uses `complex_op` for complex numbers and real numbers outside the domain of `op`
+fn real_or_complex(
+    value: Literal,
+    op: fn(f64) -> f64,
+    complex_op: fn(Complex64) -> Complex64,
+) -> Option<Literal> {
+    if !value.is_complex() {
+        let result = op(value.as_f64());
+        if !result.is_nan() {
+            return validate_and_wrap(result);
+        }
+    }
+    validate_and_wrap_complex(complex_op(value.as_complex()))
+}
+
+fn as_rationals(left: &Literal, right: &Literal) -> Option<(BigRational, BigRational)> {
+    Some((left.as_rational()?, right.as_rational()?))
+}
+
+fn as_complex_pair(left: &Literal, right: &Literal) -> Option<(Complex64, Complex64)> {
+    if left.is_complex() || right.is_complex() {
+        Some((left.as_complex(), right.as_complex()))
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod test_utils {
     use super::*;
@@ -90,4 +309,16 @@ mod test_utils {
     pub fn float(value: f64) -> Literal {
         Literal::Float(value)
     }
+
+    pub fn big(value: &str) -> Literal {
+        Literal::from(value.parse::<BigInt>().unwrap())
+    }
+
+    pub fn ratio(numer: i64, denom: i64) -> Literal {
+        Literal::from(BigRational::new(numer.into(), denom.into()))
+    }
+
+    pub fn complex(re: f64, im: f64) -> Literal {
+        Literal::from(Complex64::new(re, im))
+    }
 }
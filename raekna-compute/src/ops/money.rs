@@ -0,0 +1,312 @@
+use raekna_common::{
+    currency::{Currency, ExchangeRates},
+    expression::Literal,
+    function_name::FunctionName,
+};
+
+use crate::{
+    errors::{ComputeError, ComputeResult},
+    ops::evaluate_plain,
+};
+
+/// Amounts are exchanged into the currency of the left operand
+pub fn evaluate_fn(
+    fn_name: FunctionName,
+    args: Vec<Literal>,
+    rates: &ExchangeRates,
+) -> ComputeResult<Literal> {
+    if args.iter().any(Literal::has_unit) {
+        return Err(ComputeError::UnitArgument(fn_name));
+    }
+    if args.iter().any(Literal::is_complex) {
+        return Err(ComputeError::ComplexArgument(fn_name));
+    }
+    let mut args = args.into_iter().map(Literal::split_currency);
+    let (value, currency) = args.next().expect("functions take at least one argument");
+    match fn_name {
+        FunctionName::Add
+        | FunctionName::Subtract
+        | FunctionName::Modulus
+        | FunctionName::Max
+        | FunctionName::Min => {
+            let (right, right_currency) = args.next().expect("binary function");
+            let (currency, right_currency) = match (currency, right_currency) {
+                (Some(currency), Some(right_currency)) => (currency, right_currency),
+                (currency, right_currency) => {
+                    return Err(ComputeError::CurrencyMismatch(right_currency, currency))
+                }
+            };
+            let right = exchange(right, right_currency, currency, rates)?;
+            let value = evaluate_plain(fn_name, vec![value, right])?;
+            Ok(Literal::money(value, currency))
+        }
+        FunctionName::Less
+        | FunctionName::LessEqual
+        | FunctionName::Greater
+        | FunctionName::GreaterEqual
+        | FunctionName::Equal
+        | FunctionName::NotEqual => {
+            // A plain number is compared as if it was in the currency of the other operand, so
+            // `budget_left > 0` works
+            let right = match (currency, args.next().expect("binary function")) {
+                (Some(currency), (right, Some(right_currency))) => {
+                    exchange(right, right_currency, currency, rates)?
+                }
+                (_, (right, _)) => right,
+            };
+            evaluate_plain(fn_name, vec![value, right])
+        }
+        FunctionName::Multiply => match (currency, args.next().expect("binary function")) {
+            (Some(currency), (right, None)) | (None, (right, Some(currency))) => {
+                let value = evaluate_plain(fn_name, vec![value, right])?;
+                Ok(Literal::money(value, currency))
+            }
+            _ => Err(ComputeError::CurrencyArgument(fn_name)),
+        },
+        FunctionName::Divide => match (currency, args.next().expect("binary function")) {
+            (Some(currency), (right, None)) => {
+                let value = evaluate_plain(fn_name, vec![value, right])?;
+                Ok(Literal::money(value, currency))
+            }
+            (Some(currency), (right, Some(right_currency))) => {
+                let right = exchange(right, right_currency, currency, rates)?;
+                evaluate_plain(fn_name, vec![value, right])
+            }
+            (None, _) => Err(ComputeError::CurrencyArgument(fn_name)),
+        },
+        FunctionName::Negate
+        | FunctionName::Abs
+        | FunctionName::Ceil
+        | FunctionName::Floor
+        | FunctionName::Round
+        | FunctionName::Trunc
+        | FunctionName::Fraction
+        | FunctionName::ToHex
+        | FunctionName::ToBinary
+        | FunctionName::ToOctal => {
+            let currency = currency.expect("the only argument is an amount");
+            let value = evaluate_plain(fn_name, vec![value])?;
+            Ok(Literal::money(value, currency))
+        }
+        FunctionName::CeilPrec
+        | FunctionName::FloorPrec
+        | FunctionName::RoundPrec
+        | FunctionName::TruncPrec => match (currency, args.next().expect("binary function")) {
+            (Some(currency), (precision, None)) => {
+                let value = evaluate_plain(fn_name, vec![value, precision])?;
+                Ok(Literal::money(value, currency))
+            }
+            _ => Err(ComputeError::CurrencyArgument(fn_name)),
+        },
+        _ => Err(ComputeError::CurrencyArgument(fn_name)),
+    }
+}
+
+pub fn convert(
+    value: Literal,
+    to: &'static Currency,
+    rates: &ExchangeRates,
+) -> ComputeResult<Literal> {
+    match value.split_currency() {
+        (value, Some(from)) => {
+            let value = exchange(value, from, to, rates)?;
+            Ok(Literal::money(value, to))
+        }
+        (_, None) => Err(ComputeError::CurrencyMismatch(None, Some(to))),
+    }
+}
+
+fn exchange(
+    value: Literal,
+    from: &'static Currency,
+    to: &'static Currency,
+    rates: &ExchangeRates,
+) -> ComputeResult<Literal> {
+    if from == to {
+        return Ok(value);
+    }
+    let rate = rates
+        .rate(from, to)
+        .map_err(ComputeError::MissingExchangeRate)?;
+    evaluate_plain(FunctionName::Multiply, vec![value, Literal::from(rate)])
+}
+
+#[cfg(test)]
+mod tests {
+    use raekna_common::{
+        expression::Format,
+        unit::{NamedUnit, Unit},
+    };
+
+    use super::*;
+    use crate::ops::test_utils::{complex, int, ratio};
+
+    fn currency(code: &str) -> &'static Currency {
+        Currency::find(code).unwrap()
+    }
+
+    fn money(value: Literal, code: &str) -> Literal {
+        Literal::money(value, currency(code))
+    }
+
+    fn rates() -> ExchangeRates {
+        ExchangeRates::parse("base = SEK\nUSD = 10\nEUR = 11.5").unwrap()
+    }
+
+    #[test]
+    fn addition_exchanges_to_left_currency() {
+        let actual = evaluate_fn(
+            FunctionName::Add,
+            vec![money(int(100), "SEK"), money(int(2), "EUR")],
+            &rates(),
+        );
+        assert_eq!(actual, Ok(money(int(123), "SEK")));
+
+        let actual = evaluate_fn(
+            FunctionName::Max,
+            vec![money(int(10), "USD"), money(int(9), "EUR")],
+            &rates(),
+        );
+        assert_eq!(actual, Ok(money(ratio(207, 20), "USD")));
+    }
+
+    #[test]
+    fn comparisons_exchange_to_left_currency() {
+        let actual = evaluate_fn(
+            FunctionName::Greater,
+            vec![money(int(100), "SEK"), money(int(9), "EUR")],
+            &rates(),
+        );
+        assert_eq!(actual, Ok(Literal::Boolean(false)));
+
+        let actual = evaluate_fn(
+            FunctionName::Less,
+            vec![money(int(-150), "USD"), int(0)],
+            &rates(),
+        );
+        assert_eq!(actual, Ok(Literal::Boolean(true)));
+    }
+
+    #[test]
+    fn scaling_and_ratios() {
+        let actual = evaluate_fn(
+            FunctionName::Multiply,
+            vec![int(3), money(int(20), "EUR")],
+            &rates(),
+        );
+        assert_eq!(actual, Ok(money(int(60), "EUR")));
+
+        let actual = evaluate_fn(
+            FunctionName::Divide,
+            vec![money(int(10), "EUR"), int(4)],
+            &rates(),
+        );
+        assert_eq!(actual, Ok(money(ratio(5, 2), "EUR")));
+
+        let actual = evaluate_fn(
+            FunctionName::Divide,
+            vec![money(int(23), "SEK"), money(int(1), "EUR")],
+            &rates(),
+        );
+        assert_eq!(actual, Ok(int(2)));
+
+        let actual = evaluate_fn(
+            FunctionName::RoundPrec,
+            vec![money(ratio(10, 3), "USD"), int(1)],
+            &rates(),
+        );
+        assert_eq!(actual, Ok(money(ratio(33, 10), "USD")));
+
+        let actual = evaluate_fn(
+            FunctionName::Fraction,
+            vec![money(ratio(1, 3), "USD")],
+            &rates(),
+        );
+        assert_eq!(
+            actual,
+            Ok(money(
+                Literal::Formatted(Box::new(ratio(1, 3)), Format::Fraction),
+                "USD"
+            ))
+        );
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let test_cases = [
+            (
+                FunctionName::Add,
+                vec![money(int(1), "USD"), int(1)],
+                ComputeError::CurrencyMismatch(None, Some(currency("USD"))),
+            ),
+            (
+                FunctionName::Subtract,
+                vec![int(1), money(int(1), "USD")],
+                ComputeError::CurrencyMismatch(Some(currency("USD")), None),
+            ),
+            (
+                FunctionName::Multiply,
+                vec![money(int(1), "USD"), money(int(1), "USD")],
+                ComputeError::CurrencyArgument(FunctionName::Multiply),
+            ),
+            (
+                FunctionName::Divide,
+                vec![int(1), money(int(1), "USD")],
+                ComputeError::CurrencyArgument(FunctionName::Divide),
+            ),
+            (
+                FunctionName::Sin,
+                vec![money(int(1), "USD")],
+                ComputeError::CurrencyArgument(FunctionName::Sin),
+            ),
+            (
+                FunctionName::Multiply,
+                vec![money(int(1), "USD"), complex(0.0, 1.0)],
+                ComputeError::ComplexArgument(FunctionName::Multiply),
+            ),
+            (
+                FunctionName::Multiply,
+                vec![
+                    money(int(1), "USD"),
+                    Literal::quantity(int(1), Unit::new(NamedUnit::find("h").unwrap(), 1)),
+                ],
+                ComputeError::UnitArgument(FunctionName::Multiply),
+            ),
+        ];
+        for (fn_name, args, expected) in test_cases {
+            assert_eq!(evaluate_fn(fn_name, args, &rates()), Err(expected));
+        }
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(
+            convert(money(int(100), "USD"), currency("EUR"), &rates()),
+            Ok(money(ratio(2000, 23), "EUR"))
+        );
+        assert_eq!(
+            convert(money(int(5), "GBP"), currency("GBP"), &rates()),
+            Ok(money(int(5), "GBP"))
+        );
+        assert_eq!(
+            convert(int(100), currency("EUR"), &rates()),
+            Err(ComputeError::CurrencyMismatch(None, Some(currency("EUR"))))
+        );
+    }
+
+    #[test]
+    fn missing_rate_is_an_error() {
+        assert_eq!(
+            convert(money(int(100), "USD"), currency("GBP"), &rates()),
+            Err(ComputeError::MissingExchangeRate(currency("GBP")))
+        );
+        assert_eq!(
+            evaluate_fn(
+                FunctionName::Add,
+                vec![money(int(100), "JPY"), money(int(1), "SEK")],
+                &ExchangeRates::default(),
+            ),
+            Err(ComputeError::MissingExchangeRate(currency("SEK")))
+        );
+    }
+}
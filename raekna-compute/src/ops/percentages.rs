@@ -0,0 +1,208 @@
+use raekna_common::{expression::Literal, function_name::FunctionName};
+
+use crate::{
+    context::Context,
+    errors::{ComputeError, ComputeResult},
+    ops::evaluate_fn as evaluate,
+};
+
+/// Adding a percentage, `off` and `on` scale the value, elsewhere it's the fraction it represents
+pub fn evaluate_fn(
+    fn_name: FunctionName,
+    args: Vec<Literal>,
+    context: &Context,
+) -> ComputeResult<Literal> {
+    let args = args
+        .into_iter()
+        .map(Literal::unformatted)
+        .collect::<Vec<_>>();
+    match (fn_name, args.as_slice()) {
+        (
+            FunctionName::Add | FunctionName::Subtract | FunctionName::Max | FunctionName::Min,
+            [Literal::Percentage(left), Literal::Percentage(right)],
+        ) => {
+            let value = evaluate(fn_name, vec![*left.clone(), *right.clone()], context)?;
+            Ok(Literal::Percentage(Box::new(value)))
+        }
+        (FunctionName::Add, [value, Literal::Percentage(percent)])
+        | (FunctionName::PercentOn, [Literal::Percentage(percent), value]) => {
+            scale(value.clone(), FunctionName::Add, percent, context)
+        }
+        (FunctionName::Subtract, [value, Literal::Percentage(percent)])
+        | (FunctionName::PercentOff, [Literal::Percentage(percent), value]) => {
+            scale(value.clone(), FunctionName::Subtract, percent, context)
+        }
+        (FunctionName::PercentOn | FunctionName::PercentOff, _) => {
+            Err(ComputeError::MissingPercentage(fn_name))
+        }
+        (FunctionName::AsPercentOf, [part, whole]) => {
+            let ratio = evaluate(
+                FunctionName::Divide,
+                vec![
+                    fraction(part.clone(), context)?,
+                    fraction(whole.clone(), context)?,
+                ],
+                context,
+            )?;
+            let percent = evaluate(
+                FunctionName::Multiply,
+                vec![ratio, Literal::Integer(100)],
+                context,
+            )?;
+            evaluate(FunctionName::Percent, vec![percent], context)
+        }
+        (
+            FunctionName::Negate
+            | FunctionName::Abs
+            | FunctionName::Ceil
+            | FunctionName::Floor
+            | FunctionName::Round
+            | FunctionName::Trunc,
+            [Literal::Percentage(percent)],
+        ) => {
+            let value = evaluate(fn_name, vec![*percent.clone()], context)?;
+            Ok(Literal::Percentage(Box::new(value)))
+        }
+        (
+            FunctionName::CeilPrec
+            | FunctionName::FloorPrec
+            | FunctionName::RoundPrec
+            | FunctionName::TruncPrec,
+            [Literal::Percentage(percent), precision],
+        ) if !precision.is_percentage() => {
+            let value = evaluate(fn_name, vec![*percent.clone(), precision.clone()], context)?;
+            Ok(Literal::Percentage(Box::new(value)))
+        }
+        _ => {
+            let args = args
+                .into_iter()
+                .map(|arg| fraction(arg, context))
+                .collect::<ComputeResult<Vec<_>>>()?;
+            evaluate(fn_name, args, context)
+        }
+    }
+}
+
+fn scale(
+    value: Literal,
+    op: FunctionName,
+    percent: &Literal,
+    context: &Context,
+) -> ComputeResult<Literal> {
+    let percent = fraction(Literal::Percentage(Box::new(percent.clone())), context)?;
+    let factor = evaluate(op, vec![Literal::Integer(1), percent], context)?;
+    evaluate(FunctionName::Multiply, vec![value, factor], context)
+}
+
+fn fraction(value: Literal, context: &Context) -> ComputeResult<Literal> {
+    match value {
+        Literal::Percentage(percent) => evaluate(
+            FunctionName::Divide,
+            vec![*percent, Literal::Integer(100)],
+            context,
+        ),
+        value => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raekna_common::currency::Currency;
+
+    use super::*;
+    use crate::ops::test_utils::{float, int, ratio};
+
+    fn percent(value: Literal) -> Literal {
+        Literal::Percentage(Box::new(value))
+    }
+
+    fn eval(fn_name: FunctionName, args: Vec<Literal>) -> ComputeResult<Literal> {
+        evaluate_fn(fn_name, args, &Context::default())
+    }
+
+    #[test]
+    fn adding_a_percentage_scales_the_value() {
+        assert_eq!(
+            eval(FunctionName::Add, vec![int(200), percent(int(15))]),
+            Ok(int(230))
+        );
+        assert_eq!(
+            eval(
+                FunctionName::Subtract,
+                vec![float(80.0), percent(ratio(25, 2))]
+            ),
+            Ok(int(70))
+        );
+        assert_eq!(
+            eval(FunctionName::PercentOff, vec![percent(int(10)), int(120)]),
+            Ok(int(108))
+        );
+        assert_eq!(
+            eval(FunctionName::PercentOn, vec![percent(int(5)), int(30)]),
+            Ok(ratio(63, 2))
+        );
+    }
+
+    #[test]
+    fn percentages_keep_amounts() {
+        let usd = Currency::find("USD").unwrap();
+        assert_eq!(
+            eval(
+                FunctionName::Add,
+                vec![Literal::money(int(100), usd), percent(int(8))]
+            ),
+            Ok(Literal::money(int(108), usd))
+        );
+        assert_eq!(
+            eval(
+                FunctionName::AsPercentOf,
+                vec![Literal::money(int(50), usd), Literal::money(int(200), usd)]
+            ),
+            Ok(percent(int(25)))
+        );
+    }
+
+    #[test]
+    fn percentages_of_percentages() {
+        assert_eq!(
+            eval(FunctionName::Add, vec![percent(int(15)), percent(int(5))]),
+            Ok(percent(int(20)))
+        );
+        assert_eq!(
+            eval(FunctionName::Negate, vec![percent(int(15))]),
+            Ok(percent(int(-15)))
+        );
+        assert_eq!(
+            eval(FunctionName::Round, vec![percent(ratio(25, 2))]),
+            Ok(percent(int(13)))
+        );
+    }
+
+    #[test]
+    fn percentages_are_fractions_elsewhere() {
+        assert_eq!(
+            eval(FunctionName::Multiply, vec![percent(int(20)), int(80)]),
+            Ok(int(16))
+        );
+        assert_eq!(
+            eval(FunctionName::Add, vec![percent(int(20)), int(1)]),
+            Ok(ratio(6, 5))
+        );
+        assert_eq!(
+            eval(FunctionName::AsPercentOf, vec![int(50), int(200)]),
+            Ok(percent(int(25)))
+        );
+    }
+
+    #[test]
+    fn off_and_on_need_a_percentage() {
+        assert_eq!(
+            eval(FunctionName::PercentOff, vec![int(10), percent(int(120))]),
+            Err(ComputeError::MissingPercentage(FunctionName::PercentOff))
+        );
+        assert_eq!(
+            eval(FunctionName::AsPercentOf, vec![int(50), int(0)]),
+            Err(ComputeError::DivisionByZero)
+        );
+    }
+}
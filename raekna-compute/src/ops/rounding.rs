@@ -1,14 +1,51 @@
+use num_rational::BigRational;
+use num_traits::{Pow, ToPrimitive, Zero};
 use raekna_common::expression::Literal;
 
-use crate::{errors::ComputeResult, ComputeError};
+use crate::{errors::ComputeResult, ops::validate_and_wrap_exact, ComputeError};
 
-fn process<F>(value: Literal, precision: Option<Literal>, op: F) -> Option<Literal>
-where
-    F: Fn(f64) -> f64,
-{
+/// In either direction
+const MAX_PRECISION: i64 = 1_000;
+
+fn process(
+    value: Literal,
+    precision: Option<Literal>,
+    op: fn(f64) -> f64,
+    exact_op: fn(&BigRational) -> BigRational,
+) -> Option<Literal> {
+    if let Some(exact) = value.as_rational() {
+        let result = match &precision {
+            None => Some(exact_op(&exact)),
+            Some(Literal::Integer(_) | Literal::BigInteger(_)) => {
+                let precision = precision
+                    .as_ref()
+                    .and_then(Literal::as_big_int)
+                    .and_then(|p| p.to_i64())
+                    .unwrap_or(MAX_PRECISION)
+                    .clamp(-MAX_PRECISION, MAX_PRECISION);
+                let multiplier: BigRational =
+                    Pow::pow(BigRational::from_integer(10.into()), precision as i32);
+                Some(exact_op(&(exact * &multiplier)) / multiplier)
+            }
+            Some(Literal::Rational(stepping)) if !stepping.is_zero() => {
+                Some(exact_op(&(exact / stepping)) * stepping)
+            }
+            _ => None,
+        };
+        if let Some(result) = result {
+            return validate_and_wrap_exact(result);
+        }
+    }
     let value = value.as_f64();
     let result = match precision {
-        Some(Literal::Float(stepping)) => {
+        Some(Literal::Integer(precision)) => {
+            let multiplier = 10_f64.powi(precision as i32);
+            let result = value * multiplier;
+            let result = op(result);
+            result / multiplier
+        }
+        Some(stepping) => {
+            let stepping = stepping.as_f64();
             let precision = stepping.to_string().len();
             let precision = 10_u32.pow(precision as u32) as f64;
             let result = value / stepping;
@@ -16,50 +53,49 @@ where
             let result = op(result);
             result * stepping
         }
-        Some(Literal::Integer(precision)) => {
-            let multiplier = 10_i64.pow(precision as u32) as f64;
-            let result = value * multiplier;
-            let result = op(result);
-            result / multiplier
-        }
         None => op(value),
     };
     Some(Literal::from(result))
 }
 
 pub fn ceil(value: Literal) -> Option<Literal> {
-    process(value, None, f64::ceil)
+    process(value, None, f64::ceil, BigRational::ceil)
 }
 
 pub fn ceilprec(value: Literal, precision: Literal) -> Option<Literal> {
-    process(value, Some(precision), f64::ceil)
+    process(value, Some(precision), f64::ceil, BigRational::ceil)
 }
 
 pub fn floor(value: Literal) -> Option<Literal> {
-    process(value, None, f64::floor)
+    process(value, None, f64::floor, BigRational::floor)
 }
 
 pub fn floorprec(value: Literal, precision: Literal) -> Option<Literal> {
-    process(value, Some(precision), f64::floor)
+    process(value, Some(precision), f64::floor, BigRational::floor)
 }
 
 pub fn round(value: Literal) -> Option<Literal> {
-    process(value, None, f64::round)
+    process(value, None, f64::round, BigRational::round)
 }
 
 pub fn roundprec(value: Literal, precision: Literal) -> Option<Literal> {
-    process(value, Some(precision), f64::round)
+    process(value, Some(precision), f64::round, BigRational::round)
 }
 
 pub fn trunc(value: Literal) -> Option<Literal> {
-    process(value, None, f64::trunc)
+    process(value, None, f64::trunc, BigRational::trunc)
 }
 
 pub fn truncprec(value: Literal, precision: Literal) -> ComputeResult<Option<Literal>> {
-    if let Literal::Float(_) = precision {
+    if let Literal::Rational(_) | Literal::Float(_) = precision {
         return Err(ComputeError::InvalidTruncatePrecision(precision));
     }
-    Ok(process(value, Some(precision), f64::trunc))
+    Ok(process(
+        value,
+        Some(precision),
+        f64::trunc,
+        BigRational::trunc,
+    ))
 }
 
 #[cfg(test)]
@@ -283,4 +319,34 @@ mod tests {
             ComputeError::InvalidTruncatePrecision(Literal::Float(f)) if f == 0.2
         ));
     }
+
+    #[test]
+    fn test_big_integers() {
+        let value = Literal::from(
+            "123456789012345678901234567890"
+                .parse::<num_bigint::BigInt>()
+                .unwrap(),
+        );
+        assert_eq!(round(value.clone()), Some(value.clone()));
+        assert_eq!(
+            floorprec(value.clone(), Literal::Integer(2)),
+            Some(value.clone())
+        );
+        assert_eq!(
+            truncprec(value.clone(), Literal::Integer(0)),
+            Ok(Some(value))
+        );
+    }
+
+    #[test]
+    fn test_negative_precision() {
+        assert_eq!(
+            roundprec(Literal::Integer(1250), Literal::Integer(-2)),
+            Some(Literal::Integer(1300))
+        );
+        assert_eq!(
+            floorprec(Literal::Float(1299.5), Literal::Integer(-2)),
+            Some(Literal::Integer(1200))
+        );
+    }
 }
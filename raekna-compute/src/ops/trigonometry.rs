@@ -1,62 +1,54 @@
+use num_complex::Complex64;
 use raekna_common::expression::Literal;
 
-use crate::ops::validate_and_wrap;
-
-fn trig<F>(value: Literal, op: F) -> Option<Literal>
-where
-    F: Fn(f64) -> f64,
-{
-    let value = value.as_f64();
-    let result = op(value);
-    validate_and_wrap(result)
-}
+use crate::ops::real_or_complex;
 
 pub fn sin(value: Literal) -> Option<Literal> {
-    trig(value, f64::sin)
+    real_or_complex(value, f64::sin, Complex64::sin)
 }
 
 pub fn cos(value: Literal) -> Option<Literal> {
-    trig(value, f64::cos)
+    real_or_complex(value, f64::cos, Complex64::cos)
 }
 
 pub fn tan(value: Literal) -> Option<Literal> {
-    trig(value, f64::tan)
+    real_or_complex(value, f64::tan, Complex64::tan)
 }
 
 pub fn sinh(value: Literal) -> Option<Literal> {
-    trig(value, f64::sinh)
+    real_or_complex(value, f64::sinh, Complex64::sinh)
 }
 
 pub fn cosh(value: Literal) -> Option<Literal> {
-    trig(value, f64::cosh)
+    real_or_complex(value, f64::cosh, Complex64::cosh)
 }
 
 pub fn tanh(value: Literal) -> Option<Literal> {
-    trig(value, f64::tanh)
+    real_or_complex(value, f64::tanh, Complex64::tanh)
 }
 
 pub fn asin(value: Literal) -> Option<Literal> {
-    trig(value, f64::asin)
+    real_or_complex(value, f64::asin, Complex64::asin)
 }
 
 pub fn acos(value: Literal) -> Option<Literal> {
-    trig(value, f64::acos)
+    real_or_complex(value, f64::acos, Complex64::acos)
 }
 
 pub fn atan(value: Literal) -> Option<Literal> {
-    trig(value, f64::atan)
+    real_or_complex(value, f64::atan, Complex64::atan)
 }
 
 pub fn asinh(value: Literal) -> Option<Literal> {
-    trig(value, f64::asinh)
+    real_or_complex(value, f64::asinh, Complex64::asinh)
 }
 
 pub fn acosh(value: Literal) -> Option<Literal> {
-    trig(value, f64::acosh)
+    real_or_complex(value, f64::acosh, Complex64::acosh)
 }
 
 pub fn atanh(value: Literal) -> Option<Literal> {
-    trig(value, f64::atanh)
+    real_or_complex(value, f64::atanh, Complex64::atanh)
 }
 
 #[cfg(test)]
@@ -245,6 +237,8 @@ mod tests {
                     if expected_raw.is_normal() || expected_raw == 0.0 {
                         let actual = actual.unwrap();
                         prop_assert_eq!(actual, expected);
+                    } else if expected_raw.is_nan() {
+                        prop_assert!(actual.unwrap().is_complex());
                     } else {
                         assert!(actual.is_none());
                     }
@@ -302,6 +296,8 @@ mod tests {
                     if expected_raw.is_normal() || expected_raw == 0.0 {
                         let actual = actual.unwrap();
                         prop_assert_eq!(actual, expected);
+                    } else if expected_raw.is_nan() {
+                        prop_assert!(actual.unwrap().is_complex());
                     } else {
                         assert!(actual.is_none());
                     }
@@ -408,7 +404,7 @@ mod tests {
         }
 
         #[test]
-        fn negative_cases() {
+        fn complex_cases() {
             let test_cases = [
                 Literal::Integer(-2),
                 Literal::Integer(-10),
@@ -420,8 +416,8 @@ mod tests {
                 Literal::Float(25.5),
             ];
             test_cases.into_iter().for_each(|input| {
-                let actual = asin(input);
-                assert!(actual.is_none());
+                let actual = asin(input).unwrap();
+                assert!(actual.is_complex());
             });
         }
 
@@ -480,7 +476,7 @@ mod tests {
         }
 
         #[test]
-        fn negative_cases() {
+        fn complex_cases() {
             let test_cases = [
                 Literal::Integer(-2),
                 Literal::Integer(-10),
@@ -492,8 +488,8 @@ mod tests {
                 Literal::Float(25.5),
             ];
             test_cases.into_iter().for_each(|input| {
-                let actual = acos(input);
-                assert!(actual.is_none());
+                let actual = acos(input).unwrap();
+                assert!(actual.is_complex());
             });
         }
 
@@ -604,6 +600,8 @@ mod tests {
                     if expected_raw.is_normal() || expected_raw == 0.0 {
                         let actual = actual.unwrap();
                         prop_assert_eq!(actual, expected);
+                    } else if expected_raw.is_nan() {
+                        prop_assert!(actual.unwrap().is_complex());
                     } else {
                         assert!(actual.is_none());
                     }
@@ -643,7 +641,7 @@ mod tests {
         }
 
         #[test]
-        fn negative_cases() {
+        fn complex_cases() {
             let test_cases = [
                 Literal::Integer(-10),
                 Literal::Integer(-1),
@@ -654,8 +652,8 @@ mod tests {
                 Literal::Float(0.99999),
             ];
             test_cases.into_iter().for_each(|input| {
-                let actual = acosh(input);
-                assert!(actual.is_none());
+                let actual = acosh(input).unwrap();
+                assert!(actual.is_complex());
             });
         }
 
@@ -672,6 +670,8 @@ mod tests {
                     if expected_raw.is_normal() || expected_raw == 0.0 {
                         let actual = actual.unwrap();
                         prop_assert_eq!(actual, expected);
+                    } else if expected_raw.is_nan() {
+                        prop_assert!(actual.unwrap().is_complex());
                     } else {
                         assert!(actual.is_none());
                     }
@@ -689,6 +689,8 @@ mod tests {
                 if expected_raw.is_normal() || expected_raw == 0.0 {
                     let actual = actual.unwrap();
                     prop_assert_eq!(actual, expected);
+                } else if expected_raw.is_nan() {
+                    prop_assert!(actual.unwrap().is_complex());
                 } else {
                     assert!(actual.is_none());
                 }
@@ -719,18 +721,25 @@ mod tests {
         fn negative_cases() {
             let test_cases = [
                 Literal::Integer(-1),
-                Literal::Integer(-10),
                 Literal::Integer(1),
-                Literal::Integer(12),
-                Literal::Float(-4.0),
                 Literal::Float(-1.0),
                 Literal::Float(1.0),
-                Literal::Float(25.5),
             ];
             test_cases.into_iter().for_each(|input| {
                 let actual = atanh(input);
                 assert!(actual.is_none());
             });
+
+            let test_cases = [
+                Literal::Integer(-10),
+                Literal::Integer(12),
+                Literal::Float(-4.0),
+                Literal::Float(25.5),
+            ];
+            test_cases.into_iter().for_each(|input| {
+                let actual = atanh(input).unwrap();
+                assert!(actual.is_complex());
+            });
         }
 
         proptest! {
@@ -0,0 +1,285 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Zero};
+use raekna_common::{expression::Literal, function_name::FunctionName, unit::Unit};
+
+use crate::{
+    errors::{ComputeError, ComputeResult},
+    ops::evaluate_plain,
+};
+
+/// Additions and comparisons convert into the unit of the left operand, unless only the right unit
+/// gives an exact decimal like `1 week + 2 days` is `9 days`
+pub fn evaluate_fn(fn_name: FunctionName, args: Vec<Literal>) -> ComputeResult<Literal> {
+    let mut args = args.into_iter().map(Literal::split_unit);
+    let (value, unit) = args.next().expect("functions take at least one argument");
+    match fn_name {
+        FunctionName::Add
+        | FunctionName::Subtract
+        | FunctionName::Modulus
+        | FunctionName::Max
+        | FunctionName::Min => {
+            let (right, right_unit) = args.next().expect("binary function");
+            let right = convert_value(right, &right_unit, &unit)?;
+            let value = evaluate_plain(fn_name, vec![value, right])?;
+            if is_repeating_decimal(&value) && unit.offset().is_none() {
+                let in_right_unit = convert_value(value.clone(), &unit, &right_unit)?;
+                if !is_repeating_decimal(&in_right_unit) {
+                    return Ok(Literal::quantity(in_right_unit, right_unit));
+                }
+            }
+            Ok(Literal::quantity(value, unit))
+        }
+        FunctionName::Less
+        | FunctionName::LessEqual
+        | FunctionName::Greater
+        | FunctionName::GreaterEqual
+        | FunctionName::Equal
+        | FunctionName::NotEqual => {
+            // A plain number is compared as if it was in the unit of the other operand
+            let (right, right_unit) = args.next().expect("binary function");
+            let right = if unit.is_empty() || right_unit.is_empty() {
+                right
+            } else {
+                convert_value(right, &right_unit, &unit)?
+            };
+            evaluate_plain(fn_name, vec![value, right])
+        }
+        FunctionName::Multiply | FunctionName::Divide => {
+            let (right, right_unit) = args.next().expect("binary function");
+            let right_unit = match fn_name {
+                FunctionName::Divide => right_unit.pow(-1),
+                _ => right_unit,
+            };
+            let (unit, factor) = unit.mul(&right_unit);
+            let value = evaluate_plain(fn_name, vec![value, right])?;
+            let value = scale(value, factor)?;
+            Ok(Literal::quantity(value, unit))
+        }
+        FunctionName::Power => match args.next().expect("binary function") {
+            (Literal::Integer(exponent), exponent_unit)
+                if exponent_unit.is_empty() && i32::try_from(exponent).is_ok() =>
+            {
+                let value = evaluate_plain(fn_name, vec![value, Literal::Integer(exponent)])?;
+                Ok(Literal::quantity(value, unit.pow(exponent as i32)))
+            }
+            _ => Err(ComputeError::UnitArgument(fn_name)),
+        },
+        FunctionName::SquareRoot => match unit.root(2) {
+            Some(unit) => {
+                let value = evaluate_plain(fn_name, vec![value])?;
+                Ok(Literal::quantity(value, unit))
+            }
+            None => Err(ComputeError::UnitArgument(fn_name)),
+        },
+        FunctionName::Negate
+        | FunctionName::Abs
+        | FunctionName::Ceil
+        | FunctionName::Floor
+        | FunctionName::Round
+        | FunctionName::Trunc
+        | FunctionName::Fraction
+        | FunctionName::ToHex
+        | FunctionName::ToBinary
+        | FunctionName::ToOctal
+        | FunctionName::RealPart
+        | FunctionName::ImaginaryPart
+        | FunctionName::Conjugate => {
+            let value = evaluate_plain(fn_name, vec![value])?;
+            Ok(Literal::quantity(value, unit))
+        }
+        FunctionName::CeilPrec
+        | FunctionName::FloorPrec
+        | FunctionName::RoundPrec
+        | FunctionName::TruncPrec => match args.next().expect("binary function") {
+            (precision, precision_unit) if precision_unit.is_empty() && !unit.is_empty() => {
+                let value = evaluate_plain(fn_name, vec![value, precision])?;
+                Ok(Literal::quantity(value, unit))
+            }
+            _ => Err(ComputeError::UnitArgument(fn_name)),
+        },
+        _ => Err(ComputeError::UnitArgument(fn_name)),
+    }
+}
+
+pub fn convert(value: Literal, to: &Unit) -> ComputeResult<Literal> {
+    let (value, from) = value.split_unit();
+    let value = match (from.offset(), to.offset()) {
+        (None, None) => convert_value(value, &from, to)?,
+        (from_offset, to_offset) => {
+            // Temperatures like °C are converted through the absolute base unit
+            let from_offset = Literal::from(from_offset.unwrap_or_else(BigRational::zero));
+            let to_offset = Literal::from(to_offset.unwrap_or_else(BigRational::zero));
+            let value = evaluate_plain(FunctionName::Add, vec![value, from_offset])?;
+            let value = convert_value(value, &from, to)?;
+            evaluate_plain(FunctionName::Subtract, vec![value, to_offset])?
+        }
+    };
+    Ok(Literal::quantity(value, to.clone()))
+}
+
+fn convert_value(value: Literal, from: &Unit, to: &Unit) -> ComputeResult<Literal> {
+    let factor = from
+        .conversion_factor(to)
+        .ok_or_else(|| ComputeError::IncompatibleUnits(from.clone(), to.clone()))?;
+    scale(value, factor)
+}
+
+fn is_repeating_decimal(value: &Literal) -> bool {
+    let Literal::Rational(value) = value else {
+        return false;
+    };
+    let mut denom = value.denom().clone();
+    for factor in [BigInt::from(2), BigInt::from(5)] {
+        while (&denom % &factor).is_zero() {
+            denom /= &factor;
+        }
+    }
+    !denom.is_one()
+}
+
+fn scale(value: Literal, factor: BigRational) -> ComputeResult<Literal> {
+    if factor.is_one() {
+        Ok(value)
+    } else {
+        evaluate_plain(FunctionName::Multiply, vec![value, Literal::from(factor)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raekna_common::unit::NamedUnit;
+
+    use super::*;
+    use crate::ops::test_utils::{float, int, ratio};
+
+    fn unit(name: &str) -> Unit {
+        Unit::new(NamedUnit::find(name).unwrap(), 1)
+    }
+
+    fn quantity(value: Literal, name: &str) -> Literal {
+        Literal::quantity(value, unit(name))
+    }
+
+    #[test]
+    fn addition_converts_to_left_unit() {
+        let actual = evaluate_fn(
+            FunctionName::Add,
+            vec![quantity(int(5), "km"), quantity(int(300), "m")],
+        );
+        assert_eq!(actual, Ok(quantity(ratio(53, 10), "km")));
+
+        let actual = evaluate_fn(
+            FunctionName::Add,
+            vec![quantity(int(1), "week"), quantity(int(2), "day")],
+        );
+        assert_eq!(actual, Ok(quantity(int(9), "day")));
+
+        let actual = evaluate_fn(
+            FunctionName::Subtract,
+            vec![quantity(int(1), "h"), quantity(int(20), "min")],
+        );
+        assert_eq!(actual, Ok(quantity(int(40), "min")));
+    }
+
+    #[test]
+    fn comparisons_convert_to_left_unit() {
+        let actual = evaluate_fn(
+            FunctionName::Equal,
+            vec![quantity(int(5), "m"), quantity(int(500), "cm")],
+        );
+        assert_eq!(actual, Ok(Literal::Boolean(true)));
+
+        let actual = evaluate_fn(
+            FunctionName::GreaterEqual,
+            vec![int(0), quantity(int(3), "s")],
+        );
+        assert_eq!(actual, Ok(Literal::Boolean(false)));
+
+        let actual = evaluate_fn(
+            FunctionName::Less,
+            vec![quantity(int(5), "km"), quantity(int(3), "s")],
+        );
+        assert_eq!(
+            actual,
+            Err(ComputeError::IncompatibleUnits(unit("s"), unit("km")))
+        );
+    }
+
+    #[test]
+    fn incompatible_units() {
+        let actual = evaluate_fn(
+            FunctionName::Add,
+            vec![quantity(int(5), "km"), quantity(int(3), "s")],
+        );
+        assert_eq!(
+            actual,
+            Err(ComputeError::IncompatibleUnits(unit("s"), unit("km")))
+        );
+
+        let actual = evaluate_fn(FunctionName::Subtract, vec![quantity(int(5), "km"), int(3)]);
+        assert_eq!(
+            actual,
+            Err(ComputeError::IncompatibleUnits(Unit::default(), unit("km")))
+        );
+
+        let actual = evaluate_fn(FunctionName::Sin, vec![quantity(int(5), "km")]);
+        assert_eq!(actual, Err(ComputeError::UnitArgument(FunctionName::Sin)));
+    }
+
+    #[test]
+    fn multiplication_combines_units() {
+        let actual = evaluate_fn(
+            FunctionName::Multiply,
+            vec![quantity(int(2), "m"), quantity(int(3), "m")],
+        );
+        assert_eq!(actual, Ok(Literal::quantity(int(6), unit("m").pow(2))));
+
+        let actual = evaluate_fn(
+            FunctionName::Divide,
+            vec![quantity(int(2), "km"), quantity(int(500), "m")],
+        );
+        assert_eq!(actual, Ok(int(4)));
+
+        let actual = evaluate_fn(FunctionName::Multiply, vec![int(3), quantity(int(2), "h")]);
+        assert_eq!(actual, Ok(quantity(int(6), "h")));
+    }
+
+    #[test]
+    fn powers_and_roots() {
+        let area = Literal::quantity(int(16), unit("m").pow(2));
+        assert_eq!(
+            evaluate_fn(FunctionName::Power, vec![quantity(int(4), "m"), int(2)]),
+            Ok(area.clone())
+        );
+        assert_eq!(
+            evaluate_fn(FunctionName::SquareRoot, vec![area]),
+            Ok(quantity(int(4), "m"))
+        );
+        assert_eq!(
+            evaluate_fn(FunctionName::Power, vec![quantity(int(4), "m"), float(0.5)]),
+            Err(ComputeError::UnitArgument(FunctionName::Power))
+        );
+    }
+
+    #[test]
+    fn conversions() {
+        let (mps, _) = unit("m").mul(&unit("s").pow(-1));
+        assert_eq!(
+            convert(quantity(int(60), "mph"), &mps),
+            Ok(Literal::quantity(ratio(16_764, 625), mps))
+        );
+        assert_eq!(
+            convert(quantity(int(100), "°C"), &unit("°F")),
+            Ok(quantity(int(212), "°F"))
+        );
+        assert_eq!(
+            convert(quantity(int(0), "K"), &unit("°C")),
+            Ok(quantity(ratio(-27_315, 100), "°C"))
+        );
+        assert_eq!(
+            convert(int(5), &unit("m")),
+            Err(ComputeError::IncompatibleUnits(Unit::default(), unit("m")))
+        );
+    }
+}
@@ -1,19 +1,125 @@
-use nom::error::Error;
-use raekna_common::expression::Expression;
+use raekna_common::{expression::Expression, span::Span};
 
 use crate::lexer::Operator;
 
 pub type ParserResult<T> = Result<T, ParserError>;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum ParserError {
     EmptyExpression,
     InvalidExpression {
         expressions: Vec<Option<Expression>>,
         operators: Vec<Operator>,
+        span: Span,
     },
-    InvalidSign(char),
-    UnknownFunctionName(String),
-    InvalidVariableDefinition(String),
-    NomError(nom::Err<Error<()>>),
+    InvalidSign(char, Span),
+    InvalidVariableDefinition(String, Span),
+    /// E.g. a variable named `prev`
+    LineReferenceName(String, Span),
+    InvalidConversion(Span),
+    /// E.g. `2026-02-30` or `25:00`
+    InvalidDate(String, Span),
+    InvalidSyntax(Span),
+}
+
+impl ParserError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::EmptyExpression => None,
+            Self::InvalidExpression { span, .. }
+            | Self::InvalidSign(_, span)
+            | Self::InvalidVariableDefinition(_, span)
+            | Self::LineReferenceName(_, span)
+            | Self::InvalidConversion(span)
+            | Self::InvalidDate(_, span)
+            | Self::InvalidSyntax(span) => Some(*span),
+        }
+    }
+}
+
+impl std::fmt::Display for ParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::EmptyExpression => write!(f, "empty expression"),
+            Self::InvalidExpression {
+                expressions,
+                operators,
+                ..
+            } => {
+                if expressions.len() > operators.len() {
+                    write!(f, "missing operator")
+                } else {
+                    write!(f, "missing operand")
+                }
+            }
+            Self::InvalidSign(sign, _) => write!(f, "unexpected `{sign}`"),
+            Self::InvalidVariableDefinition(name, _) => {
+                write!(f, "`{name}:` must be at the start of the line")
+            }
+            Self::LineReferenceName(name, _) => {
+                write!(f, "`{name}` refers to a line and can't be defined")
+            }
+            Self::InvalidConversion(_) => {
+                write!(f, "conversions must be at the end of the expression")
+            }
+            Self::InvalidDate(text, _) if text.contains('-') => {
+                write!(f, "`{text}` isn't a valid date")
+            }
+            Self::InvalidDate(text, _) => write!(f, "`{text}` isn't a valid time"),
+            Self::InvalidSyntax(_) => write!(f, "invalid syntax"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn display() {
+        let test_cases = [
+            ("1 +", "missing operand"),
+            ("* 2", "unexpected `*`"),
+            ("2 + f(x): x", "`f(x):` must be at the start of the line"),
+            ("2 + a: 5", "`a:` must be at the start of the line"),
+            ("prev: 5", "`prev` refers to a line and can't be defined"),
+            (
+                "f(x, line2): x",
+                "`line2` refers to a line and can't be defined",
+            ),
+            (
+                "5 km in m + 1",
+                "conversions must be at the end of the expression",
+            ),
+            ("1 $ 2", "invalid syntax"),
+            ("2026-02-29", "`2026-02-29` isn't a valid date"),
+            ("2026-12-24 25:00", "`25:00` isn't a valid time"),
+        ];
+        for (input, expected) in test_cases {
+            let error = parse(input).unwrap_err();
+            assert_eq!(error.to_string(), expected, "input: {input}");
+        }
+    }
+
+    #[test]
+    fn span() {
+        let test_cases = [
+            ("1 +", Span::at(3)),
+            ("1 + 2 3", Span::new(6, 7)),
+            ("1 + * 2", Span::new(4, 5)),
+            ("* 2", Span::new(0, 1)),
+            ("2 * f(x): x", Span::new(4, 9)),
+            ("2 + a: 5", Span::new(4, 6)),
+            ("5 km to m * 2", Span::new(5, 9)),
+            ("1 $ 2", Span::new(2, 3)),
+            ("max(1, (2 $ 3))", Span::new(10, 11)),
+            ("max(1, , 3)", Span::new(7, 8)),
+            ("1 + 2026-13-45 * 2", Span::new(4, 14)),
+        ];
+        for (input, expected) in test_cases {
+            let error = parse(input).unwrap_err();
+            assert_eq!(error.span(), Some(expected), "input: {input}");
+        }
+    }
 }
@@ -1,82 +1,188 @@
+use chrono::{FixedOffset, NaiveDate, NaiveTime};
+use currency_parsers::*;
+use date_parsers::*;
 use nom::{
     branch::alt,
-    bytes::complete::{is_not, tag, take_while},
-    character::complete::{char, one_of},
-    combinator::{map, map_res, opt, recognize, verify},
-    error::{ErrorKind, ParseError},
+    bytes::complete::{tag, take_while, take_while_m_n},
+    character::complete::{char, digit1, one_of, satisfy},
+    combinator::{all_consuming, map, map_opt, map_res, opt, recognize, verify},
+    error::{Error, ErrorKind, ParseError},
     multi::{many0, many1, separated_list0},
-    sequence::{pair, preceded, terminated},
-    AsChar, IResult, Parser,
+    sequence::{delimited, pair, preceded, terminated},
+    AsChar, IResult, Offset, Parser,
 };
+use num_bigint::{BigInt, ParseBigIntError};
+use num_rational::BigRational;
 use number_parsers::*;
-use raekna_common::expression::Literal;
+use raekna_common::{
+    currency::Currency,
+    expression::{ConversionTarget, Format, LineReference, Literal},
+    unit::{NamedUnit, Unit},
+};
 use text_parsers::*;
+use unit_parsers::*;
 
 use crate::lexer::{
     token::{Operator, Token},
     token_tree::TokenTree,
 };
 
+/// Parses a number with an optional unit or currency, or an amount like `€20`
 pub fn parse_number(input: &str) -> IResult<&str, Token> {
     let (remaining, literal) = preceded(
         whitespace,
         alt((
-            map(scientific_notation, |f| {
-                if f.fract().abs() < f64::EPSILON {
-                    Literal::Integer(f as i64)
-                } else {
-                    Literal::Float(f)
-                }
+            map(pair(currency_symbol, number), |(currency, value)| {
+                Literal::money(value, currency)
             }),
-            map(float, |f| {
-                if f.fract().abs() < f64::EPSILON {
-                    Literal::Integer(f as i64)
-                } else {
-                    Literal::Float(f)
-                }
+            map(pair(number, currency_code), |(value, currency)| {
+                Literal::money(value, currency)
+            }),
+            compound_quantity,
+            map(pair(number, opt(unit)), |(value, unit)| {
+                Literal::quantity(value, unit.unwrap_or_default())
             }),
-            map(integer, Literal::Integer),
         )),
     )
     .parse(input)?;
     Ok((remaining, Token::Literal(literal)))
 }
 
+/// Parses dates and times like `2026-12-24 14:30 UTC+9` or `14:30`, which win over subtraction
+pub fn parse_date(input: &str) -> IResult<&str, Token> {
+    let (remaining, literal) = preceded(
+        whitespace,
+        alt((
+            map(
+                (
+                    date,
+                    alt((verify(whitespace, |s: &str| !s.is_empty()), tag("T"))),
+                    time,
+                    opt(preceded(whitespace, time_zone)),
+                ),
+                |(date, _, time, offset)| Literal::DateTime(date.and_time(time), offset),
+            ),
+            map(date, Literal::Date),
+            map(time, Literal::Time),
+        )),
+    )
+    .parse(input)?;
+    Ok((remaining, Token::Literal(literal)))
+}
+
+/// Parses a conversion like `in km/h`, `in SEK` or `to hex`
+pub fn conversion(input: &str) -> IResult<&str, Token> {
+    let (remaining, target) = preceded(
+        (
+            whitespace,
+            alt((tag("in"), tag("to"))),
+            verify(whitespace, |s: &str| !s.is_empty()),
+        ),
+        alt((
+            map(time_zone, ConversionTarget::TimeZone),
+            map(base, ConversionTarget::Format),
+            map(currency_code, ConversionTarget::Currency),
+            map(unit, ConversionTarget::Unit),
+        )),
+    )
+    .parse(input)?;
+    Ok((remaining, Token::Conversion(target)))
+}
+
 pub fn operator(input: &str) -> IResult<&str, Token> {
     let (remaining, operator) = preceded(
         whitespace,
         alt((
+            map(tag("<<"), |_| Operator::ShiftLeft),
+            map(tag(">>"), |_| Operator::ShiftRight),
+            map(tag("<="), |_| Operator::LessEqual),
+            map(tag(">="), |_| Operator::GreaterEqual),
+            map(tag("=="), |_| Operator::Equal),
+            map(tag("!="), |_| Operator::NotEqual),
+            map(char('<'), |_| Operator::Less),
+            map(char('>'), |_| Operator::Greater),
             map(char('+'), |_| Operator::Add),
             map(char('-'), |_| Operator::Subtract),
             map(char('*'), |_| Operator::Multiply),
             map(char('/'), |_| Operator::Divide),
             map(char('%'), |_| Operator::Modulo),
             map(char('^'), |_| Operator::Power),
+            map(char('&'), |_| Operator::BitAnd),
+            map(char('|'), |_| Operator::BitOr),
+            map(char('~'), |_| Operator::BitNot),
+        )),
+    )
+    .parse(input)?;
+    Ok((remaining, Token::Operator(operator)))
+}
+
+pub fn keyword_operator(input: &str) -> IResult<&str, Token> {
+    let keyword = |name: &'static str| verify(identifier, move |word: &str| word == name);
+    let (remaining, operator) = preceded(
+        whitespace,
+        alt((
+            map(keyword("of"), |_| Operator::Of),
+            map(keyword("off"), |_| Operator::Off),
+            map(keyword("on"), |_| Operator::On),
+            map(keyword("xor"), |_| Operator::BitXor),
+            map(keyword("and"), |_| Operator::And),
+            map(keyword("or"), |_| Operator::Or),
+            map(keyword("not"), |_| Operator::Not),
+            map(
+                (
+                    keyword("as"),
+                    whitespace,
+                    char('%'),
+                    whitespace,
+                    keyword("of"),
+                ),
+                |_| Operator::AsPercentOf,
+            ),
         )),
     )
     .parse(input)?;
     Ok((remaining, Token::Operator(operator)))
 }
 
+/// Parses the `%` in `15%`, it's modulo instead if an operand or a sign without space follows it
+pub fn percent_sign(input: &str) -> IResult<&str, Token> {
+    let (remaining, _) = preceded(whitespace, char('%')).parse(input)?;
+    let next = remaining.trim_start();
+    let next = next.strip_prefix(['+', '-']).unwrap_or(next);
+    let starts_operand = match next.chars().next() {
+        Some(c) if c.is_ascii_digit() || c == '.' || c == '(' || c == '~' => true,
+        Some(c) if c.is_alphabetic() => {
+            let is_binary_operator = matches!(
+                keyword_operator(next),
+                Ok((_, Token::Operator(operator))) if operator != Operator::Not
+            );
+            !is_binary_operator && conversion(next).is_err()
+        }
+        _ => Currency::find_symbol(next).is_some(),
+    };
+    if starts_operand {
+        return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
+    }
+    Ok((remaining, Token::Percent))
+}
+
 pub fn nested(input: &str) -> IResult<&str, Token> {
-    map_res(preceded(whitespace, parentheses()), |n| {
-        TokenTree::parse_input(n).map(|(_, token_tree)| Token::Nested(token_tree))
-    })
-    .parse(input)
+    let (remaining, content) = preceded(whitespace, parentheses()).parse(input)?;
+    let (_, mut token_tree) = TokenTree::parse_input(content)?;
+    token_tree.shift(input.offset(content));
+    Ok((remaining, Token::Nested(token_tree)))
 }
 
 pub fn function(input: &str) -> IResult<&str, Token> {
-    map(
-        map_res(
-            pair(
-                preceded(whitespace, identifier),
-                preceded(whitespace, parentheses()),
-            ),
-            |(f_name, e)| function_arguments(e).map(|e| (f_name, e)),
-        ),
-        |(f_name, (_, args))| Token::Function(f_name.to_owned(), args),
+    let (remaining, (f_name, content)) = pair(
+        preceded(whitespace, identifier),
+        preceded(whitespace, parentheses()),
     )
-    .parse(input)
+    .parse(input)?;
+    let (_, mut args) = function_arguments(content)?;
+    let offset = input.offset(content);
+    args.iter_mut().for_each(|arg| arg.shift(offset));
+    Ok((remaining, Token::Function(f_name.to_owned(), args)))
 }
 
 pub fn variable_definition(input: &str) -> IResult<&str, Token> {
@@ -90,6 +196,33 @@ pub fn variable_definition(input: &str) -> IResult<&str, Token> {
     .parse(input)
 }
 
+pub fn function_definition(input: &str) -> IResult<&str, Token> {
+    let (remaining, (name, content)) = pair(
+        preceded(whitespace, identifier),
+        preceded(whitespace, parentheses()),
+    )
+    .parse(input)?;
+    let parameter = delimited(whitespace, identifier, whitespace);
+    let (_, parameters) = all_consuming(separated_list0(char(','), parameter)).parse(content)?;
+    let (remaining, _) = preceded(whitespace, char(':')).parse(remaining)?;
+    let parameters = parameters.into_iter().map(str::to_owned).collect();
+    Ok((
+        remaining,
+        Token::FunctionDefinition(name.to_owned(), parameters),
+    ))
+}
+
+pub fn line_reference(input: &str) -> IResult<&str, Token> {
+    let reference = alt((
+        recognize(pair(char('#'), alt((digit1, tag("?"))))),
+        recognize(pair(identifier, opt(char('?')))),
+    ));
+    map_opt(preceded(whitespace, reference), |reference| {
+        LineReference::find(reference).map(Token::LineReference)
+    })
+    .parse(input)
+}
+
 pub fn variable_reference(input: &str) -> IResult<&str, Token> {
     map(preceded(whitespace, identifier), |ident| {
         Token::VariableReference(ident.to_owned())
@@ -100,45 +233,289 @@ pub fn variable_reference(input: &str) -> IResult<&str, Token> {
 mod number_parsers {
     use super::*;
 
-    pub fn scientific_notation(input: &str) -> IResult<&str, f64> {
+    pub fn number(input: &str) -> IResult<&str, Literal> {
+        alt((prefixed_integer, scientific_notation, float, integer)).parse(input)
+    }
+
+    /// Parses integers like `0xFF`, `0b1010` or `0o777`, a prefix without valid digits is a failure
+    /// unless it's zero in a unit like `0bit` or `0oz`
+    pub fn prefixed_integer(input: &str) -> IResult<&str, Literal> {
+        let (after_prefix, radix) = alt((
+            map(tag("0x"), |_| 16),
+            map(tag("0b"), |_| 2),
+            map(tag("0o"), |_| 8),
+        ))
+        .parse(input)?;
+        let invalid = || match identifier(&input[1..]) {
+            Ok((_, name)) if NamedUnit::find(name).is_some() => {
+                nom::Err::Error(Error::new(input, ErrorKind::Verify))
+            }
+            _ => nom::Err::Failure(Error::new(input, ErrorKind::Digit)),
+        };
+        let (remaining, digits) = digits_in_base(radix)(after_prefix).map_err(|_| invalid())?;
+        if remaining.starts_with(|c: char| c.is_alphanumeric()) {
+            return Err(invalid());
+        }
+        let digits = digits.replace('_', "");
+        match BigInt::parse_bytes(digits.as_bytes(), radix) {
+            Some(value) => Ok((remaining, Literal::from(value))),
+            None => Err(nom::Err::Failure(Error::new(input, ErrorKind::Digit))),
+        }
+    }
+
+    fn digits_in_base(radix: u32) -> impl Fn(&str) -> IResult<&str, &str> {
+        move |input: &str| {
+            recognize(many1(terminated(
+                satisfy(|c| c.is_digit(radix)),
+                many0(char('_')),
+            )))
+            .parse(input)
+        }
+    }
+
+    pub fn base(input: &str) -> IResult<&str, Format> {
+        map_opt(identifier, |name| match name {
+            "hex" => Some(Format::Hexadecimal),
+            "bin" => Some(Format::Binary),
+            "oct" => Some(Format::Octal),
+            _ => None,
+        })
+        .parse(input)
+    }
+
+    /// Scientific notation is exact unless the exponent is huge, e.g. `1e30` is an integer
+    pub fn scientific_notation(input: &str) -> IResult<&str, Literal> {
         // Factor can be either float or integer
-        let factor = alt((float, map(integer, |i| i as f64)));
+        let factor = alt((recognize(float), recognize(decimal)));
         // Exponent can only be an integer
-        let exponent = integer;
-        map_res(
+        let exponent = decimal;
+        map_opt(
             recognize((factor, one_of("eE"), opt(char('-')), exponent)),
-            to_f64,
+            to_exact_literal,
         )
         .parse(input)
     }
 
-    /// Parses floating point numbers
-    /// A floating point number must include a period with at least on digit on at least one side
-    pub fn float(input: &str) -> IResult<&str, f64> {
-        map_res(
+    /// Parses decimal numbers as exact fractions, e.g. `0.1` is exactly one tenth
+    /// A decimal number must include a period with at least on digit on at least one side
+    pub fn float(input: &str) -> IResult<&str, Literal> {
+        map_opt(
             alt((
                 recognize((char('.'), decimal)),
                 recognize((decimal, char('.'), opt((opt(char('-')), decimal)))),
             )),
-            to_f64,
+            to_exact_literal,
         )
         .parse(input)
     }
 
-    pub fn integer(input: &str) -> IResult<&str, i64> {
-        map_res(recognize(decimal), to_i64).parse(input)
+    pub fn integer(input: &str) -> IResult<&str, Literal> {
+        map_res(recognize(decimal), to_integer).parse(input)
     }
 
     fn decimal(input: &str) -> IResult<&str, &str> {
         recognize(many1(terminated(one_of("0123456789"), many0(char('_'))))).parse(input)
     }
 
-    fn to_i64(input: &str) -> Result<i64, std::num::ParseIntError> {
-        input.parse::<i64>()
+    fn to_integer(input: &str) -> Result<Literal, ParseBigIntError> {
+        let input = input.replace('_', "");
+        match input.parse::<i64>() {
+            Ok(value) => Ok(Literal::Integer(value)),
+            Err(_) => input.parse::<BigInt>().map(Literal::from),
+        }
+    }
+
+    fn to_exact_literal(input: &str) -> Option<Literal> {
+        /// Exponents beyond this are computed as floats to avoid building enormous numbers
+        const MAX_EXACT_EXPONENT: i64 = 10_000;
+
+        let input = input.replace('_', "");
+        let (factor, exponent) = input.split_once(['e', 'E']).unwrap_or((&input, "0"));
+        let (whole, fraction) = factor.split_once('.').unwrap_or((factor, ""));
+        let scale = exponent.parse::<i64>().ok()? - fraction.len() as i64;
+        if scale.abs() > MAX_EXACT_EXPONENT {
+            return input.parse::<f64>().ok().map(Literal::from);
+        }
+        let digits = format!("{whole}{fraction}").parse::<BigInt>().ok()?;
+        let multiplier = BigInt::from(10).pow(scale.unsigned_abs() as u32);
+        let literal = if scale < 0 {
+            Literal::from(BigRational::new(digits, multiplier))
+        } else {
+            Literal::from(digits * multiplier)
+        };
+        Some(literal)
+    }
+}
+
+mod unit_parsers {
+    use super::*;
+
+    /// Parses known units like `km`, `m/s^2` or `N*m`, but not function calls like `min(...)`
+    pub fn unit(input: &str) -> IResult<&str, Unit> {
+        let (mut remaining, (first, power)) = preceded(whitespace, unit_term).parse(input)?;
+        let mut unit = Unit::new(first, power);
+        while let Ok((rem, (operator, (named, power)))) = pair(
+            preceded(whitespace, one_of("*/")),
+            preceded(whitespace, unit_term),
+        )
+        .parse(remaining)
+        {
+            let power = if operator == '/' { -power } else { power };
+            unit = unit.with_term(named, power);
+            remaining = rem;
+        }
+        Ok((remaining, unit))
+    }
+
+    fn unit_term(input: &str) -> IResult<&str, (&'static NamedUnit, i32)> {
+        let (remaining, named) = map_opt(
+            recognize(pair(
+                satisfy(|c| c.is_alphabetic() || c == '°'),
+                take_while(|c: char| c.is_alphanumeric()),
+            )),
+            NamedUnit::find,
+        )
+        .parse(input)?;
+        if remaining.trim_start().starts_with('(') {
+            return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
+        }
+        let (remaining, power) = opt(preceded(
+            char('^'),
+            map_res(recognize(pair(opt(char('-')), digit1)), str::parse::<i32>),
+        ))
+        .parse(remaining)?;
+        Ok((remaining, (named, power.unwrap_or(1))))
+    }
+
+    /// Parses quantities written next to each other, like `3h 25min`, as their sum in the last unit
+    pub fn compound_quantity(input: &str) -> IResult<&str, Literal> {
+        let quantity = || pair(number, unit);
+        let (mut remaining, (mut total, mut total_unit)) = quantity().parse(input)?;
+        let mut parts = 1;
+        while let Ok((rem, (value, unit))) = preceded(whitespace, quantity()).parse(remaining) {
+            let factor = match total_unit.conversion_factor(&unit) {
+                Some(factor) if total_unit.offset().is_none() && unit.offset().is_none() => factor,
+                _ => break,
+            };
+            let (Some(left), Some(right)) = (total.as_rational(), value.as_rational()) else {
+                break;
+            };
+            total = Literal::from(left * factor + right);
+            total_unit = unit;
+            remaining = rem;
+            parts += 1;
+        }
+        if parts == 1 {
+            return Err(nom::Err::Error(Error::new(input, ErrorKind::Many1)));
+        }
+        Ok((remaining, Literal::quantity(total, total_unit)))
     }
+}
 
-    fn to_f64(input: &str) -> Result<f64, std::num::ParseFloatError> {
-        input.parse::<f64>()
+mod date_parsers {
+    use super::*;
+
+    /// Fails with [`ErrorKind::Verify`] for dates that don't exist, like `2026-02-30`
+    pub fn date(input: &str) -> IResult<&str, NaiveDate> {
+        let (remaining, (year, _, month, _, day)) =
+            (digits(4), char('-'), digits(2), char('-'), digits(2)).parse(input)?;
+        if starts_number(remaining) {
+            return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
+        }
+        match NaiveDate::from_ymd_opt(year as i32, month, day) {
+            Some(date) => Ok((remaining, date)),
+            None => Err(nom::Err::Failure(Error::new(input, ErrorKind::Verify))),
+        }
+    }
+
+    /// Fails like [`date`] for times that don't exist, like `25:00`
+    pub fn time(input: &str) -> IResult<&str, NaiveTime> {
+        let (remaining, (hours, _, minutes, seconds)) = (
+            verify(
+                take_while_m_n(1, 2, |c: char| c.is_ascii_digit()),
+                |s: &str| !s.is_empty(),
+            ),
+            char(':'),
+            digits(2),
+            opt(preceded(char(':'), digits(2))),
+        )
+            .parse(input)?;
+        if starts_number(remaining) {
+            return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
+        }
+        let hours = hours.parse::<u32>().expect("only digits");
+        match NaiveTime::from_hms_opt(hours, minutes, seconds.unwrap_or(0)) {
+            Some(time) => Ok((remaining, time)),
+            None => Err(nom::Err::Failure(Error::new(input, ErrorKind::Verify))),
+        }
+    }
+
+    /// Parses `UTC`, `UTC+9` or `UTC-5:30`
+    pub fn time_zone(input: &str) -> IResult<&str, FixedOffset> {
+        let (remaining, (_, offset)) = pair(
+            verify(identifier, |name: &str| name == "UTC"),
+            opt((
+                one_of("+-"),
+                verify(
+                    take_while_m_n(1, 2, |c: char| c.is_ascii_digit()),
+                    |s: &str| !s.is_empty(),
+                ),
+                opt(preceded(char(':'), digits(2))),
+            )),
+        )
+        .parse(input)?;
+        let seconds = match offset {
+            Some((sign, hours, minutes)) => {
+                let hours = hours.parse::<i32>().expect("only digits");
+                let seconds = hours * 3600 + minutes.unwrap_or(0) as i32 * 60;
+                if sign == '-' {
+                    -seconds
+                } else {
+                    seconds
+                }
+            }
+            None => 0,
+        };
+        match FixedOffset::east_opt(seconds) {
+            Some(offset) if seconds.abs() <= 14 * 3600 => Ok((remaining, offset)),
+            _ => Err(nom::Err::Error(Error::new(input, ErrorKind::Verify))),
+        }
+    }
+
+    fn digits(count: usize) -> impl Fn(&str) -> IResult<&str, u32> {
+        move |input: &str| {
+            map_res(
+                take_while_m_n(count, count, |c: char| c.is_ascii_digit()),
+                str::parse::<u32>,
+            )
+            .parse(input)
+        }
+    }
+
+    /// Like the third digit in `12:345`
+    fn starts_number(input: &str) -> bool {
+        input.starts_with(|c: char| c.is_ascii_digit() || c == '.' || c == '_' || c == ':')
+    }
+}
+
+mod currency_parsers {
+    use super::*;
+
+    /// Doesn't match the names of functions
+    pub fn currency_code(input: &str) -> IResult<&str, &'static Currency> {
+        let (remaining, currency) =
+            preceded(whitespace, map_opt(identifier, Currency::find)).parse(input)?;
+        if remaining.trim_start().starts_with('(') {
+            return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
+        }
+        Ok((remaining, currency))
+    }
+
+    pub fn currency_symbol(input: &str) -> IResult<&str, &'static Currency> {
+        match Currency::find_symbol(input) {
+            Some((currency, length)) => Ok((&input[length..], currency)),
+            None => Err(nom::Err::Error(Error::new(input, ErrorKind::Tag))),
+        }
     }
 }
 
@@ -153,19 +530,45 @@ mod text_parsers {
         .parse(input)
     }
 
+    /// Splits the arguments on commas that aren't inside nested parentheses
     pub fn function_arguments(input: &str) -> IResult<&str, Vec<TokenTree>> {
-        map_res(
-            separated_list0(
-                preceded(whitespace, tag(",")),
-                preceded(whitespace, is_not(",")),
-            ),
-            |args| {
-                args.into_iter()
-                    .map(|s| TokenTree::parse_input(s).map(|(_, a)| a))
-                    .collect::<Result<Vec<_>, _>>()
-            },
-        )
-        .parse(input)
+        if input.trim().is_empty() {
+            return Ok((input, vec![]));
+        }
+        let mut args = vec![];
+        let mut depth = 0;
+        let mut start = 0;
+        for (index, c) in input.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    args.push(function_argument(input, start, index)?);
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+        args.push(function_argument(input, start, input.len())?);
+        Ok(("", args))
+    }
+
+    fn function_argument(
+        input: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<TokenTree, nom::Err<Error<&str>>> {
+        let arg = &input[start..end];
+        if arg.trim().is_empty() {
+            // Point at the separator or closing parenthesis where the argument should have been
+            return Err(nom::Err::Failure(Error::new(
+                &input[end..],
+                ErrorKind::SeparatedList,
+            )));
+        }
+        let (_, mut token_tree) = TokenTree::parse_input(arg)?;
+        token_tree.shift(start);
+        Ok(token_tree)
     }
 
     pub fn parentheses() -> impl Fn(&str) -> IResult<&str, &str> {
@@ -209,6 +612,10 @@ mod text_parsers {
 mod tests {
     use super::*;
 
+    fn ratio(numer: i64, denom: i64) -> Literal {
+        Literal::from(BigRational::new(numer.into(), denom.into()))
+    }
+
     mod internal_parsers {
         use super::*;
 
@@ -216,14 +623,11 @@ mod tests {
         fn test_parentheses() {
             let input = "(1 + 2 ) * 3";
 
-            let expected = Token::Nested(TokenTree {
-                num_operators: 1,
-                tokens: vec![
-                    Token::Literal(Literal::Integer(1)),
-                    Token::Operator(Operator::Add),
-                    Token::Literal(Literal::Integer(2)),
-                ],
-            });
+            let expected = Token::Nested(TokenTree::from_tokens(vec![
+                Token::Literal(Literal::Integer(1)),
+                Token::Operator(Operator::Add),
+                Token::Literal(Literal::Integer(2)),
+            ]));
             let (rem, actual) = nested(input).unwrap();
 
             assert_eq!(rem, " * 3");
@@ -254,6 +658,45 @@ mod tests {
                 assert!(rem.is_empty());
                 assert_eq!(actual, expected);
             }
+
+            #[test]
+            fn other_bases() {
+                let test_cases = [
+                    ("0xFF", 255),
+                    ("0x1f_ff", 8191),
+                    ("0b1010", 10),
+                    ("0o777", 511),
+                ];
+                for (input, expected) in test_cases {
+                    let (rem, actual) = parse_number(input).unwrap();
+
+                    assert!(rem.is_empty());
+                    assert_eq!(actual, Token::Literal(Literal::Integer(expected)));
+                }
+            }
+
+            #[test]
+            fn invalid_digits_for_base() {
+                for input in ["0b102", "0xFG", "0o", "0x", "0b"] {
+                    assert!(
+                        matches!(prefixed_integer(input), Err(nom::Err::Failure(_))),
+                        "{input}"
+                    );
+                }
+                assert!(matches!(prefixed_integer("0bit"), Err(nom::Err::Error(_))));
+            }
+
+            #[test]
+            fn bigger_than_i64() {
+                let input = "123_456_789_012_345_678_901";
+
+                let expected = "123456789012345678901".parse::<BigInt>().unwrap();
+                let expected = Token::Literal(Literal::BigInteger(expected));
+                let (rem, actual) = parse_number(input).unwrap();
+
+                assert!(rem.is_empty());
+                assert_eq!(actual, expected);
+            }
         }
 
         mod decimals {
@@ -274,7 +717,7 @@ mod tests {
             fn positive() {
                 let input = "11.1";
 
-                let expected = Token::Literal(Literal::Float(11.1));
+                let expected = Token::Literal(ratio(111, 10));
                 let (rem, actual) = parse_number(input).unwrap();
 
                 assert!(rem.is_empty());
@@ -302,7 +745,7 @@ mod tests {
                     fn pos_e_neg() {
                         let input = "4e-5";
 
-                        let expected = Token::Literal(Literal::Float(0.00004));
+                        let expected = Token::Literal(ratio(1, 25_000));
                         let (rem, actual) = parse_number(input).unwrap();
 
                         assert!(rem.is_empty());
@@ -324,11 +767,23 @@ mod tests {
                         assert_eq!(actual, expected);
                     }
 
+                    #[test]
+                    fn bigger_than_i64() {
+                        let input = "1.5e30";
+
+                        let expected = "1500000000000000000000000000000".parse::<BigInt>().unwrap();
+                        let expected = Token::Literal(Literal::BigInteger(expected));
+                        let (rem, actual) = parse_number(input).unwrap();
+
+                        assert!(rem.is_empty());
+                        assert_eq!(actual, expected);
+                    }
+
                     #[test]
                     fn pos_e_neg() {
                         let input = "7.987123e-5";
 
-                        let expected = Token::Literal(Literal::Float(0.00007987123));
+                        let expected = Token::Literal(ratio(7_987_123, 100_000_000_000));
                         let (rem, actual) = parse_number(input).unwrap();
 
                         assert!(rem.is_empty());
@@ -437,6 +892,52 @@ mod tests {
             assert_eq!(actual, expected);
         }
 
+        #[test]
+        fn bitwise() {
+            let test_cases = [
+                ("&", Operator::BitAnd),
+                ("|", Operator::BitOr),
+                ("~", Operator::BitNot),
+                ("<<", Operator::ShiftLeft),
+                (">>", Operator::ShiftRight),
+            ];
+            for (input, expected) in test_cases {
+                let (rem, actual) = operator(input).unwrap();
+
+                assert!(rem.is_empty());
+                assert_eq!(actual, Token::Operator(expected));
+            }
+            let (_, actual) = keyword_operator(" xor 3").unwrap();
+            assert_eq!(actual, Token::Operator(Operator::BitXor));
+        }
+
+        #[test]
+        fn comparisons() {
+            let test_cases = [
+                ("<", Operator::Less),
+                ("<=", Operator::LessEqual),
+                (">", Operator::Greater),
+                (">=", Operator::GreaterEqual),
+                ("==", Operator::Equal),
+                ("!=", Operator::NotEqual),
+            ];
+            for (input, expected) in test_cases {
+                let (rem, actual) = operator(input).unwrap();
+
+                assert!(rem.is_empty());
+                assert_eq!(actual, Token::Operator(expected));
+            }
+            for (input, expected) in [
+                (" and b", Operator::And),
+                (" or b", Operator::Or),
+                (" not b", Operator::Not),
+            ] {
+                let (_, actual) = keyword_operator(input).unwrap();
+                assert_eq!(actual, Token::Operator(expected));
+            }
+            assert!(keyword_operator("notes").is_err());
+        }
+
         mod whitespace {
             use super::*;
 
@@ -474,10 +975,7 @@ mod tests {
         fn empty_parentheses() {
             let input = "()";
 
-            let expected = Token::Nested(TokenTree {
-                num_operators: 0,
-                tokens: vec![],
-            });
+            let expected = Token::Nested(TokenTree::from_tokens(vec![]));
             let (rem, actual) = nested(input).unwrap();
 
             assert!(rem.is_empty());
@@ -488,10 +986,9 @@ mod tests {
         fn simple_case() {
             let input = "(5)";
 
-            let expected = Token::Nested(TokenTree {
-                num_operators: 0,
-                tokens: vec![Token::Literal(Literal::Integer(5))],
-            });
+            let expected = Token::Nested(TokenTree::from_tokens(vec![Token::Literal(
+                Literal::Integer(5),
+            )]));
             let (rem, actual) = nested(input).unwrap();
 
             assert!(rem.is_empty());
@@ -502,21 +999,15 @@ mod tests {
         fn one_level_of_nesting() {
             let input = "((10 + 2) * 5)";
 
-            let expected = Token::Nested(TokenTree {
-                num_operators: 1,
-                tokens: vec![
-                    Token::Nested(TokenTree {
-                        num_operators: 1,
-                        tokens: vec![
-                            Token::Literal(Literal::Integer(10)),
-                            Token::Operator(Operator::Add),
-                            Token::Literal(Literal::Integer(2)),
-                        ],
-                    }),
-                    Token::Operator(Operator::Multiply),
-                    Token::Literal(Literal::Integer(5)),
-                ],
-            });
+            let expected = Token::Nested(TokenTree::from_tokens(vec![
+                Token::Nested(TokenTree::from_tokens(vec![
+                    Token::Literal(Literal::Integer(10)),
+                    Token::Operator(Operator::Add),
+                    Token::Literal(Literal::Integer(2)),
+                ])),
+                Token::Operator(Operator::Multiply),
+                Token::Literal(Literal::Integer(5)),
+            ]));
             let (rem, actual) = nested(input).unwrap();
 
             assert!(rem.is_empty());
@@ -527,28 +1018,19 @@ mod tests {
         fn two_levels_of_nesting() {
             let input = "((10 + (1 / 1)) * 5)";
 
-            let expected = Token::Nested(TokenTree {
-                num_operators: 1,
-                tokens: vec![
-                    Token::Nested(TokenTree {
-                        num_operators: 1,
-                        tokens: vec![
-                            Token::Literal(Literal::Integer(10)),
-                            Token::Operator(Operator::Add),
-                            Token::Nested(TokenTree {
-                                num_operators: 1,
-                                tokens: vec![
-                                    Token::Literal(Literal::Integer(1)),
-                                    Token::Operator(Operator::Divide),
-                                    Token::Literal(Literal::Integer(1)),
-                                ],
-                            }),
-                        ],
-                    }),
-                    Token::Operator(Operator::Multiply),
-                    Token::Literal(Literal::Integer(5)),
-                ],
-            });
+            let expected = Token::Nested(TokenTree::from_tokens(vec![
+                Token::Nested(TokenTree::from_tokens(vec![
+                    Token::Literal(Literal::Integer(10)),
+                    Token::Operator(Operator::Add),
+                    Token::Nested(TokenTree::from_tokens(vec![
+                        Token::Literal(Literal::Integer(1)),
+                        Token::Operator(Operator::Divide),
+                        Token::Literal(Literal::Integer(1)),
+                    ])),
+                ])),
+                Token::Operator(Operator::Multiply),
+                Token::Literal(Literal::Integer(5)),
+            ]));
             let (rem, actual) = nested(input).unwrap();
 
             assert!(rem.is_empty());
@@ -569,14 +1051,11 @@ mod tests {
             fn whitespace_before() {
                 let input = " (5 + 1)";
 
-                let expected = Token::Nested(TokenTree {
-                    num_operators: 1,
-                    tokens: vec![
-                        Token::Literal(Literal::Integer(5)),
-                        Token::Operator(Operator::Add),
-                        Token::Literal(Literal::Integer(1)),
-                    ],
-                });
+                let expected = Token::Nested(TokenTree::from_tokens(vec![
+                    Token::Literal(Literal::Integer(5)),
+                    Token::Operator(Operator::Add),
+                    Token::Literal(Literal::Integer(1)),
+                ]));
                 let (rem, actual) = nested(input).unwrap();
 
                 assert!(rem.is_empty());
@@ -587,14 +1066,11 @@ mod tests {
             fn whitespace_after_opening_parenthesis() {
                 let input = "( 5 + 1)";
 
-                let expected = Token::Nested(TokenTree {
-                    num_operators: 1,
-                    tokens: vec![
-                        Token::Literal(Literal::Integer(5)),
-                        Token::Operator(Operator::Add),
-                        Token::Literal(Literal::Integer(1)),
-                    ],
-                });
+                let expected = Token::Nested(TokenTree::from_tokens(vec![
+                    Token::Literal(Literal::Integer(5)),
+                    Token::Operator(Operator::Add),
+                    Token::Literal(Literal::Integer(1)),
+                ]));
                 let (rem, actual) = nested(input).unwrap();
 
                 assert!(rem.is_empty());
@@ -605,14 +1081,11 @@ mod tests {
             fn whitespace_before_closing_parenthesis() {
                 let input = "(5 + 1 )";
 
-                let expected = Token::Nested(TokenTree {
-                    num_operators: 1,
-                    tokens: vec![
-                        Token::Literal(Literal::Integer(5)),
-                        Token::Operator(Operator::Add),
-                        Token::Literal(Literal::Integer(1)),
-                    ],
-                });
+                let expected = Token::Nested(TokenTree::from_tokens(vec![
+                    Token::Literal(Literal::Integer(5)),
+                    Token::Operator(Operator::Add),
+                    Token::Literal(Literal::Integer(1)),
+                ]));
                 let (rem, actual) = nested(input).unwrap();
 
                 assert!(rem.is_empty());
@@ -637,10 +1110,9 @@ mod tests {
 
             let expected = Token::Function(
                 "my_fn".to_owned(),
-                vec![TokenTree {
-                    num_operators: 0,
-                    tokens: vec![Token::Literal(Literal::Integer(5))],
-                }],
+                vec![TokenTree::from_tokens(vec![Token::Literal(
+                    Literal::Integer(5),
+                )])],
             );
             let (rem, actual) = function(input).unwrap();
 
@@ -655,14 +1127,8 @@ mod tests {
             let expected = Token::Function(
                 "my_fn".to_owned(),
                 vec![
-                    TokenTree {
-                        num_operators: 0,
-                        tokens: vec![Token::Literal(Literal::Integer(5))],
-                    },
-                    TokenTree {
-                        num_operators: 0,
-                        tokens: vec![Token::Literal(Literal::Integer(10))],
-                    },
+                    TokenTree::from_tokens(vec![Token::Literal(Literal::Integer(5))]),
+                    TokenTree::from_tokens(vec![Token::Literal(Literal::Integer(10))]),
                 ],
             );
             let (rem, actual) = function(input).unwrap();
@@ -677,21 +1143,15 @@ mod tests {
 
             let expected = Token::Function(
                 "my_fn".to_owned(),
-                vec![TokenTree {
-                    num_operators: 1,
-                    tokens: vec![
-                        Token::Literal(Literal::Integer(5)),
+                vec![TokenTree::from_tokens(vec![
+                    Token::Literal(Literal::Integer(5)),
+                    Token::Operator(Operator::Add),
+                    Token::Nested(TokenTree::from_tokens(vec![
+                        Token::Literal(Literal::Integer(2)),
                         Token::Operator(Operator::Add),
-                        Token::Nested(TokenTree {
-                            num_operators: 1,
-                            tokens: vec![
-                                Token::Literal(Literal::Integer(2)),
-                                Token::Operator(Operator::Add),
-                                Token::Literal(Literal::Integer(1)),
-                            ],
-                        }),
-                    ],
-                }],
+                        Token::Literal(Literal::Integer(1)),
+                    ])),
+                ])],
             );
             let (rem, actual) = function(input).unwrap();
 
@@ -809,6 +1269,64 @@ mod tests {
         }
     }
 
+    mod function_definition {
+        use super::*;
+
+        #[test]
+        fn parameters() {
+            let test_cases = [
+                ("area(w, h):", vec!["w", "h"]),
+                (" f ( x ) :", vec!["x"]),
+                ("answer():", vec![]),
+            ];
+            for (input, parameters) in test_cases {
+                let name = input.trim_start().split(['(', ' ']).next().unwrap();
+                let parameters = parameters.into_iter().map(str::to_owned).collect();
+                let expected = Token::FunctionDefinition(name.to_owned(), parameters);
+                let (rem, actual) = function_definition(input).unwrap();
+
+                assert!(rem.is_empty());
+                assert_eq!(actual, expected);
+            }
+        }
+
+        #[test]
+        fn parameters_must_be_names() {
+            for input in ["f(2):", "f(x + 1):", "f(x,):", "f(x)"] {
+                assert!(function_definition(input).is_err(), "{input}");
+            }
+        }
+    }
+
+    mod line_reference {
+        use super::*;
+
+        #[test]
+        fn references() {
+            let test_cases = [
+                ("prev", LineReference::Previous),
+                (" ans", LineReference::Previous),
+                ("line3", LineReference::Line(3)),
+                ("#42", LineReference::Line(42)),
+                ("#?", LineReference::Removed),
+                ("line?", LineReference::Removed),
+            ];
+            for (input, reference) in test_cases {
+                let (rem, actual) = line_reference(input).unwrap();
+
+                assert!(rem.is_empty(), "{input}");
+                assert_eq!(actual, Token::LineReference(reference));
+            }
+        }
+
+        #[test]
+        fn other_names_are_not_references() {
+            for input in ["line", "previous", "my_line3", "# 3", "#x"] {
+                assert!(line_reference(input).is_err(), "{input}");
+            }
+        }
+    }
+
     mod variable_reference {
         use super::*;
 
@@ -1,4 +1,4 @@
-use raekna_common::expression::Literal;
+use raekna_common::expression::{ConversionTarget, LineReference, Literal};
 
 use super::token_tree::TokenTree;
 
@@ -10,6 +10,59 @@ pub enum Operator {
     Divide,
     Modulo,
     Power,
+    /// `of` as in `20% of 80`, which is a multiplication
+    Of,
+    Off,
+    On,
+    /// `as % of`
+    AsPercentOf,
+    BitAnd,
+    BitOr,
+    BitXor,
+    /// Only valid in front of an operand
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+    /// Only valid in front of an operand
+    Not,
+}
+
+impl Operator {
+    /// Arithmetic binds tighter than bitwise operators, then comparisons and then logic
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Self::Or => 0,
+            Self::And => 1,
+            Self::Not => 2,
+            Self::Less
+            | Self::LessEqual
+            | Self::Greater
+            | Self::GreaterEqual
+            | Self::Equal
+            | Self::NotEqual => 3,
+            Self::BitOr => 4,
+            Self::BitXor => 5,
+            Self::BitAnd => 6,
+            Self::ShiftLeft | Self::ShiftRight => 7,
+            Self::Add | Self::Subtract => 8,
+            Self::Multiply
+            | Self::Divide
+            | Self::Modulo
+            | Self::Of
+            | Self::Off
+            | Self::On
+            | Self::AsPercentOf => 9,
+            Self::Power | Self::BitNot => 10,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -18,6 +71,20 @@ pub enum Token {
     Operator(Operator),
     Function(String, Vec<TokenTree>),
     VariableDefinition(String),
+    FunctionDefinition(String, Vec<String>),
     VariableReference(String),
+    LineReference(LineReference),
     Nested(TokenTree),
+    Percent,
+    Conversion(ConversionTarget),
+}
+
+impl Token {
+    pub fn shift(&mut self, offset: usize) {
+        match self {
+            Self::Function(_, args) => args.iter_mut().for_each(|arg| arg.shift(offset)),
+            Self::Nested(token_tree) => token_tree.shift(offset),
+            _ => {}
+        }
+    }
 }
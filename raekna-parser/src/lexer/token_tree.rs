@@ -1,49 +1,122 @@
-use nom::IResult;
+use nom::{
+    error::{Error, ErrorKind},
+    IResult, Offset,
+};
+use raekna_common::span::Span;
 
 use super::{
-    parsers::{function, nested, operator, parse_number, variable_definition, variable_reference},
+    parsers::{
+        conversion, function, function_definition, keyword_operator, line_reference, nested,
+        operator, parse_date, parse_number, percent_sign, variable_definition, variable_reference,
+    },
     token::Token,
 };
 
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// `spans[i]` is the span of `tokens[i]`, spans are ignored in comparisons
+#[derive(Clone, Debug, Default)]
 pub struct TokenTree {
-    pub num_operators: usize,
-    pub tokens: Vec<Token>,
+    num_operators: usize,
+    tokens: Vec<Token>,
+    spans: Vec<Span>,
 }
 
 impl TokenTree {
-    pub fn parse_input(input: &str) -> IResult<(), Self> {
+    /// Errors are failures holding the input from the offending character on
+    pub fn parse_input(input: &str) -> IResult<&str, Self> {
         let mut token_tree = Self::default();
 
-        let mut input = input;
+        let mut remaining = input;
         let parsers = [
             variable_definition,
+            function_definition,
+            parse_date,
             parse_number,
+            conversion,
+            keyword_operator,
             function,
+            line_reference,
             variable_reference,
+            percent_sign,
             operator,
             nested,
         ];
-        'outer: while !input.is_empty() {
+        'outer: while !remaining.trim_start().is_empty() {
             for parser in parsers.iter() {
-                if let Ok((rem, token)) = parser(input) {
-                    input = rem;
-                    if let Token::Operator(_) = token {
-                        token_tree.num_operators += 1;
+                match parser(remaining) {
+                    Ok((rem, mut token)) => {
+                        token.shift(input.offset(remaining));
+                        let start = input.offset(remaining.trim_start());
+                        token_tree.push(token, Span::new(start, input.offset(rem)));
+                        remaining = rem;
+                        continue 'outer;
                     }
-                    token_tree.tokens.push(token);
-                    continue 'outer;
+                    Err(nom::Err::Failure(e)) => return Err(nom::Err::Failure(e)),
+                    Err(_) => {}
                 }
             }
-            return Err(nom::Err::Incomplete(nom::Needed::Unknown));
+            return Err(nom::Err::Failure(Error::new(
+                remaining.trim_start(),
+                ErrorKind::Alt,
+            )));
+        }
+
+        Ok(("", token_tree))
+    }
+
+    fn push(&mut self, token: Token, span: Span) {
+        if let Token::Operator(_) = token {
+            self.num_operators += 1;
+        }
+        self.tokens.push(token);
+        self.spans.push(span);
+    }
+
+    pub fn num_operators(&self) -> usize {
+        self.num_operators
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    pub fn into_tokens(self) -> impl Iterator<Item = (Token, Span)> {
+        self.tokens.into_iter().zip(self.spans)
+    }
+
+    /// A tree with made up spans, for tests that only look at the tokens
+    #[cfg(test)]
+    pub fn from_tokens(tokens: Vec<Token>) -> Self {
+        let mut token_tree = Self::default();
+        for (i, token) in tokens.into_iter().enumerate() {
+            token_tree.push(token, Span::new(i, i + 1));
         }
+        token_tree
+    }
 
-        Ok(((), token_tree))
+    /// Moves all spans, including those of nested trees
+    pub fn shift(&mut self, offset: usize) {
+        self.spans
+            .iter_mut()
+            .for_each(|span| *span = span.shift(offset));
+        self.tokens.iter_mut().for_each(|token| token.shift(offset));
     }
 }
 
+impl PartialEq for TokenTree {
+    fn eq(&self, other: &Self) -> bool {
+        self.num_operators == other.num_operators && self.tokens == other.tokens
+    }
+}
+
+impl Eq for TokenTree {}
+
 #[cfg(test)]
 mod tests {
+    use num_rational::BigRational;
     use raekna_common::expression::Literal;
 
     use super::*;
@@ -53,10 +126,7 @@ mod tests {
     fn empty_input() {
         let input = "";
 
-        let expected = TokenTree {
-            num_operators: 0,
-            tokens: vec![],
-        };
+        let expected = TokenTree::default();
         let (_, actual) = TokenTree::parse_input(input).unwrap();
 
         assert_eq!(actual, expected);
@@ -66,28 +136,19 @@ mod tests {
     fn function_with_negative_arguments() {
         let input = "add(-1, -2)";
 
-        let expected = TokenTree {
-            num_operators: 0,
-            tokens: vec![Token::Function(
-                "add".to_owned(),
-                vec![
-                    TokenTree {
-                        num_operators: 1,
-                        tokens: vec![
-                            Token::Operator(Operator::Subtract),
-                            Token::Literal(Literal::Integer(1)),
-                        ],
-                    },
-                    TokenTree {
-                        num_operators: 1,
-                        tokens: vec![
-                            Token::Operator(Operator::Subtract),
-                            Token::Literal(Literal::Integer(2)),
-                        ],
-                    },
-                ],
-            )],
-        };
+        let expected = TokenTree::from_tokens(vec![Token::Function(
+            "add".to_owned(),
+            vec![
+                TokenTree::from_tokens(vec![
+                    Token::Operator(Operator::Subtract),
+                    Token::Literal(Literal::Integer(1)),
+                ]),
+                TokenTree::from_tokens(vec![
+                    Token::Operator(Operator::Subtract),
+                    Token::Literal(Literal::Integer(2)),
+                ]),
+            ],
+        )]);
         let (_, actual) = TokenTree::parse_input(input).unwrap();
 
         assert_eq!(actual, expected);
@@ -97,46 +158,88 @@ mod tests {
     fn scenario1() {
         let input = "var_def: pow(sqrt(var_ref), 5 / 2.0) * (1e2 + 2.2)";
 
-        let expected = TokenTree {
-            num_operators: 1,
-            tokens: vec![
-                Token::VariableDefinition("var_def".to_owned()),
-                Token::Function(
-                    "pow".to_owned(),
-                    vec![
-                        TokenTree {
-                            num_operators: 0,
-                            tokens: vec![Token::Function(
-                                "sqrt".to_owned(),
-                                vec![TokenTree {
-                                    num_operators: 0,
-                                    tokens: vec![Token::VariableReference("var_ref".to_owned())],
-                                }],
-                            )],
-                        },
-                        TokenTree {
-                            num_operators: 1,
-                            tokens: vec![
-                                Token::Literal(Literal::Integer(5)),
-                                Token::Operator(Operator::Divide),
-                                Token::Literal(Literal::Integer(2)),
-                            ],
-                        },
-                    ],
-                ),
-                Token::Operator(Operator::Multiply),
-                Token::Nested(TokenTree {
-                    num_operators: 1,
-                    tokens: vec![
-                        Token::Literal(Literal::Integer(100)),
-                        Token::Operator(Operator::Add),
-                        Token::Literal(Literal::Float(2.2)),
-                    ],
-                }),
-            ],
-        };
+        let expected = TokenTree::from_tokens(vec![
+            Token::VariableDefinition("var_def".to_owned()),
+            Token::Function(
+                "pow".to_owned(),
+                vec![
+                    TokenTree::from_tokens(vec![Token::Function(
+                        "sqrt".to_owned(),
+                        vec![TokenTree::from_tokens(vec![Token::VariableReference(
+                            "var_ref".to_owned(),
+                        )])],
+                    )]),
+                    TokenTree::from_tokens(vec![
+                        Token::Literal(Literal::Integer(5)),
+                        Token::Operator(Operator::Divide),
+                        Token::Literal(Literal::Integer(2)),
+                    ]),
+                ],
+            ),
+            Token::Operator(Operator::Multiply),
+            Token::Nested(TokenTree::from_tokens(vec![
+                Token::Literal(Literal::Integer(100)),
+                Token::Operator(Operator::Add),
+                Token::Literal(Literal::from(BigRational::new(11.into(), 5.into()))),
+            ])),
+        ]);
         let (_, actual) = TokenTree::parse_input(input).unwrap();
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn spans_are_relative_to_the_whole_input() {
+        let input = "a: max(1, ( b+2 )) ^ 3 ";
+
+        let (_, actual) = TokenTree::parse_input(input).unwrap();
+
+        assert_eq!(
+            actual.spans,
+            vec![
+                Span::new(0, 2),
+                Span::new(3, 18),
+                Span::new(19, 20),
+                Span::new(21, 22)
+            ]
+        );
+        let Token::Function(_, args) = &actual.tokens[1] else {
+            panic!("Expected a function, got {:?}", actual.tokens[1]);
+        };
+        assert_eq!(args[0].spans, vec![Span::new(7, 8)]);
+        assert_eq!(args[1].spans, vec![Span::new(10, 17)]);
+        let Token::Nested(nested) = &args[1].tokens[0] else {
+            panic!("Expected a nested expression, got {:?}", args[1].tokens[0]);
+        };
+        assert_eq!(
+            nested.spans,
+            vec![Span::new(12, 13), Span::new(13, 14), Span::new(14, 15)]
+        );
+    }
+
+    #[test]
+    fn function_arguments_with_nested_commas() {
+        let input = "max(min(1, 2), 3)";
+
+        let (_, actual) = TokenTree::parse_input(input).unwrap();
+
+        let Token::Function(_, args) = &actual.tokens[0] else {
+            panic!("Expected a function, got {:?}", actual.tokens[0]);
+        };
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[0].spans, vec![Span::new(4, 13)]);
+        assert_eq!(args[1].spans, vec![Span::new(15, 16)]);
+    }
+
+    #[test]
+    fn error_points_at_invalid_input() {
+        let input = "1 + (2 $ 3)";
+
+        let error = TokenTree::parse_input(input).unwrap_err();
+
+        let nom::Err::Failure(error) = error else {
+            panic!("Expected a failure, got {error:?}");
+        };
+        assert_eq!(error.input, "$ 3");
+    }
 }
@@ -1,6 +1,8 @@
 mod errors;
 mod lexer;
+mod line;
 mod parser;
 
-pub use errors::ParserError;
+pub use errors::{ParserError, ParserResult};
+pub use line::line_kind;
 pub use parser::parse;
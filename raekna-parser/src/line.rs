@@ -0,0 +1,67 @@
+use raekna_common::LineKind;
+
+/// A `#` followed by a digit or `?` is a line reference, not a comment
+fn comment_start(line: &str) -> Option<usize> {
+    line.char_indices().find_map(|(index, c)| match c {
+        '#' if line[index + 1..].starts_with(|c: char| c.is_ascii_digit() || c == '?') => None,
+        '#' => Some(index),
+        '/' if line[index + 1..].starts_with('/') => Some(index),
+        _ => None,
+    })
+}
+
+pub fn line_kind(line: &str) -> LineKind {
+    let comment = comment_start(line);
+    let code = line[..comment.unwrap_or(line.len())].trim();
+    if code.is_empty() {
+        return match comment {
+            Some(_) => LineKind::Comment,
+            None => LineKind::Blank,
+        };
+    }
+    let is_heading = code
+        .strip_suffix(':')
+        .is_some_and(|title| title.starts_with(char::is_alphabetic));
+    if is_heading {
+        LineKind::Heading
+    } else {
+        LineKind::Expression(comment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments() {
+        let test_cases = [
+            ("# prices include VAT", LineKind::Comment),
+            ("  // todo", LineKind::Comment),
+            ("5 * 3 # three boxes", LineKind::Expression(Some(6))),
+            ("10 / 2 // half", LineKind::Expression(Some(7))),
+            ("10 / 2", LineKind::Expression(None)),
+            ("#3 * 2 # doubled", LineKind::Expression(Some(7))),
+            ("#? + 1", LineKind::Expression(None)),
+            ("", LineKind::Blank),
+            ("   ", LineKind::Blank),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(line_kind(input), expected, "input: {input}");
+        }
+    }
+
+    #[test]
+    fn headings() {
+        let test_cases = [
+            ("Groceries:", LineKind::Heading),
+            ("Q3 costs (estimated):", LineKind::Heading),
+            ("Rent: # monthly", LineKind::Heading),
+            ("rent: 1200", LineKind::Expression(None)),
+            ("2026-10-17 10:", LineKind::Expression(None)),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(line_kind(input), expected, "input: {input}");
+        }
+    }
+}
@@ -1,52 +1,119 @@
 use std::str::FromStr;
 
+use nom::{error::ErrorKind, Offset};
 use raekna_common::{
-    expression::{Expression, Literal},
+    expression::{ConversionTarget, Expression, LineReference, Literal},
     function_name::FunctionName,
+    span::Span,
+    LineKind,
 };
 
 use crate::{
     errors::ParserResult,
     lexer::{Operator, Token, TokenTree},
+    line::line_kind,
     ParserError,
 };
 
+/// Parses the expression on a line, lines without one are [`ParserError::EmptyExpression`]
 pub fn parse(raw_expr: &'_ str) -> ParserResult<Expression> {
-    if raw_expr.is_empty() {
-        return Err(ParserError::EmptyExpression);
-    }
+    let raw_expr = match line_kind(raw_expr) {
+        LineKind::Expression(comment) => &raw_expr[..comment.unwrap_or(raw_expr.len())],
+        _ => return Err(ParserError::EmptyExpression),
+    };
     TokenTree::parse_input(raw_expr)
-        .map_err(ParserError::NomError)
+        .map_err(|e| {
+            let (rest, code) = match e {
+                nom::Err::Error(e) | nom::Err::Failure(e) => (e.input, e.code),
+                nom::Err::Incomplete(_) => ("", ErrorKind::Fail),
+            };
+            let start = raw_expr.offset(rest);
+            // Only dates and times that don't exist fail verification
+            if code == ErrorKind::Verify {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_digit() || c == '-' || c == ':'))
+                    .unwrap_or(rest.len());
+                let span = Span::new(start, start + len);
+                return ParserError::InvalidDate(rest[..len].to_owned(), span);
+            }
+            let end = start + rest.chars().next().map_or(0, char::len_utf8);
+            ParserError::InvalidSyntax(Span::new(start, end))
+        })
         .and_then(|(_, tt)| convert_token_tree(tt, true))
 }
 
 fn convert_token_tree(token_tree: TokenTree, allow_variable_def: bool) -> ParserResult<Expression> {
-    let mut parser = Parser::new(token_tree.num_operators);
+    let mut parser = Parser::new(token_tree.num_operators());
     parser.convert_token_tree(token_tree, allow_variable_def)?;
     parser.finish()
 }
 
+/// Converts parentheses or a function argument, which can't be empty
+fn convert_nested_token_tree(token_tree: TokenTree, span: Span) -> ParserResult<Expression> {
+    convert_token_tree(token_tree, false).map_err(|e| match e {
+        ParserError::EmptyExpression => ParserError::InvalidExpression {
+            expressions: vec![],
+            operators: vec![],
+            span,
+        },
+        e => e,
+    })
+}
+
 struct Parser {
     variable: Option<String>,
+    function: Option<(String, Vec<String>)>,
+    conversion: Option<ConversionTarget>,
     operators: Vec<Operator>,
     expressions: Vec<Option<Expression>>,
     is_sign: bool,
     should_negate: bool,
+    /// The `~`s and signs in front of the next operand, outermost first
+    prefixes: Vec<FunctionName>,
+    expects_operand: bool,
+    span: Span,
 }
 
 impl Parser {
     fn new(num_operators: usize) -> Self {
         Self {
             variable: None,
+            function: None,
+            conversion: None,
             operators: Vec::with_capacity(num_operators),
             expressions: vec![],
             is_sign: true,
             should_negate: false,
+            prefixes: vec![],
+            expects_operand: true,
+            span: Span::default(),
         }
     }
 
     fn finish(mut self) -> ParserResult<Expression> {
-        Self::collapse_expressions(&mut self.expressions, &mut self.operators, &self.variable)
+        let expr =
+            Self::collapse_expressions(&mut self.expressions, &mut self.operators, self.span)?;
+        let expr = match self.conversion {
+            Some(target) => Expression::Conversion(Box::new(expr), target),
+            None => expr,
+        };
+        match (self.variable, self.function) {
+            (Some(name), _) => Ok(Expression::Variable(name, Box::new(expr))),
+            (_, Some((name, parameters))) => Ok(Expression::FunctionDefinition(
+                name,
+                parameters,
+                Box::new(expr),
+            )),
+            (None, None) => Ok(expr),
+        }
+    }
+
+    fn invalid_expression(&mut self, span: Span) -> ParserError {
+        ParserError::InvalidExpression {
+            expressions: std::mem::take(&mut self.expressions),
+            operators: std::mem::take(&mut self.operators),
+            span,
+        }
     }
 
     fn convert_token_tree(
@@ -54,55 +121,108 @@ impl Parser {
         token_tree: TokenTree,
         allow_variable_def: bool,
     ) -> ParserResult<()> {
-        for (i, token) in token_tree.tokens.into_iter().enumerate() {
+        let spans = token_tree.spans();
+        if let (Some(first), Some(last)) = (spans.first(), spans.last()) {
+            self.span = first.to(*last);
+        }
+        let num_tokens = token_tree.tokens().len();
+        for (i, (token, span)) in token_tree.into_tokens().enumerate() {
+            // An operand right after another one is multiplied with it, like in `2x` and
+            // `(a+b)(a-b)`, unless it's a number since `2 3` is more likely a mistake
+            let is_implicit_factor = !matches!(token, Token::Literal(_));
             let expr = match token {
+                // Negating a date is an error, so it's left to the evaluator to report it
+                Token::Literal(literal) if literal.is_date() => {
+                    Some(self.maybe_negate(Expression::Literal(literal)))
+                }
                 Token::Literal(literal) => {
-                    let sn = match literal {
-                        Literal::Integer(i) => {
-                            Literal::Integer(if self.should_negate { -i } else { i })
-                        }
-                        Literal::Float(f) => {
-                            Literal::Float(if self.should_negate { -f } else { f })
-                        }
+                    let sn = if self.should_negate {
+                        -literal
+                    } else {
+                        literal
                     };
-                    self.is_sign = false;
                     self.should_negate = false;
-                    Some(Expression::Literal(sn))
+                    Some(self.maybe_negate(Expression::Literal(sn)))
+                }
+                // `~` can follow a sign and be followed by one, like in `-~x` and `~-5`
+                Token::Operator(Operator::BitNot) if self.expects_operand => {
+                    if self.should_negate {
+                        self.prefixes.push(FunctionName::Negate);
+                        self.should_negate = false;
+                    }
+                    self.prefixes.push(FunctionName::BitNot);
+                    self.is_sign = true;
+                    None
                 }
                 Token::Operator(operator) => {
                     if self.is_sign {
                         match operator {
                             Operator::Add => {}
                             Operator::Subtract => self.should_negate = true,
-                            Operator::Multiply => return Err(ParserError::InvalidSign('*')),
-                            Operator::Divide => return Err(ParserError::InvalidSign('/')),
-                            Operator::Modulo => return Err(ParserError::InvalidSign('%')),
-                            Operator::Power => return Err(ParserError::InvalidSign('^')),
+                            Operator::Multiply => return Err(ParserError::InvalidSign('*', span)),
+                            Operator::Divide => return Err(ParserError::InvalidSign('/', span)),
+                            Operator::Modulo => return Err(ParserError::InvalidSign('%', span)),
+                            Operator::Power => return Err(ParserError::InvalidSign('^', span)),
+                            Operator::BitAnd => return Err(ParserError::InvalidSign('&', span)),
+                            Operator::BitOr => return Err(ParserError::InvalidSign('|', span)),
+                            Operator::BitNot => unreachable!("`~` is handled above"),
+                            // `not` applies to everything up to the next `and` or `or`, so it's
+                            // collapsed like a binary operator with a placeholder on its left
+                            Operator::Not if self.prefixes.is_empty() => {
+                                self.expressions.push(None);
+                                self.operators.push(operator);
+                                continue;
+                            }
+                            Operator::Of
+                            | Operator::Off
+                            | Operator::On
+                            | Operator::AsPercentOf
+                            | Operator::BitXor
+                            | Operator::ShiftLeft
+                            | Operator::ShiftRight
+                            | Operator::Less
+                            | Operator::LessEqual
+                            | Operator::Greater
+                            | Operator::GreaterEqual
+                            | Operator::Equal
+                            | Operator::NotEqual
+                            | Operator::And
+                            | Operator::Or
+                            | Operator::Not => return Err(self.invalid_expression(span)),
                         }
                         self.is_sign = false;
+                    } else if self.expects_operand
+                        || operator == Operator::BitNot
+                        || operator == Operator::Not
+                    {
+                        return Err(self.invalid_expression(span));
                     } else {
                         self.operators.push(operator);
                         self.is_sign = true;
+                        self.expects_operand = true;
                     }
                     None
                 }
                 Token::Function(name, args) => {
                     let args = args
                         .into_iter()
-                        .map(|a| convert_token_tree(a, false))
+                        .map(|a| convert_nested_token_tree(a, span))
                         .collect::<ParserResult<Vec<_>>>()?;
-                    let function = {
-                        use FunctionName::*;
-                        let function = FunctionName::from_str(&name)
-                            .map_err(|_| ParserError::UnknownFunctionName(name))?;
-                        match function {
-                            Ceil if args.len() == 2 => CeilPrec,
-                            Floor if args.len() == 2 => FloorPrec,
-                            Round if args.len() == 2 => RoundPrec,
-                            _ => function,
+                    // Other names may be functions defined in the sheet, which only the evaluator
+                    // knows about
+                    let expr = match FunctionName::from_str(&name) {
+                        Ok(function) => {
+                            use FunctionName::*;
+                            let function = match function {
+                                Ceil if args.len() == 2 => CeilPrec,
+                                Floor if args.len() == 2 => FloorPrec,
+                                Round if args.len() == 2 => RoundPrec,
+                                _ => function,
+                            };
+                            Expression::Function(function, args)
                         }
+                        Err(_) => Expression::UserFunction(name, args),
                     };
-                    let expr = Expression::Function(function, args);
                     let expr = self.maybe_negate(expr);
                     self.is_sign = false;
                     self.should_negate = false;
@@ -110,11 +230,28 @@ impl Parser {
                 }
                 Token::VariableDefinition(name) => {
                     if i != 0 || !allow_variable_def {
-                        return Err(ParserError::InvalidVariableDefinition(name));
+                        return Err(ParserError::InvalidVariableDefinition(name, span));
+                    }
+                    if LineReference::find(&name).is_some() {
+                        return Err(ParserError::LineReferenceName(name, span));
                     }
                     self.variable = Some(name);
                     None
                 }
+                Token::FunctionDefinition(name, parameters) => {
+                    if i != 0 || !allow_variable_def {
+                        let signature = format!("{name}({})", parameters.join(", "));
+                        return Err(ParserError::InvalidVariableDefinition(signature, span));
+                    }
+                    if let Some(parameter) = parameters
+                        .iter()
+                        .find(|parameter| LineReference::find(parameter).is_some())
+                    {
+                        return Err(ParserError::LineReferenceName(parameter.clone(), span));
+                    }
+                    self.function = Some((name, parameters));
+                    None
+                }
                 Token::VariableReference(name) => {
                     let expr = Expression::VariableRef(name);
                     let expr = self.maybe_negate(expr);
@@ -122,30 +259,66 @@ impl Parser {
                     self.should_negate = false;
                     Some(expr)
                 }
+                Token::LineReference(reference) => {
+                    let expr = Expression::LineRef(reference);
+                    let expr = self.maybe_negate(expr);
+                    self.is_sign = false;
+                    self.should_negate = false;
+                    Some(expr)
+                }
                 Token::Nested(nested_tree) => {
-                    let expr = convert_token_tree(nested_tree, false)?;
+                    let expr = convert_nested_token_tree(nested_tree, span)?;
                     let expr = self.maybe_negate(expr);
                     self.is_sign = false;
                     self.should_negate = false;
                     Some(expr)
                 }
+                Token::Percent => {
+                    if self.expects_operand {
+                        return Err(self.invalid_expression(span));
+                    }
+                    let operand = self.expressions.pop().flatten();
+                    let percentage = match operand {
+                        Some(Expression::Literal(literal))
+                            if !literal.has_unit() && literal.currency().is_none() =>
+                        {
+                            Expression::Literal(Literal::Percentage(Box::new(literal)))
+                        }
+                        operand => Expression::Function(
+                            FunctionName::Percent,
+                            operand.into_iter().collect(),
+                        ),
+                    };
+                    self.expressions.push(Some(percentage));
+                    None
+                }
+                Token::Conversion(target) => {
+                    if self.expects_operand {
+                        return Err(self.invalid_expression(span));
+                    }
+                    if i != num_tokens - 1 {
+                        return Err(ParserError::InvalidConversion(span));
+                    }
+                    self.conversion = Some(target);
+                    None
+                }
             };
             if let Some(expr) = expr {
+                if !self.expects_operand {
+                    if !is_implicit_factor {
+                        self.expressions.push(Some(expr));
+                        return Err(self.invalid_expression(span));
+                    }
+                    self.operators.push(Operator::Multiply);
+                }
                 self.expressions.push(Some(expr));
+                self.expects_operand = false;
             }
         }
         match (self.expressions.is_empty(), self.operators.is_empty()) {
-            (true, true) => Err(ParserError::EmptyExpression),
-            (true, false) => {
-                let mut expressions = vec![];
-                let mut operators = vec![];
-                std::mem::swap(&mut expressions, &mut self.expressions);
-                std::mem::swap(&mut operators, &mut self.operators);
-                Err(ParserError::InvalidExpression {
-                    expressions,
-                    operators,
-                })
-            }
+            (true, true) if self.prefixes.is_empty() => Err(ParserError::EmptyExpression),
+            (true, _) => Err(self.invalid_expression(self.span)),
+            _ if self.expects_operand => Err(self.invalid_expression(Span::at(self.span.end))),
             _ => Ok(()),
         }
     }
@@ -153,56 +326,60 @@ impl Parser {
     fn collapse_expressions(
         exprs: &mut [Option<Expression>],
         operators: &mut [Operator],
-        variable: &Option<String>,
+        span: Span,
     ) -> ParserResult<Expression> {
-        let expr = if exprs.len() != operators.len() + 1 {
-            return Err(ParserError::InvalidExpression {
+        let invalid_expression =
+            |exprs: &[Option<Expression>], operators: &[Operator]| ParserError::InvalidExpression {
                 expressions: exprs.to_owned(),
                 operators: operators.to_owned(),
-            });
+                span,
+            };
+        if exprs.len() != operators.len() + 1 {
+            Err(invalid_expression(exprs, operators))
         } else if exprs.len() == 1 {
             let mut res = None;
             std::mem::swap(&mut exprs[0], &mut res);
-            res.unwrap()
+            res.ok_or_else(|| invalid_expression(exprs, operators))
         } else {
             let mut last_operator = (0, operators[0]);
             for (i, o) in operators.iter().enumerate().skip(1) {
-                match (last_operator.1, o) {
-                    (Operator::Power, _)
-                    | (
-                        Operator::Multiply | Operator::Divide | Operator::Modulo,
-                        Operator::Multiply
-                        | Operator::Divide
-                        | Operator::Modulo
-                        | Operator::Add
-                        | Operator::Subtract,
-                    )
-                    | (Operator::Add | Operator::Subtract, Operator::Add | Operator::Subtract) => {
-                        last_operator = (i, *o)
-                    }
-                    _ => {}
+                // The leftmost of several `not`s is applied last, so `not not a` is `not (not a)`
+                if (last_operator.1 == Operator::Power
+                    || o.precedence() <= last_operator.1.precedence())
+                    && !(last_operator.1 == Operator::Not && *o == Operator::Not)
+                {
+                    last_operator = (i, *o)
                 }
             }
+            if last_operator.1 == Operator::Not {
+                // Anything to the left of `not` binds tighter, like the `<` in `a < not b`
+                if last_operator.0 != 0 || exprs[0].is_some() {
+                    return Err(invalid_expression(exprs, operators));
+                }
+                let operand =
+                    Self::collapse_expressions(&mut exprs[1..], &mut operators[1..], span)?;
+                return Ok(Expression::Function(FunctionName::Not, vec![operand]));
+            }
             let left = if last_operator.0 == 0 {
                 let mut left = None;
                 std::mem::swap(&mut exprs[0], &mut left);
-                left.unwrap()
+                left.ok_or_else(|| invalid_expression(exprs, operators))?
             } else {
                 Self::collapse_expressions(
                     &mut exprs[..last_operator.0 + 1],
                     &mut operators[..last_operator.0],
-                    variable,
+                    span,
                 )?
             };
             let right = if last_operator.0 == operators.len().saturating_sub(1) {
                 let mut left = None;
                 std::mem::swap(&mut exprs[operators.len()], &mut left);
-                left.unwrap()
+                left.ok_or_else(|| invalid_expression(exprs, operators))?
             } else {
                 Self::collapse_expressions(
                     &mut exprs[last_operator.0 + 1..],
                     &mut operators[last_operator.0 + 1..],
-                    variable,
+                    span,
                 )?
             };
             let function_name = match last_operator.1 {
@@ -212,22 +389,40 @@ impl Parser {
                 Operator::Divide => FunctionName::Divide,
                 Operator::Modulo => FunctionName::Modulus,
                 Operator::Power => FunctionName::Power,
+                Operator::Of => FunctionName::Multiply,
+                Operator::Off => FunctionName::PercentOff,
+                Operator::On => FunctionName::PercentOn,
+                Operator::AsPercentOf => FunctionName::AsPercentOf,
+                Operator::BitAnd => FunctionName::BitAnd,
+                Operator::BitOr => FunctionName::BitOr,
+                Operator::BitXor => FunctionName::BitXor,
+                Operator::ShiftLeft => FunctionName::ShiftLeft,
+                Operator::ShiftRight => FunctionName::ShiftRight,
+                Operator::Less => FunctionName::Less,
+                Operator::LessEqual => FunctionName::LessEqual,
+                Operator::Greater => FunctionName::Greater,
+                Operator::GreaterEqual => FunctionName::GreaterEqual,
+                Operator::Equal => FunctionName::Equal,
+                Operator::NotEqual => FunctionName::NotEqual,
+                Operator::And => FunctionName::And,
+                Operator::Or => FunctionName::Or,
+                Operator::BitNot => unreachable!("`~` is a prefix"),
+                Operator::Not => unreachable!("`not` is collapsed above"),
             };
-            Expression::Function(function_name, vec![left, right])
-        };
-        if let Some(name) = variable {
-            Ok(Expression::Variable(name.clone(), Box::new(expr)))
-        } else {
-            Ok(expr)
+            Ok(Expression::Function(function_name, vec![left, right]))
         }
     }
 
+    /// Applies the closest sign or `~` first, so `~-5` is `4` and `-~1` is `2`
     fn maybe_negate(&mut self, expr: Expression) -> Expression {
         let expr = if self.should_negate {
             Expression::Function(FunctionName::Negate, vec![expr])
         } else {
             expr
         };
+        let expr = self.prefixes.drain(..).rev().fold(expr, |expr, prefix| {
+            Expression::Function(prefix, vec![expr])
+        });
         self.is_sign = false;
         self.should_negate = false;
         expr
@@ -236,6 +431,8 @@ impl Parser {
 
 #[cfg(test)]
 mod tests {
+    use raekna_common::expression::Literal;
+
     use super::*;
     use crate::lexer::{Operator, Token};
 
@@ -244,18 +441,15 @@ mod tests {
 
         #[test]
         fn same_operator_repeated() {
-            let tt = TokenTree {
-                num_operators: 3,
-                tokens: vec![
-                    Token::Literal(Literal::Integer(1)),
-                    Token::Operator(Operator::Add),
-                    Token::Literal(Literal::Integer(2)),
-                    Token::Operator(Operator::Add),
-                    Token::Literal(Literal::Integer(3)),
-                    Token::Operator(Operator::Add),
-                    Token::Literal(Literal::Integer(4)),
-                ],
-            };
+            let tt = TokenTree::from_tokens(vec![
+                Token::Literal(Literal::Integer(1)),
+                Token::Operator(Operator::Add),
+                Token::Literal(Literal::Integer(2)),
+                Token::Operator(Operator::Add),
+                Token::Literal(Literal::Integer(3)),
+                Token::Operator(Operator::Add),
+                Token::Literal(Literal::Integer(4)),
+            ]);
 
             let expected = Expression::Function(
                 FunctionName::Add,
@@ -283,24 +477,21 @@ mod tests {
 
         #[test]
         fn different_operators_mixed() {
-            let tt = TokenTree {
-                num_operators: 6,
-                tokens: vec![
-                    Token::Literal(Literal::Integer(1)),
-                    Token::Operator(Operator::Add),
-                    Token::Literal(Literal::Integer(2)),
-                    Token::Operator(Operator::Multiply),
-                    Token::Literal(Literal::Integer(3)),
-                    Token::Operator(Operator::Power),
-                    Token::Literal(Literal::Integer(4)),
-                    Token::Operator(Operator::Divide),
-                    Token::Literal(Literal::Integer(5)),
-                    Token::Operator(Operator::Subtract),
-                    Token::Literal(Literal::Integer(6)),
-                    Token::Operator(Operator::Add),
-                    Token::Literal(Literal::Integer(7)),
-                ],
-            };
+            let tt = TokenTree::from_tokens(vec![
+                Token::Literal(Literal::Integer(1)),
+                Token::Operator(Operator::Add),
+                Token::Literal(Literal::Integer(2)),
+                Token::Operator(Operator::Multiply),
+                Token::Literal(Literal::Integer(3)),
+                Token::Operator(Operator::Power),
+                Token::Literal(Literal::Integer(4)),
+                Token::Operator(Operator::Divide),
+                Token::Literal(Literal::Integer(5)),
+                Token::Operator(Operator::Subtract),
+                Token::Literal(Literal::Integer(6)),
+                Token::Operator(Operator::Add),
+                Token::Literal(Literal::Integer(7)),
+            ]);
 
             let expected = Expression::Function(
                 FunctionName::Add,
@@ -351,28 +542,21 @@ mod tests {
 
     #[test]
     fn mix_of_expressions() {
-        let tt = TokenTree {
-            num_operators: 1,
-            tokens: vec![
-                Token::VariableDefinition("var_def".to_owned()),
-                Token::Function(
-                    "sqrt".to_owned(),
-                    vec![TokenTree {
-                        num_operators: 0,
-                        tokens: vec![Token::Literal(Literal::Integer(1))],
-                    }],
-                ),
-                Token::Operator(Operator::Multiply),
-                Token::Nested(TokenTree {
-                    num_operators: 1,
-                    tokens: vec![
-                        Token::VariableReference("my_var".to_owned()),
-                        Token::Operator(Operator::Add),
-                        Token::VariableReference("my_second_var".to_owned()),
-                    ],
-                }),
-            ],
-        };
+        let tt = TokenTree::from_tokens(vec![
+            Token::VariableDefinition("var_def".to_owned()),
+            Token::Function(
+                "sqrt".to_owned(),
+                vec![TokenTree::from_tokens(vec![Token::Literal(
+                    Literal::Integer(1),
+                )])],
+            ),
+            Token::Operator(Operator::Multiply),
+            Token::Nested(TokenTree::from_tokens(vec![
+                Token::VariableReference("my_var".to_owned()),
+                Token::Operator(Operator::Add),
+                Token::VariableReference("my_second_var".to_owned()),
+            ])),
+        ]);
 
         let expected = Expression::Variable(
             "var_def".to_owned(),
@@ -400,13 +584,10 @@ mod tests {
 
     #[test]
     fn negative_number() {
-        let tt = TokenTree {
-            num_operators: 1,
-            tokens: vec![
-                Token::Operator(Operator::Subtract),
-                Token::Literal(Literal::Integer(2)),
-            ],
-        };
+        let tt = TokenTree::from_tokens(vec![
+            Token::Operator(Operator::Subtract),
+            Token::Literal(Literal::Integer(2)),
+        ]);
 
         let expected = Expression::Literal(Literal::Integer(-2));
         let actual = convert_token_tree(tt, true).unwrap();
@@ -416,28 +597,19 @@ mod tests {
 
     #[test]
     fn function_with_negative_arguments() {
-        let tt = TokenTree {
-            num_operators: 0,
-            tokens: vec![Token::Function(
-                "add".to_owned(),
-                vec![
-                    TokenTree {
-                        num_operators: 1,
-                        tokens: vec![
-                            Token::Operator(Operator::Subtract),
-                            Token::Literal(Literal::Integer(1)),
-                        ],
-                    },
-                    TokenTree {
-                        num_operators: 1,
-                        tokens: vec![
-                            Token::Operator(Operator::Subtract),
-                            Token::Literal(Literal::Integer(2)),
-                        ],
-                    },
-                ],
-            )],
-        };
+        let tt = TokenTree::from_tokens(vec![Token::Function(
+            "add".to_owned(),
+            vec![
+                TokenTree::from_tokens(vec![
+                    Token::Operator(Operator::Subtract),
+                    Token::Literal(Literal::Integer(1)),
+                ]),
+                TokenTree::from_tokens(vec![
+                    Token::Operator(Operator::Subtract),
+                    Token::Literal(Literal::Integer(2)),
+                ]),
+            ],
+        )]);
 
         let expected = Expression::Function(
             FunctionName::Add,
@@ -451,55 +623,50 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn unknown_function_name() {
+        let tt = TokenTree::from_tokens(vec![Token::Function("unknown".to_owned(), vec![])]);
+        let actual = convert_token_tree(tt, true).unwrap();
+        assert_eq!(
+            actual,
+            Expression::UserFunction("unknown".to_owned(), vec![])
+        );
+    }
+
     #[test]
     #[should_panic]
-    fn invalid_function_name() {
-        let tt = TokenTree {
-            num_operators: 0,
-            tokens: vec![Token::Function("invalid".to_owned(), vec![])],
-        };
+    fn only_function_def() {
+        let tt = TokenTree::from_tokens(vec![Token::FunctionDefinition("f".to_owned(), vec![])]);
         convert_token_tree(tt, true).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn only_variable_def() {
-        let tt = TokenTree {
-            num_operators: 0,
-            tokens: vec![Token::VariableDefinition("my_var".to_owned())],
-        };
+        let tt = TokenTree::from_tokens(vec![Token::VariableDefinition("my_var".to_owned())]);
         convert_token_tree(tt, true).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn variable_def_is_not_first_token() {
-        let tt = TokenTree {
-            num_operators: 0,
-            tokens: vec![
-                Token::Literal(Literal::Integer(1)),
-                Token::VariableDefinition("invalid".to_owned()),
-            ],
-        };
+        let tt = TokenTree::from_tokens(vec![
+            Token::Literal(Literal::Integer(1)),
+            Token::VariableDefinition("invalid".to_owned()),
+        ]);
         convert_token_tree(tt, true).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn variable_def_in_nested_token_tree() {
-        let tt = TokenTree {
-            num_operators: 0,
-            tokens: vec![
-                Token::Nested(TokenTree {
-                    num_operators: 0,
-                    tokens: vec![
-                        Token::VariableDefinition("invalid".to_owned()),
-                        Token::Literal(Literal::Integer(1)),
-                    ],
-                }),
+        let tt = TokenTree::from_tokens(vec![
+            Token::Nested(TokenTree::from_tokens(vec![
+                Token::VariableDefinition("invalid".to_owned()),
                 Token::Literal(Literal::Integer(1)),
-            ],
-        };
+            ])),
+            Token::Literal(Literal::Integer(1)),
+        ]);
         convert_token_tree(tt, true).unwrap();
     }
 }
@@ -1,5 +1,6 @@
+use num_rational::BigRational;
 use raekna_common::{
-    expression::{Expression, Literal},
+    expression::{Expression, LineReference, Literal},
     function_name::FunctionName,
 };
 
@@ -12,8 +13,8 @@ fn int(value: i64) -> Expression {
     Expression::Literal(literal)
 }
 
-fn float(value: f64) -> Expression {
-    let literal = Literal::Float(value);
+fn ratio(numer: i64, denom: i64) -> Expression {
+    let literal = Literal::from(BigRational::new(numer.into(), denom.into()));
     Expression::Literal(literal)
 }
 
@@ -32,6 +33,16 @@ generate_function_expression!(div_expr, Divide);
 generate_function_expression!(mod_expr, Modulus);
 generate_function_expression!(pow_expr, Power);
 generate_function_expression!(sqrt_expr, SquareRoot);
+generate_function_expression!(bitand_expr, BitAnd);
+generate_function_expression!(bitor_expr, BitOr);
+generate_function_expression!(bitxor_expr, BitXor);
+generate_function_expression!(bitnot_expr, BitNot);
+generate_function_expression!(shl_expr, ShiftLeft);
+generate_function_expression!(lt_expr, Less);
+generate_function_expression!(eq_expr, Equal);
+generate_function_expression!(and_expr, And);
+generate_function_expression!(or_expr, Or);
+generate_function_expression!(not_expr, Not);
 
 mod parse_literals {
     use super::*;
@@ -57,7 +68,7 @@ mod parse_literals {
         fn positive() {
             let input = "11.1";
 
-            let expected = float(11.1);
+            let expected = ratio(111, 10);
             let actual = parse(input);
 
             assert_eq!(actual, expected);
@@ -83,7 +94,7 @@ mod parse_literals {
                 fn pos_e_neg() {
                     let input = "4e-5";
 
-                    let expected = float(0.00004);
+                    let expected = ratio(1, 25_000);
                     let actual = parse(input);
 
                     assert_eq!(actual, expected);
@@ -107,7 +118,7 @@ mod parse_literals {
                 fn pos_e_neg() {
                     let input = "7.987123e-3";
 
-                    let expected = float(0.007987123);
+                    let expected = ratio(7_987_123, 1_000_000_000);
                     let actual = parse(input);
 
                     assert_eq!(actual, expected);
@@ -441,6 +452,84 @@ mod order_of_operations {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn bitwise_operators_bind_looser_than_arithmetic() {
+        let input = "1 | 2 xor 3 & 4 << 5 + 6";
+
+        let expected = bitor_expr(vec![
+            int(1),
+            bitxor_expr(vec![
+                int(2),
+                bitand_expr(vec![
+                    int(3),
+                    shl_expr(vec![int(4), add_expr(vec![int(5), int(6)])]),
+                ]),
+            ]),
+        ]);
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn shifts_are_left_associative() {
+        let input = "256 >> 2 << 1";
+
+        let expected = Expression::Function(
+            FunctionName::ShiftLeft,
+            vec![
+                Expression::Function(FunctionName::ShiftRight, vec![int(256), int(2)]),
+                int(1),
+            ],
+        );
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn bitwise_not_binds_tighter_than_everything() {
+        [
+            ("~5", bitnot_expr(vec![int(5)])),
+            ("~-5", bitnot_expr(vec![int(-5)])),
+            (
+                "~~x",
+                bitnot_expr(vec![bitnot_expr(vec![Expression::VariableRef(
+                    "x".to_owned(),
+                )])]),
+            ),
+            ("~~1.5", bitnot_expr(vec![bitnot_expr(vec![ratio(3, 2)])])),
+            (
+                "-~1",
+                Expression::Function(FunctionName::Negate, vec![bitnot_expr(vec![int(1)])]),
+            ),
+            (
+                "2 * ~x",
+                mul_expr(vec![
+                    int(2),
+                    bitnot_expr(vec![Expression::VariableRef("x".to_owned())]),
+                ]),
+            ),
+            (
+                "2 * -~x",
+                mul_expr(vec![
+                    int(2),
+                    Expression::Function(
+                        FunctionName::Negate,
+                        vec![bitnot_expr(vec![Expression::VariableRef("x".to_owned())])],
+                    ),
+                ]),
+            ),
+        ]
+        .into_iter()
+        .for_each(|(input, expected)| {
+            let actual = parse(input);
+            assert_eq!(actual, expected);
+        });
+        assert!(raekna_parser::parse("5 ~ 3").is_err());
+        assert!(raekna_parser::parse("& 3").is_err());
+    }
 }
 
 mod functions {
@@ -567,10 +656,37 @@ mod functions {
     }
 
     #[test]
-    #[should_panic]
-    fn invalid_function_name() {
-        let input = "invalid_function(25)";
-        parse(input);
+    fn unknown_names_are_user_functions() {
+        let input = "my_function(25, 2)";
+
+        let expected = Expression::UserFunction("my_function".to_owned(), vec![int(25), int(2)]);
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn function_definition() {
+        let input = "area(w, h): w * h";
+
+        let expected = Expression::FunctionDefinition(
+            "area".to_owned(),
+            vec!["w".to_owned(), "h".to_owned()],
+            Box::new(mul_expr(vec![
+                Expression::VariableRef("w".to_owned()),
+                Expression::VariableRef("h".to_owned()),
+            ])),
+        );
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn function_definition_must_be_first() {
+        assert!(raekna_parser::parse("1 + f(x): x").is_err());
+        assert!(raekna_parser::parse("max(f(x): x, 1)").is_err());
+        assert!(raekna_parser::parse("a: f(x): x").is_err());
     }
 }
 
@@ -594,6 +710,19 @@ mod variables {
         parse(input);
     }
 
+    #[test]
+    fn variable_definition_with_mixed_operators() {
+        let input = "my_var: 1 + 2 * 3";
+
+        let expected = Expression::Variable(
+            "my_var".to_owned(),
+            Box::new(add_expr(vec![int(1), mul_expr(vec![int(2), int(3)])])),
+        );
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn variable_reference() {
         let input = "5 + my_var";
@@ -615,6 +744,720 @@ mod variables {
     }
 }
 
+mod units {
+    use raekna_common::{
+        expression::ConversionTarget,
+        unit::{NamedUnit, Unit},
+    };
+
+    use super::*;
+
+    fn unit(name: &str) -> Unit {
+        Unit::new(NamedUnit::find(name).unwrap(), 1)
+    }
+
+    fn quantity(value: i64, unit: Unit) -> Expression {
+        Expression::Literal(Literal::quantity(Literal::Integer(value), unit))
+    }
+
+    #[test]
+    fn number_with_unit() {
+        let input = "5 km + 300m";
+
+        let expected = add_expr(vec![quantity(5, unit("km")), quantity(300, unit("m"))]);
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn compound_unit() {
+        let input = "9.81 m/s^2 * 2 kg";
+
+        let acceleration = unit("m").with_term(NamedUnit::find("s").unwrap(), -2);
+        let expected = mul_expr(vec![
+            Expression::Literal(Literal::quantity(
+                Literal::from(BigRational::new(981.into(), 100.into())),
+                acceleration,
+            )),
+            quantity(2, unit("kg")),
+        ]);
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn unit_names_are_not_variables_or_functions() {
+        let input = "5 min(1, 2)";
+        let expected = mul_expr(vec![
+            int(5),
+            Expression::Function(FunctionName::Min, vec![int(1), int(2)]),
+        ]);
+        assert_eq!(parse(input), expected);
+
+        let input = "10 / s";
+        let expected = div_expr(vec![int(10), Expression::VariableRef("s".to_owned())]);
+        assert_eq!(parse(input), expected);
+    }
+
+    #[test]
+    fn conversion() {
+        let input = "x: 60 mph in m/s";
+
+        let target = unit("m").with_term(NamedUnit::find("s").unwrap(), -1);
+        let expected = Expression::Variable(
+            "x".to_owned(),
+            Box::new(Expression::Conversion(
+                Box::new(quantity(60, unit("mph"))),
+                ConversionTarget::Unit(target),
+            )),
+        );
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn conversion_applies_to_whole_expression() {
+        let input = "5 km + 300 m to m";
+
+        let expected = Expression::Conversion(
+            Box::new(add_expr(vec![
+                quantity(5, unit("km")),
+                quantity(300, unit("m")),
+            ])),
+            ConversionTarget::Unit(unit("m")),
+        );
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn conversion_must_be_last() {
+        assert!(raekna_parser::parse("5 km in m + 3 m").is_err());
+        assert!(raekna_parser::parse("in m").is_err());
+    }
+}
+
+mod currencies {
+    use raekna_common::{currency::Currency, expression::ConversionTarget};
+
+    use super::*;
+
+    fn money(value: i64, code: &str) -> Expression {
+        Expression::Literal(Literal::money(
+            Literal::Integer(value),
+            Currency::find(code).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn currency_codes_and_symbols() {
+        let input = "100 USD + €20 - 3SEK";
+
+        let expected = sub_expr(vec![
+            add_expr(vec![money(100, "USD"), money(20, "EUR")]),
+            money(3, "SEK"),
+        ]);
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn negative_amount() {
+        let input = "-$5";
+
+        let expected = money(-5, "USD");
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn conversion() {
+        let input = "100 USD in SEK";
+
+        let expected = Expression::Conversion(
+            Box::new(money(100, "USD")),
+            ConversionTarget::Currency(Currency::find("SEK").unwrap()),
+        );
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn unknown_codes_are_variables() {
+        let input = "100 * usd";
+
+        let expected = mul_expr(vec![int(100), Expression::VariableRef("usd".to_owned())]);
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+
+        let input = "100 XYZ";
+        let expected = mul_expr(vec![int(100), Expression::VariableRef("XYZ".to_owned())]);
+        assert_eq!(parse(input), expected);
+    }
+}
+
+mod percentages {
+    use super::*;
+
+    fn percent(value: i64) -> Expression {
+        Expression::Literal(Literal::Percentage(Box::new(Literal::Integer(value))))
+    }
+
+    #[test]
+    fn percentage_literals() {
+        [
+            ("200 + 15%", add_expr(vec![int(200), percent(15)])),
+            (
+                "200 - 15% - 5",
+                sub_expr(vec![sub_expr(vec![int(200), percent(15)]), int(5)]),
+            ),
+            ("-15%", percent(-15)),
+            (
+                "(1 + 2)%",
+                Expression::Function(FunctionName::Percent, vec![add_expr(vec![int(1), int(2)])]),
+            ),
+        ]
+        .into_iter()
+        .for_each(|(input, expected)| {
+            let actual = parse(input);
+            assert_eq!(actual, expected);
+        });
+    }
+
+    #[test]
+    fn percent_sign_followed_by_an_operand_is_modulo() {
+        [
+            ("7 % 3", mod_expr(vec![int(7), int(3)])),
+            (
+                "7 % x",
+                mod_expr(vec![int(7), Expression::VariableRef("x".to_owned())]),
+            ),
+            ("7 % (3)", mod_expr(vec![int(7), int(3)])),
+        ]
+        .into_iter()
+        .for_each(|(input, expected)| {
+            let actual = parse(input);
+            assert_eq!(actual, expected);
+        });
+    }
+
+    #[test]
+    fn percentage_keywords() {
+        [
+            ("20% of 80", mul_expr(vec![percent(20), int(80)])),
+            (
+                "10% off 120",
+                Expression::Function(FunctionName::PercentOff, vec![percent(10), int(120)]),
+            ),
+            (
+                "5% on 30",
+                Expression::Function(FunctionName::PercentOn, vec![percent(5), int(30)]),
+            ),
+            (
+                "50 as % of 200",
+                Expression::Function(FunctionName::AsPercentOf, vec![int(50), int(200)]),
+            ),
+        ]
+        .into_iter()
+        .for_each(|(input, expected)| {
+            let actual = parse(input);
+            assert_eq!(actual, expected);
+        });
+    }
+
+    #[test]
+    fn keywords_bind_like_multiplication() {
+        let input = "1 + 10% off 120 ^ 2";
+
+        let expected = add_expr(vec![
+            int(1),
+            Expression::Function(
+                FunctionName::PercentOff,
+                vec![percent(10), pow_expr(vec![int(120), int(2)])],
+            ),
+        ]);
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn keywords_are_not_operands() {
+        assert!(raekna_parser::parse("of 5").is_err());
+        assert!(raekna_parser::parse("5 off").is_err());
+
+        let input = "offset * 2";
+        let expected = mul_expr(vec![Expression::VariableRef("offset".to_owned()), int(2)]);
+        assert_eq!(parse(input), expected);
+    }
+}
+
+mod dates {
+    use chrono::{FixedOffset, NaiveDate, NaiveTime};
+    use raekna_common::{
+        expression::ConversionTarget,
+        unit::{NamedUnit, Unit},
+    };
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn time(hour: u32, minute: u32, second: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, second).unwrap()
+    }
+
+    fn quantity(value: i64, unit: &str) -> Expression {
+        Expression::Literal(Literal::quantity(
+            Literal::Integer(value),
+            Unit::new(NamedUnit::find(unit).unwrap(), 1),
+        ))
+    }
+
+    #[test]
+    fn date_and_time_literals() {
+        let christmas = date(2026, 12, 24);
+        [
+            ("2026-12-24", Literal::Date(christmas)),
+            ("9:05", Literal::Time(time(9, 5, 0))),
+            ("14:30:15", Literal::Time(time(14, 30, 15))),
+            (
+                "2026-12-24 14:30",
+                Literal::DateTime(christmas.and_time(time(14, 30, 0)), None),
+            ),
+            (
+                "2026-12-24T14:30 UTC-5:30",
+                Literal::DateTime(
+                    christmas.and_time(time(14, 30, 0)),
+                    FixedOffset::west_opt(5 * 3600 + 1800),
+                ),
+            ),
+        ]
+        .into_iter()
+        .for_each(|(input, expected)| {
+            let actual = parse(input);
+            assert_eq!(actual, Expression::Literal(expected));
+        });
+    }
+
+    #[test]
+    fn dates_and_times_that_dont_exist_are_errors() {
+        for (input, invalid) in [
+            ("2026-02-29", "2026-02-29"),
+            ("2026-13-45", "2026-13-45"),
+            ("1 + 2026-13-01 * 2", "2026-13-01"),
+            ("25:00", "25:00"),
+            ("9:60", "9:60"),
+            ("2026-12-24 24:30", "24:30"),
+        ] {
+            let actual = raekna_parser::parse(input);
+            assert!(
+                matches!(&actual, Err(raekna_parser::ParserError::InvalidDate(text, _)) if text == invalid),
+                "{input}: {actual:?}"
+            );
+        }
+        assert!(raekna_parser::parse("12:345").is_err());
+        assert_eq!(
+            parse("2026 - 13 - 1"),
+            sub_expr(vec![sub_expr(vec![int(2026), int(13)]), int(1)])
+        );
+    }
+
+    #[test]
+    fn dates_are_negated_by_the_evaluator() {
+        let input = "-2026-12-24";
+
+        let expected = Expression::Function(
+            FunctionName::Negate,
+            vec![Expression::Literal(Literal::Date(date(2026, 12, 24)))],
+        );
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn compound_durations() {
+        [
+            ("3h 25min", quantity(205, "min")),
+            ("5 ft 3 inch", quantity(63, "inch")),
+            (
+                "1 h 30 min * 2",
+                mul_expr(vec![quantity(90, "min"), int(2)]),
+            ),
+        ]
+        .into_iter()
+        .for_each(|(input, expected)| {
+            let actual = parse(input);
+            assert_eq!(actual, expected);
+        });
+        assert!(raekna_parser::parse("3 h 2 km").is_err());
+    }
+
+    #[test]
+    fn time_zone_conversion() {
+        let input = "now in UTC+9";
+
+        let expected = Expression::Conversion(
+            Box::new(Expression::VariableRef("now".to_owned())),
+            ConversionTarget::TimeZone(FixedOffset::east_opt(9 * 3600).unwrap()),
+        );
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+}
+
+mod bases {
+    use raekna_common::expression::{ConversionTarget, Format};
+
+    use super::*;
+
+    #[test]
+    fn prefixed_literals() {
+        let input = "0xFF + 0b1010 * 0o17";
+
+        let expected = add_expr(vec![int(255), mul_expr(vec![int(10), int(15)])]);
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn conversion_to_base() {
+        [
+            ("255 in hex", Format::Hexadecimal),
+            ("255 to bin", Format::Binary),
+            ("255 in oct", Format::Octal),
+        ]
+        .into_iter()
+        .for_each(|(input, format)| {
+            let expected =
+                Expression::Conversion(Box::new(int(255)), ConversionTarget::Format(format));
+            let actual = parse(input);
+            assert_eq!(actual, expected);
+        });
+    }
+}
+
+mod comparisons {
+    use super::*;
+
+    fn var(name: &str) -> Expression {
+        Expression::VariableRef(name.to_owned())
+    }
+
+    #[test]
+    fn comparison_operators() {
+        [
+            ("1 < 2", FunctionName::Less),
+            ("1 <= 2", FunctionName::LessEqual),
+            ("1 > 2", FunctionName::Greater),
+            ("1 >= 2", FunctionName::GreaterEqual),
+            ("1 == 2", FunctionName::Equal),
+            ("1 != 2", FunctionName::NotEqual),
+        ]
+        .into_iter()
+        .for_each(|(input, fn_name)| {
+            let expected = Expression::Function(fn_name, vec![int(1), int(2)]);
+            let actual = parse(input);
+            assert_eq!(actual, expected);
+        });
+    }
+
+    #[test]
+    fn comparisons_bind_looser_than_arithmetic() {
+        let input = "a + 1 < b | 2";
+
+        let expected = lt_expr(vec![
+            add_expr(vec![var("a"), int(1)]),
+            bitor_expr(vec![var("b"), int(2)]),
+        ]);
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let input = "a or b and c == 1";
+
+        let expected = or_expr(vec![
+            var("a"),
+            and_expr(vec![var("b"), eq_expr(vec![var("c"), int(1)])]),
+        ]);
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn not_applies_up_to_the_next_and_or_or() {
+        [
+            ("not a", not_expr(vec![var("a")])),
+            ("not not a", not_expr(vec![not_expr(vec![var("a")])])),
+            ("not a < 1", not_expr(vec![lt_expr(vec![var("a"), int(1)])])),
+            (
+                "not a and not b",
+                and_expr(vec![not_expr(vec![var("a")]), not_expr(vec![var("b")])]),
+            ),
+            (
+                "not(a) or b",
+                or_expr(vec![not_expr(vec![var("a")]), var("b")]),
+            ),
+        ]
+        .into_iter()
+        .for_each(|(input, expected)| {
+            let actual = parse(input);
+            assert_eq!(actual, expected);
+        });
+    }
+
+    #[test]
+    fn conditional() {
+        let input = "if(a > 0, a, -a)";
+
+        let expected = Expression::Function(
+            FunctionName::If,
+            vec![
+                Expression::Function(FunctionName::Greater, vec![var("a"), int(0)]),
+                var("a"),
+                Expression::Function(FunctionName::Negate, vec![var("a")]),
+            ],
+        );
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn invalid_operators() {
+        ["not", "a not b", "a < not b", "-not a", "< 1", "1 <"]
+            .into_iter()
+            .for_each(|input| assert!(raekna_parser::parse(input).is_err(), "{input}"));
+    }
+}
+
+mod comments {
+    use super::*;
+
+    #[test]
+    fn comments_after_an_expression_are_ignored() {
+        ["5 * 3 # three boxes", "5 * 3 // three boxes", "5 * 3#"]
+            .into_iter()
+            .for_each(|input| {
+                let expected = mul_expr(vec![int(5), int(3)]);
+                let actual = parse(input);
+                assert_eq!(actual, expected);
+            });
+    }
+
+    #[test]
+    fn lines_without_an_expression_are_empty() {
+        [
+            "# just a note",
+            "  // todo",
+            "Groceries:",
+            "Rent: # monthly",
+        ]
+        .into_iter()
+        .for_each(|input| {
+            let actual = raekna_parser::parse(input);
+            assert!(
+                matches!(actual, Err(raekna_parser::ParserError::EmptyExpression)),
+                "{input}"
+            );
+        });
+    }
+
+    #[test]
+    fn comment_after_an_incomplete_expression() {
+        let error = raekna_parser::parse("1 + # note").unwrap_err();
+        assert_eq!(error.to_string(), "missing operand");
+        assert_eq!(error.span(), Some(raekna_common::span::Span::at(3)));
+    }
+}
+
+mod line_references {
+    use super::*;
+
+    fn line(reference: LineReference) -> Expression {
+        Expression::LineRef(reference)
+    }
+
+    #[test]
+    fn references() {
+        let test_cases = [
+            (
+                "prev * 2",
+                mul_expr(vec![line(LineReference::Previous), int(2)]),
+            ),
+            ("ans", line(LineReference::Previous)),
+            (
+                "#1 + line12",
+                add_expr(vec![
+                    line(LineReference::Line(1)),
+                    line(LineReference::Line(12)),
+                ]),
+            ),
+            (
+                "-#?",
+                Expression::Function(FunctionName::Negate, vec![line(LineReference::Removed)]),
+            ),
+            ("#3 # the total", line(LineReference::Line(3))),
+            ("line", Expression::VariableRef("line".to_owned())),
+        ];
+        for (input, expected) in test_cases {
+            let actual = parse(input);
+            assert_eq!(actual, expected, "input: {input}");
+        }
+    }
+
+    #[test]
+    fn references_cant_be_defined() {
+        for input in ["prev: 5", "line2: 5", "f(ans): ans"] {
+            let actual = raekna_parser::parse(input);
+            assert!(
+                matches!(
+                    actual,
+                    Err(raekna_parser::ParserError::LineReferenceName(..))
+                ),
+                "{input}"
+            );
+        }
+    }
+}
+
+mod implicit_multiplication {
+    use super::*;
+
+    fn var(name: &str) -> Expression {
+        Expression::VariableRef(name.to_owned())
+    }
+
+    #[test]
+    fn numbers_and_names() {
+        let test_cases = [
+            ("2pi", mul_expr(vec![int(2), var("pi")])),
+            ("2x", mul_expr(vec![int(2), var("x")])),
+            ("2.5 x", mul_expr(vec![ratio(5, 2), var("x")])),
+            ("x y", mul_expr(vec![var("x"), var("y")])),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(parse(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn groups_and_function_calls() {
+        let test_cases = [
+            (
+                "3(4+5)",
+                mul_expr(vec![int(3), add_expr(vec![int(4), int(5)])]),
+            ),
+            (
+                "(a+b)(a-b)",
+                mul_expr(vec![
+                    add_expr(vec![var("a"), var("b")]),
+                    sub_expr(vec![var("a"), var("b")]),
+                ]),
+            ),
+            ("2sqrt(4)", mul_expr(vec![int(2), sqrt_expr(vec![int(4)])])),
+            (
+                "x f(2)",
+                mul_expr(vec![
+                    var("x"),
+                    Expression::UserFunction("f".to_owned(), vec![int(2)]),
+                ]),
+            ),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(parse(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn names_followed_by_a_group_are_function_calls() {
+        let input = "x(2)";
+
+        let expected = Expression::UserFunction("x".to_owned(), vec![int(2)]);
+        let actual = parse(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn precedence_matches_explicit_multiplication() {
+        let test_cases = [
+            ("2x^2", "2*x^2"),
+            ("1/2x", "1/2*x"),
+            ("-2x", "-2*x"),
+            ("2x + 1", "2*x + 1"),
+            ("2x % 3", "2*x % 3"),
+            ("3(4+5)^2", "3*(4+5)^2"),
+            ("a: 2x", "a: 2*x"),
+        ];
+        for (input, explicit) in test_cases {
+            assert_eq!(parse(input), parse(explicit), "{input}");
+        }
+    }
+
+    #[test]
+    fn exponents_are_part_of_the_number() {
+        let test_cases = [
+            ("2e3", int(2000)),
+            ("2E3", int(2000)),
+            ("2e-3", ratio(1, 500)),
+            ("2e", mul_expr(vec![int(2), var("e")])),
+            ("2e3x", mul_expr(vec![int(2000), var("x")])),
+            ("2ee", mul_expr(vec![int(2), var("ee")])),
+            ("2 e3", mul_expr(vec![int(2), var("e3")])),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(parse(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn prefixes_without_digits_are_errors() {
+        for input in ["0x", "0b", "0o", "0xZZ", "0b102", "0x1 + 0xG"] {
+            assert!(
+                matches!(
+                    raekna_parser::parse(input),
+                    Err(raekna_parser::ParserError::InvalidSyntax(_))
+                ),
+                "{input}"
+            );
+        }
+        assert_eq!(parse("0xff"), int(255));
+        assert_eq!(parse("0 x"), mul_expr(vec![int(0), var("x")]));
+        assert!(matches!(parse("0bit"), Expression::Literal(_)));
+    }
+
+    #[test]
+    fn units_are_not_multiplied() {
+        assert_eq!(parse("2m"), parse("2 m"));
+        assert!(matches!(parse("2m"), Expression::Literal(_)));
+    }
+
+    #[test]
+    fn numbers_are_not_multiplied() {
+        for input in ["2 3", "(1+2)3", "x 2", "2x 3"] {
+            assert!(raekna_parser::parse(input).is_err(), "{input}");
+        }
+    }
+}
+
 mod combining_rules {
     use super::*;
 
@@ -629,7 +1472,7 @@ mod combining_rules {
                     Expression::VariableRef("var_ref".to_owned()),
                     div_expr(vec![int(5), int(2)]),
                 ]),
-                add_expr(vec![int(100), float(2.2)]),
+                add_expr(vec![int(100), ratio(11, 5)]),
             ])),
         );
         let actual = parse(input);
@@ -0,0 +1,355 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use raekna_common::errors::{CommonError, CommonResult};
+
+use crate::storage::Storage;
+
+const SESSION_EXTENSION: &str = "session";
+const LINES_EXTENSION: &str = "txt";
+
+enum Message {
+    Update(Vec<String>),
+    Flush(Sender<()>),
+    Stop,
+}
+
+/// Writes a snapshot of a sheet to its own locked session once there are no edits for `delay`
+#[derive(Debug)]
+pub struct Autosave {
+    session: Option<Session>,
+    sender: Sender<Message>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Autosave {
+    pub fn new(dir: &Path, delay: Duration, max_delay: Duration) -> CommonResult<Self> {
+        let session = Session::create(dir)?;
+        let (sender, receiver) = mpsc::channel();
+        let lines_path = session.lines_path.clone();
+        let handle = std::thread::spawn(move || {
+            let mut pending = Pending::new(max_delay);
+            let write = |lines: Option<Vec<String>>| {
+                if let Some(lines) = lines {
+                    if let Err(e) = Storage::from(lines).save(&lines_path) {
+                        log::error!("Could not write recovery file: {e}");
+                    }
+                }
+            };
+            loop {
+                match receiver.recv_timeout(delay) {
+                    Ok(Message::Update(lines)) => write(pending.update(lines, Instant::now())),
+                    Ok(Message::Flush(done)) => {
+                        write(pending.take());
+                        let _ = done.send(());
+                    }
+                    Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+                    Err(RecvTimeoutError::Timeout) => write(pending.take()),
+                }
+            }
+        });
+        Ok(Self {
+            session: Some(session),
+            sender,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn schedule(&self, lines: Vec<String>) {
+        let _ = self.sender.send(Message::Update(lines));
+    }
+
+    pub fn flush(&self) {
+        let (done, receiver) = mpsc::channel();
+        if self.sender.send(Message::Flush(done)).is_ok() {
+            let _ = receiver.recv();
+        }
+    }
+
+    pub fn set_file_path(&mut self, path: Option<&Path>) {
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        if let Err(e) = session.write_file_path(path) {
+            log::error!("Could not write recovery file: {e}");
+        }
+    }
+
+    /// Finds an unlocked session that an instance didn't remove when it exited
+    pub fn recover(dir: &Path) -> Option<Recovered> {
+        let mut paths = fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|e| e == SESSION_EXTENSION))
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.into_iter().find_map(|path| Recovered::open(&path))
+    }
+}
+
+impl Drop for Autosave {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Message::Stop);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        if let Some(session) = self.session.take() {
+            session.remove();
+        }
+    }
+}
+
+struct Pending {
+    max_delay: Duration,
+    /// When the oldest snapshot that hasn't been written was scheduled
+    snapshot: Option<(Vec<String>, Instant)>,
+}
+
+impl Pending {
+    fn new(max_delay: Duration) -> Self {
+        Self {
+            max_delay,
+            snapshot: None,
+        }
+    }
+
+    /// Returns the snapshot to write right away if it has waited for `max_delay`
+    fn update(&mut self, lines: Vec<String>, now: Instant) -> Option<Vec<String>> {
+        let since = self.snapshot.take().map_or(now, |(_, since)| since);
+        if now.duration_since(since) >= self.max_delay {
+            return Some(lines);
+        }
+        self.snapshot = Some((lines, since));
+        None
+    }
+
+    fn take(&mut self) -> Option<Vec<String>> {
+        self.snapshot.take().map(|(lines, _)| lines)
+    }
+}
+
+/// Its session stays locked and is only removed by [`Recovered::discard`]
+#[derive(Debug)]
+pub struct Recovered {
+    pub lines: Vec<String>,
+    pub file_path: Option<PathBuf>,
+    session: Session,
+}
+
+impl Recovered {
+    fn open(session_path: &Path) -> Option<Self> {
+        let mut lock = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(session_path)
+            .ok()?;
+        lock.try_lock().ok()?;
+        let mut file_path = String::new();
+        lock.read_to_string(&mut file_path).ok()?;
+        let session = Session {
+            session_path: session_path.to_owned(),
+            lines_path: session_path.with_extension(LINES_EXTENSION),
+            lock,
+        };
+        let lines = match Storage::load(&session.lines_path) {
+            Ok(storage) => storage.get_lines().0.to_vec(),
+            // The instance stopped before anything was written
+            Err(CommonError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(_) => return None,
+        };
+        if lines.iter().all(String::is_empty) {
+            session.remove();
+            return None;
+        }
+        Some(Self {
+            lines,
+            file_path: (!file_path.is_empty()).then(|| PathBuf::from(file_path)),
+            session,
+        })
+    }
+
+    pub fn discard(self) {
+        self.session.remove();
+    }
+}
+
+#[derive(Debug)]
+struct Session {
+    session_path: PathBuf,
+    lines_path: PathBuf,
+    lock: File,
+}
+
+impl Session {
+    /// Named after the process id, with a suffix if a crashed session has the same id
+    fn create(dir: &Path) -> CommonResult<Self> {
+        fs::create_dir_all(dir)?;
+        let id = std::process::id();
+        for attempt in 0.. {
+            let name = match attempt {
+                0 => id.to_string(),
+                n => format!("{id}-{n}"),
+            };
+            let session_path = dir.join(&name).with_extension(SESSION_EXTENSION);
+            let lock = match OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&session_path)
+            {
+                Ok(lock) => lock,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            };
+            lock.lock()?;
+            return Ok(Self {
+                lines_path: session_path.with_extension(LINES_EXTENSION),
+                session_path,
+                lock,
+            });
+        }
+        unreachable!("there is always another name to try")
+    }
+
+    fn write_file_path(&mut self, path: Option<&Path>) -> CommonResult<()> {
+        let path = path.map(Path::to_string_lossy).unwrap_or_default();
+        self.lock.set_len(0)?;
+        self.lock.rewind()?;
+        self.lock.write_all(path.as_bytes())?;
+        Ok(())
+    }
+
+    /// The lines go first, so an unlocked session file that is still around has nothing to recover
+    fn remove(self) {
+        let _ = fs::remove_file(&self.lines_path);
+        drop(self.lock);
+        let _ = fs::remove_file(&self.session_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_secs(3600);
+
+    fn session_files(dir: &Path) -> Vec<PathBuf> {
+        let mut files = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn writes_the_latest_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let recovery_dir = dir.path().join("raekna");
+        let sut = Autosave::new(&recovery_dir, DELAY, DELAY).unwrap();
+
+        sut.schedule(vec!["1".to_owned()]);
+        sut.schedule(vec!["1 +".to_owned()]);
+        sut.schedule(vec!["1 + 2".to_owned()]);
+        let lines_path = &sut.session.as_ref().unwrap().lines_path;
+        assert!(!lines_path.exists());
+        sut.flush();
+
+        assert_eq!(fs::read_to_string(lines_path).unwrap(), "1 + 2\n");
+    }
+
+    #[test]
+    fn session_is_removed_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let sut = Autosave::new(dir.path(), DELAY, DELAY).unwrap();
+
+        sut.schedule(vec!["1 + 2".to_owned()]);
+        sut.flush();
+        assert_eq!(session_files(dir.path()).len(), 2);
+
+        drop(sut);
+        assert!(session_files(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn sessions_of_running_instances_are_not_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = Autosave::new(dir.path(), DELAY, DELAY).unwrap();
+        let second = Autosave::new(dir.path(), DELAY, DELAY).unwrap();
+
+        first.schedule(vec!["1 + 2".to_owned()]);
+        first.flush();
+
+        assert!(Autosave::recover(dir.path()).is_none());
+        drop(second);
+        assert_eq!(session_files(dir.path()).len(), 2);
+    }
+
+    #[test]
+    fn recover() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Autosave::recover(dir.path()).is_none());
+
+        fs::write(dir.path().join("1.session"), "").unwrap();
+        fs::write(dir.path().join("1.txt"), "\n\n").unwrap();
+        assert!(Autosave::recover(dir.path()).is_none());
+        assert!(session_files(dir.path()).is_empty());
+
+        fs::write(dir.path().join("2.session"), "/home/me/budget.txt").unwrap();
+        fs::write(dir.path().join("2.txt"), "a: 5\na * 2\n").unwrap();
+        let actual = Autosave::recover(dir.path()).unwrap();
+
+        assert_eq!(actual.lines, vec!["a: 5".to_owned(), "a * 2".to_owned()]);
+        assert_eq!(actual.file_path, Some(PathBuf::from("/home/me/budget.txt")));
+        // It's locked until it's dealt with
+        assert!(Autosave::recover(dir.path()).is_none());
+    }
+
+    #[test]
+    fn sessions_without_lines_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("1.session"), "/home/me/budget.txt").unwrap();
+
+        assert!(Autosave::recover(dir.path()).is_none());
+        assert!(session_files(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn long_bursts_of_edits_are_written_after_the_max_delay() {
+        let mut sut = Pending::new(Duration::from_secs(30));
+        let start = Instant::now();
+
+        assert_eq!(sut.update(vec!["1".to_owned()], start), None);
+        let later = start + Duration::from_secs(29);
+        assert_eq!(sut.update(vec!["1 +".to_owned()], later), None);
+        let later = start + Duration::from_secs(30);
+        assert_eq!(
+            sut.update(vec!["1 + 2".to_owned()], later),
+            Some(vec!["1 + 2".to_owned()])
+        );
+        assert_eq!(sut.take(), None);
+
+        assert_eq!(sut.update(vec!["1 + 23".to_owned()], later), None);
+        assert_eq!(sut.take(), Some(vec!["1 + 23".to_owned()]));
+    }
+
+    #[test]
+    fn recovered_sessions_are_kept_until_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("2.session"), "").unwrap();
+        fs::write(dir.path().join("2.txt"), "1 + 2\n").unwrap();
+
+        drop(Autosave::recover(dir.path()).unwrap());
+        let recovered = Autosave::recover(dir.path()).unwrap();
+        assert_eq!(recovered.file_path, None);
+
+        recovered.discard();
+        assert!(session_files(dir.path()).is_empty());
+    }
+}
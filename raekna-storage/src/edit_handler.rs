@@ -1,26 +1,205 @@
-use raekna_common::{EditAction, EditPosition};
+use std::ops::Range;
 
-use crate::storage::Storage;
+use raekna_common::{EditAction, EditPosition, LineKind};
+use raekna_parser::line_kind;
+
+use crate::{
+    history::{Edit, EditKind, LineChange},
+    line_references::{shift_references, LineShift},
+    storage::Storage,
+};
 
 pub struct EditHandler<'a> {
     storage: &'a mut Storage,
 }
 
 impl<'a> EditHandler<'a> {
-    pub fn handle_actions(storage: &'a mut Storage, actions: Vec<EditAction>) {
+    /// Also renumbers line references in expressions when lines are inserted or removed
+    pub fn handle_actions(storage: &'a mut Storage, actions: Vec<EditAction>) -> Edit {
+        let kind = EditKind::from_actions(&actions);
+        let before = actions
+            .first()
+            .map(Self::position_before)
+            .unwrap_or(EditPosition::new(0, 0));
         let mut handler = EditHandler { storage };
-        actions.into_iter().for_each(|action| match action {
-            EditAction::NewLine(position) => handler.handle_newline(position),
-            EditAction::Insert(position, c) => handler.handle_insert(position, c),
+        let mut after = before;
+        let mut changes = Vec::with_capacity(actions.len());
+        actions.into_iter().for_each(|action| {
+            let (first, last) = handler.affected_lines(&action);
+            let line_count = handler.storage.lines.len();
+            let removed = handler.storage.lines.content()[first..=last].to_vec();
+            after = handler.position_after(&action);
+            let inserted_at = Self::inserted_at(&action);
+            let is_paste = matches!(action, EditAction::InsertMultiple(_, _));
+            handler.handle_action(action);
+            let line_count_after = handler.storage.lines.len();
+            let last = last + line_count_after - line_count;
+            let inserted = handler.storage.lines.content()[first..=last].to_vec();
+            if removed != inserted {
+                changes.push(LineChange {
+                    start: first,
+                    removed,
+                    inserted,
+                });
+            }
+            let shift = if line_count_after > line_count {
+                LineShift {
+                    start: inserted_at,
+                    removed: 0,
+                    inserted: line_count_after - line_count,
+                }
+            } else if line_count_after < line_count {
+                LineShift {
+                    start: first + 1,
+                    removed: line_count - line_count_after,
+                    inserted: 0,
+                }
+            } else {
+                return;
+            };
+            // References in pasted text are left as they were written
+            let pasted = match is_paste {
+                true => first..last + 1,
+                false => 0..0,
+            };
+            changes.extend(handler.shift_references(shift, pasted));
+        });
+        Edit {
+            changes,
+            kind,
+            before,
+            after,
+        }
+    }
+
+    fn handle_action(&mut self, action: EditAction) {
+        match action {
+            EditAction::NewLine(position) => self.handle_newline(position),
+            EditAction::Insert(position, c) => self.handle_insert(position, c),
             EditAction::InsertMultiple(position, content) => {
-                handler.handle_insert_multiple(position, content)
+                self.handle_insert_multiple(position, content)
             }
             EditAction::Delete {
                 selection_start,
                 selection_end,
-            } => handler.handle_delete(selection_start, selection_end),
-            EditAction::DeleteForward(position) => handler.handle_delete_forward(position),
-        });
+            } => self.handle_delete(selection_start, selection_end),
+            EditAction::DeleteForward(position) => self.handle_delete_forward(position),
+        }
+    }
+
+    fn affected_lines(&self, action: &EditAction) -> (usize, usize) {
+        let (first, last) = match action {
+            EditAction::NewLine(position)
+            | EditAction::Insert(position, _)
+            | EditAction::InsertMultiple(position, _) => (position.line, position.line),
+            EditAction::Delete {
+                selection_start,
+                selection_end: Some(selection_end),
+            } => (selection_start.line, selection_end.line),
+            EditAction::Delete {
+                selection_start,
+                selection_end: None,
+            } if selection_start.column == 0 => {
+                (selection_start.line.saturating_sub(1), selection_start.line)
+            }
+            EditAction::Delete {
+                selection_start, ..
+            } => (selection_start.line, selection_start.line),
+            EditAction::DeleteForward(position) => (position.line, position.line + 1),
+        };
+        let last_line = self.storage.lines.len() - 1;
+        (first.min(last_line), last.min(last_line))
+    }
+
+    fn inserted_at(action: &EditAction) -> usize {
+        match action {
+            EditAction::NewLine(position) | EditAction::InsertMultiple(position, _)
+                if position.column == 0 =>
+            {
+                position.line
+            }
+            EditAction::NewLine(position)
+            | EditAction::InsertMultiple(position, _)
+            | EditAction::Insert(position, _)
+            | EditAction::DeleteForward(position)
+            | EditAction::Delete {
+                selection_start: position,
+                ..
+            } => position.line + 1,
+        }
+    }
+
+    fn shift_references(&mut self, shift: LineShift, skipped: Range<usize>) -> Vec<LineChange> {
+        (0..self.storage.lines.len())
+            .filter(|index| !skipped.contains(index))
+            .filter_map(|index| {
+                let line = &self.storage.lines[index];
+                let LineKind::Expression(comment) = line_kind(line) else {
+                    return None;
+                };
+                let (expression, comment) = line.split_at(comment.unwrap_or(line.len()));
+                let shifted = shift_references(expression, shift)? + comment;
+                let removed = std::mem::replace(&mut self.storage.lines[index], shifted.clone());
+                Some(LineChange {
+                    start: index,
+                    removed: vec![removed],
+                    inserted: vec![shifted],
+                })
+            })
+            .collect()
+    }
+
+    fn position_before(action: &EditAction) -> EditPosition {
+        match action {
+            EditAction::Delete {
+                selection_end: Some(selection_end),
+                ..
+            } => *selection_end,
+            EditAction::NewLine(position)
+            | EditAction::Insert(position, _)
+            | EditAction::InsertMultiple(position, _)
+            | EditAction::DeleteForward(position)
+            | EditAction::Delete {
+                selection_start: position,
+                ..
+            } => *position,
+        }
+    }
+
+    /// Has to be called before the action is handled
+    fn position_after(&self, action: &EditAction) -> EditPosition {
+        match action {
+            EditAction::NewLine(position) => EditPosition::new(position.line + 1, 0),
+            EditAction::Insert(position, c) => {
+                EditPosition::new(position.line, position.column + c.len_utf8())
+            }
+            EditAction::InsertMultiple(position, content) => {
+                let lines = content.lines().collect::<Vec<_>>();
+                match lines.as_slice() {
+                    [] => *position,
+                    [line] => EditPosition::new(position.line, position.column + line.len()),
+                    [.., last] => EditPosition::new(position.line + lines.len() - 1, last.len()),
+                }
+            }
+            EditAction::Delete {
+                selection_start,
+                selection_end: None,
+            } => {
+                let EditPosition { line, column } = *selection_start;
+                if column > 0 {
+                    EditPosition::new(line, column - 1)
+                } else if line > 0 {
+                    let previous_len = self.storage.lines.get(line - 1).map_or(0, |(c, _)| c.len());
+                    EditPosition::new(line - 1, previous_len)
+                } else {
+                    *selection_start
+                }
+            }
+            EditAction::Delete {
+                selection_start, ..
+            }
+            | EditAction::DeleteForward(selection_start) => *selection_start,
+        }
     }
 
     fn line_at<F, R>(&mut self, index: usize, op: F) -> Option<R>
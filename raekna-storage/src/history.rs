@@ -0,0 +1,246 @@
+use raekna_common::{EditAction, EditPosition};
+
+use crate::lines::Lines;
+
+const MAX_ENTRIES: usize = 1000;
+
+/// Replaces `removed` with `inserted`, reverting it does the opposite
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineChange {
+    pub start: usize,
+    pub removed: Vec<String>,
+    pub inserted: Vec<String>,
+}
+
+impl LineChange {
+    fn apply(&self, lines: &mut Lines) {
+        lines.replace(self.start, self.removed.len(), self.inserted.clone());
+    }
+
+    fn revert(&self, lines: &mut Lines) {
+        lines.replace(self.start, self.inserted.len(), self.removed.clone());
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EditKind {
+    Typing(char),
+    Backspace,
+    Other,
+}
+
+impl EditKind {
+    pub fn from_actions(actions: &[EditAction]) -> Self {
+        match actions {
+            [EditAction::Insert(_, c)] => Self::Typing(*c),
+            [EditAction::Delete {
+                selection_start,
+                selection_end: None,
+            }] if selection_start.column > 0 => Self::Backspace,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    changes: Vec<LineChange>,
+    kind: EditKind,
+    before: EditPosition,
+    after: EditPosition,
+    is_sealed: bool,
+}
+
+impl Entry {
+    /// Typing is grouped into words, including the whitespace after them
+    fn can_merge(&self, kind: EditKind, position: EditPosition) -> bool {
+        if self.is_sealed || position != self.after {
+            return false;
+        }
+        match (self.kind, kind) {
+            (EditKind::Typing(last), EditKind::Typing(next)) => {
+                !last.is_whitespace() || next.is_whitespace()
+            }
+            (EditKind::Backspace, EditKind::Backspace) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Edit {
+    pub changes: Vec<LineChange>,
+    pub kind: EditKind,
+    pub before: EditPosition,
+    pub after: EditPosition,
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    undo_stack: Vec<Entry>,
+    redo_stack: Vec<Entry>,
+}
+
+impl History {
+    pub fn record(&mut self, edit: Edit) {
+        if edit.changes.is_empty() {
+            return;
+        }
+        self.redo_stack.clear();
+        let kind = edit.kind;
+        if let Some(last) = self.undo_stack.last_mut() {
+            if last.can_merge(kind, edit.before) {
+                last.changes.extend(edit.changes);
+                last.kind = kind;
+                last.after = edit.after;
+                return;
+            }
+        }
+        if self.undo_stack.len() == MAX_ENTRIES {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(Entry {
+            changes: edit.changes,
+            kind,
+            before: edit.before,
+            after: edit.after,
+            is_sealed: false,
+        });
+    }
+
+    /// Returns the caret position from before the entry
+    pub fn undo(&mut self, lines: &mut Lines) -> Option<EditPosition> {
+        let mut entry = self.undo_stack.pop()?;
+        entry
+            .changes
+            .iter()
+            .rev()
+            .for_each(|change| change.revert(lines));
+        entry.is_sealed = true;
+        let position = entry.before;
+        self.redo_stack.push(entry);
+        self.seal_last();
+        Some(position)
+    }
+
+    /// Returns the caret position from after the entry
+    pub fn redo(&mut self, lines: &mut Lines) -> Option<EditPosition> {
+        let entry = self.redo_stack.pop()?;
+        entry.changes.iter().for_each(|change| change.apply(lines));
+        let position = entry.after;
+        self.undo_stack.push(entry);
+        Some(position)
+    }
+
+    fn seal_last(&mut self) {
+        if let Some(last) = self.undo_stack.last_mut() {
+            last.is_sealed = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(content: &[&str]) -> Lines {
+        Lines::from(content.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    }
+
+    fn edit(
+        actions: &[EditAction],
+        removed: &[&str],
+        inserted: &[&str],
+        before: (usize, usize),
+    ) -> Edit {
+        let to_owned = |s: &[&str]| s.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        Edit {
+            kind: EditKind::from_actions(actions),
+            changes: vec![LineChange {
+                start: before.0,
+                removed: to_owned(removed),
+                inserted: to_owned(inserted),
+            }],
+            before: EditPosition::new(before.0, before.1),
+            after: EditPosition::new(before.0, before.1 + 1),
+        }
+    }
+
+    #[test]
+    fn typing_is_grouped_by_word() {
+        let mut sut = History::default();
+        let mut content = lines(&["ab c"]);
+        sut.record(edit(
+            &[EditAction::Insert(EditPosition::new(0, 0), 'a')],
+            &[""],
+            &["a"],
+            (0, 0),
+        ));
+        sut.record(edit(
+            &[EditAction::Insert(EditPosition::new(0, 1), 'b')],
+            &["a"],
+            &["ab"],
+            (0, 1),
+        ));
+        sut.record(edit(
+            &[EditAction::Insert(EditPosition::new(0, 2), ' ')],
+            &["ab"],
+            &["ab "],
+            (0, 2),
+        ));
+        sut.record(edit(
+            &[EditAction::Insert(EditPosition::new(0, 3), 'c')],
+            &["ab "],
+            &["ab c"],
+            (0, 3),
+        ));
+
+        assert_eq!(sut.undo(&mut content), Some(EditPosition::new(0, 3)));
+        assert_eq!(content.content(), vec!["ab ".to_owned()]);
+        assert_eq!(sut.undo(&mut content), Some(EditPosition::new(0, 0)));
+        assert_eq!(content.content(), vec!["".to_owned()]);
+        assert_eq!(sut.undo(&mut content), None);
+    }
+
+    #[test]
+    fn undo_then_redo() {
+        let mut sut = History::default();
+        let mut content = lines(&["a", "b"]);
+        sut.record(edit(
+            &[EditAction::NewLine(EditPosition::new(0, 0))],
+            &["ab"],
+            &["a", "b"],
+            (0, 1),
+        ));
+
+        sut.undo(&mut content);
+        assert_eq!(content.content(), vec!["ab".to_owned()]);
+        assert_eq!(content.results.len(), 1);
+
+        assert_eq!(sut.redo(&mut content), Some(EditPosition::new(0, 2)));
+        assert_eq!(content.content(), vec!["a".to_owned(), "b".to_owned()]);
+        assert_eq!(content.results.len(), 2);
+        assert_eq!(sut.redo(&mut content), None);
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut sut = History::default();
+        let mut content = lines(&["a"]);
+        sut.record(edit(
+            &[EditAction::Insert(EditPosition::new(0, 0), 'a')],
+            &[""],
+            &["a"],
+            (0, 0),
+        ));
+        sut.undo(&mut content);
+        sut.record(edit(
+            &[EditAction::Insert(EditPosition::new(0, 0), 'b')],
+            &[""],
+            &["b"],
+            (0, 0),
+        ));
+
+        assert_eq!(sut.redo(&mut content), None);
+    }
+}
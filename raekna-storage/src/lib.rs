@@ -1,4 +1,7 @@
+pub mod autosave;
 mod edit_handler;
+mod history;
+mod line_references;
 mod lines;
 pub mod storage;
 mod word_boundaries;
@@ -0,0 +1,136 @@
+/// The `removed` lines starting at index `start` were replaced by `inserted` new lines
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LineShift {
+    pub start: usize,
+    pub removed: usize,
+    pub inserted: usize,
+}
+
+impl LineShift {
+    /// Lines are numbered from 1, `None` if the line was removed
+    pub fn renumber(&self, number: usize) -> Option<usize> {
+        let Some(index) = number.checked_sub(1) else {
+            return Some(number);
+        };
+        if index < self.start {
+            Some(number)
+        } else if index < self.start + self.removed {
+            None
+        } else {
+            Some(number - self.removed + self.inserted)
+        }
+    }
+}
+
+/// Rewrites references like `#3` in an expression without its comment, `None` if there are none
+pub fn shift_references(line: &str, shift: LineShift) -> Option<String> {
+    let is_identifier = |c: char| c == '_' || c.is_alphanumeric();
+    let mut shifted = String::with_capacity(line.len());
+    let mut copied = 0;
+    let mut index = 0;
+    while let Some(c) = line[index..].chars().next() {
+        let follows_identifier = line[..index].chars().next_back().is_some_and(is_identifier);
+        let prefix = if c == '#' {
+            Some("#")
+        } else if line[index..].starts_with("line") && !follows_identifier {
+            Some("line")
+        } else {
+            None
+        };
+        if let Some(prefix) = prefix {
+            let digits_start = index + prefix.len();
+            let digits = line[digits_start..]
+                .bytes()
+                .take_while(u8::is_ascii_digit)
+                .count();
+            let end = digits_start + digits;
+            let is_reference =
+                digits > 0 && (prefix == "#" || !line[end..].starts_with(is_identifier));
+            if is_reference {
+                if let Ok(number) = line[digits_start..end].parse() {
+                    let renumbered = shift.renumber(number);
+                    if renumbered != Some(number) {
+                        shifted.push_str(&line[copied..digits_start]);
+                        match renumbered {
+                            Some(renumbered) => shifted.push_str(&renumbered.to_string()),
+                            None => shifted.push('?'),
+                        }
+                        copied = end;
+                    }
+                }
+                index = end;
+                continue;
+            }
+        }
+        index += c.len_utf8();
+    }
+    if copied == 0 {
+        return None;
+    }
+    shifted.push_str(&line[copied..]);
+    Some(shifted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renumber() {
+        let shift = LineShift {
+            start: 2,
+            removed: 2,
+            inserted: 1,
+        };
+        let test_cases = [
+            (0, Some(0)),
+            (2, Some(2)),
+            (3, None),
+            (4, None),
+            (5, Some(4)),
+        ];
+        for (number, expected) in test_cases {
+            assert_eq!(shift.renumber(number), expected, "number: {number}");
+        }
+    }
+
+    #[test]
+    fn shift_references_after_insertion() {
+        let shift = LineShift {
+            start: 1,
+            removed: 0,
+            inserted: 1,
+        };
+        let test_cases = [
+            ("#1 + #2", Some("#1 + #3")),
+            ("line2*line10", Some("line3*line11")),
+            ("prev + 1", None),
+            ("#1", None),
+            ("#? + line?", None),
+            ("my_line2 + line2x + lines2", None),
+            ("#9 - #12", Some("#10 - #13")),
+        ];
+        for (line, expected) in test_cases {
+            let actual = shift_references(line, shift);
+            assert_eq!(actual.as_deref(), expected, "line: {line}");
+        }
+    }
+
+    #[test]
+    fn shift_references_after_removal() {
+        let shift = LineShift {
+            start: 1,
+            removed: 1,
+            inserted: 0,
+        };
+        let test_cases = [
+            ("#2 * 2", Some("#? * 2")),
+            ("line3 - line1", Some("line2 - line1")),
+            ("€#2", Some("€#?")),
+        ];
+        for (line, expected) in test_cases {
+            let actual = shift_references(line, shift);
+            assert_eq!(actual.as_deref(), expected, "line: {line}");
+        }
+    }
+}
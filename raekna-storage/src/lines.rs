@@ -1,15 +1,22 @@
 use std::ops::{Index, IndexMut};
 
+use raekna_common::expression::Expression;
+use raekna_parser::{parse, ParserResult};
+
 #[derive(Debug)]
 pub struct Lines {
-    pub content: Vec<String>,
+    /// Only changed through the methods below, so `parsed` knows which lines changed
+    content: Vec<String>,
     pub results: Vec<String>,
+    /// `None` for lines that changed since they were last parsed
+    parsed: Vec<Option<ParserResult<Expression>>>,
 }
 
 impl Lines {
     pub fn insert(&mut self, index: usize, c: String) {
         self.content.insert(index, c);
         self.results.insert(index, "".to_owned());
+        self.parsed.insert(index, None);
     }
 
     pub fn new_at(&mut self, index: usize) {
@@ -26,32 +33,78 @@ impl Lines {
 
     pub fn get_mut(&mut self, index: usize) -> Option<(&mut str, &str)> {
         if index < self.content.len() {
+            self.parsed[index] = None;
             Some((&mut self.content[index], &self.results[index]))
         } else {
             None
         }
     }
 
+    pub fn content(&self) -> &[String] {
+        &self.content
+    }
+
+    pub fn content_and_results_mut(&mut self) -> (&[String], &mut [String]) {
+        (&self.content, &mut self.results)
+    }
+
     pub fn len(&self) -> usize {
         self.content.len()
     }
 
+    pub fn replace(&mut self, start: usize, count: usize, content: Vec<String>) {
+        let results = vec!["".to_owned(); content.len()];
+        let parsed = vec![None; content.len()];
+        self.content.splice(start..start + count, content);
+        self.results.splice(start..start + count, results);
+        self.parsed.splice(start..start + count, parsed);
+        if self.content.is_empty() {
+            *self = Self::default();
+        }
+    }
+
     pub fn remove(&mut self, index: usize) -> (String, String) {
         let c = self.content.remove(index);
         let r = self.results.remove(index);
+        self.parsed.remove(index);
         if self.content.is_empty() {
-            self.content.push("".to_owned());
-            self.results.push("".to_owned());
+            *self = Self::default();
         }
         (c, r)
     }
+
+    /// Lines are only parsed again after they change
+    pub fn parse(&mut self) -> impl Iterator<Item = (&str, &ParserResult<Expression>)> {
+        self.content
+            .iter()
+            .zip(self.parsed.iter_mut())
+            .filter(|(_, parsed)| parsed.is_none())
+            .for_each(|(content, parsed)| *parsed = Some(parse(content)));
+        self.content
+            .iter()
+            .map(String::as_str)
+            .zip(self.parsed.iter().flatten())
+    }
+}
+
+impl From<Vec<String>> for Lines {
+    fn from(content: Vec<String>) -> Self {
+        if content.is_empty() {
+            return Self::default();
+        }
+        let results = vec!["".to_owned(); content.len()];
+        let parsed = vec![None; content.len()];
+        Self {
+            content,
+            results,
+            parsed,
+        }
+    }
 }
 
 impl Default for Lines {
     fn default() -> Self {
-        let content = vec!["".to_owned()];
-        let results = vec!["".to_owned()];
-        Self { content, results }
+        Self::from(vec!["".to_owned()])
     }
 }
 
@@ -65,6 +118,27 @@ impl Index<usize> for Lines {
 
 impl IndexMut<usize> for Lines {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        self.parsed[index] = None;
         &mut self.content[index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_parsed_again_after_they_change() {
+        let mut sut = Lines::from(vec!["1 +".to_owned(), "2".to_owned()]);
+        assert!(sut.parse().next().unwrap().1.is_err());
+
+        sut[0].push_str(" 1");
+        sut.insert(0, "3".to_owned());
+
+        let parsed = sut
+            .parse()
+            .map(|(_, parsed)| parsed.is_ok())
+            .collect::<Vec<_>>();
+        assert_eq!(parsed, [true, true, true]);
+    }
+}
@@ -1,33 +1,110 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
 use raekna_common::{
     errors::{CommonError, CommonResult},
+    expression::Expression,
     BoundaryPriority, EditAction, EditPosition,
 };
+use raekna_parser::ParserResult;
 
-use crate::{edit_handler::EditHandler, lines::Lines, word_boundaries::find_word_boundaries};
+use crate::{
+    edit_handler::EditHandler,
+    history::{Edit, EditKind, History, LineChange},
+    lines::Lines,
+    word_boundaries::find_word_boundaries,
+};
 
 #[derive(Debug, Default)]
 pub struct Storage {
     pub lines: Lines,
+    history: History,
 }
 
 impl Storage {
+    pub fn load(path: &Path) -> CommonResult<Self> {
+        let content = fs::read_to_string(path)?;
+        let lines = content.lines().map(str::to_owned).collect::<Vec<_>>();
+        Ok(Self::from(lines))
+    }
+
+    /// Writes a temporary file next to `path` and renames it, so a failed save leaves the old file
+    pub fn save(&self, path: &Path) -> CommonResult<()> {
+        let mut content = self.lines.content().join("\n");
+        content.push('\n');
+        // Saving through a symlink replaces the file it points to, not the link
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        let mut temp_name = OsString::from(".");
+        temp_name.push(path.file_name().unwrap_or_default());
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+        let result = File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(content.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp_path, &path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        Ok(result?)
+    }
+
     pub fn get_lines(&self) -> (&[String], &[String]) {
-        (&self.lines.content, &self.lines.results)
+        (self.lines.content(), &self.lines.results)
     }
 
     pub fn get_lines_mut(&mut self) -> (&[String], &mut [String]) {
-        (&self.lines.content, &mut self.lines.results)
+        self.lines.content_and_results_mut()
+    }
+
+    /// Lines are only parsed again after they change
+    pub fn parse_lines(&mut self) -> impl Iterator<Item = (&str, &ParserResult<Expression>)> {
+        self.lines.parse()
     }
 
     pub fn get_line(&self, index: usize) -> CommonResult<(&str, &str)> {
         if index >= self.lines.len() {
             return Err(CommonError::OutOfBounds(index));
         }
-        Ok((&self.lines.content[index], &self.lines.results[index]))
+        Ok((&self.lines[index], &self.lines.results[index]))
     }
 
     pub fn handle_actions(&mut self, actions: Vec<EditAction>) {
-        EditHandler::handle_actions(self, actions)
+        let edit = EditHandler::handle_actions(self, actions);
+        self.history.record(edit);
+    }
+
+    pub fn replace_all(&mut self, content: Vec<String>) {
+        let content = match content.is_empty() {
+            true => vec![String::new()],
+            false => content,
+        };
+        let after = EditPosition::new(content.len() - 1, content[content.len() - 1].len());
+        let removed = self.lines.content().to_vec();
+        self.lines.replace(0, removed.len(), content.clone());
+        self.history.record(Edit {
+            changes: vec![LineChange {
+                start: 0,
+                removed,
+                inserted: content,
+            }],
+            kind: EditKind::Other,
+            before: EditPosition::new(0, 0),
+            after,
+        });
+    }
+
+    pub fn undo(&mut self) -> Option<EditPosition> {
+        self.history.undo(&mut self.lines)
+    }
+
+    pub fn redo(&mut self) -> Option<EditPosition> {
+        self.history.redo(&mut self.lines)
     }
 
     /// Assumes start and end are ordered
@@ -69,6 +146,15 @@ impl Storage {
     }
 }
 
+impl From<Vec<String>> for Storage {
+    fn from(lines: Vec<String>) -> Self {
+        Self {
+            lines: Lines::from(lines),
+            history: History::default(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,7 +169,7 @@ mod tests {
             let actions = vec![EditAction::Insert(EditPosition::new(0, 0), 'a')];
             sut.handle_actions(actions);
 
-            assert_eq!(sut.lines.content, vec!["a".to_owned()]);
+            assert_eq!(sut.get_lines().0, vec!["a".to_owned()]);
         }
 
         #[test]
@@ -102,7 +188,7 @@ mod tests {
             sut.handle_actions(actions);
 
             assert_eq!(
-                sut.lines.content,
+                sut.get_lines().0,
                 vec!["ab".to_owned(), "cd".to_owned(), "efg".to_owned()]
             );
         }
@@ -110,10 +196,8 @@ mod tests {
         #[test]
         fn insert_and_delete() {
             let mut sut = Storage {
-                lines: Lines {
-                    content: vec!["abc".to_owned()],
-                    results: vec!["".to_owned()],
-                },
+                lines: Lines::from(vec!["abc".to_owned()]),
+                ..Default::default()
             };
 
             let actions = vec![EditAction::Delete {
@@ -122,21 +206,19 @@ mod tests {
             }];
             sut.handle_actions(actions);
 
-            assert_eq!(sut.lines.content, vec!["bc".to_owned()]);
+            assert_eq!(sut.get_lines().0, vec!["bc".to_owned()]);
 
             let actions = vec![EditAction::DeleteForward(EditPosition::new(0, 0))];
             sut.handle_actions(actions);
 
-            assert_eq!(sut.lines.content, vec!["c".to_owned()]);
+            assert_eq!(sut.get_lines().0, vec!["c".to_owned()]);
         }
 
         #[test]
         fn delete_single_line_selection() {
             let mut sut = Storage {
-                lines: Lines {
-                    content: vec!["abcd".to_owned()],
-                    results: vec!["".to_owned()],
-                },
+                lines: Lines::from(vec!["abcd".to_owned()]),
+                ..Default::default()
             };
 
             let actions = vec![EditAction::Delete {
@@ -145,16 +227,14 @@ mod tests {
             }];
             sut.handle_actions(actions);
 
-            assert_eq!(sut.lines.content, vec!["ad".to_owned()]);
+            assert_eq!(sut.get_lines().0, vec!["ad".to_owned()]);
         }
 
         #[test]
         fn delete_multi_line_selection() {
             let mut sut = Storage {
-                lines: Lines {
-                    content: vec!["abc".to_owned(), "def".to_owned()],
-                    results: vec!["".to_owned(), "".to_owned()],
-                },
+                lines: Lines::from(vec!["abc".to_owned(), "def".to_owned()]),
+                ..Default::default()
             };
 
             let actions = vec![EditAction::Delete {
@@ -163,7 +243,293 @@ mod tests {
             }];
             sut.handle_actions(actions);
 
-            assert_eq!(sut.lines.content, vec!["abf".to_owned()]);
+            assert_eq!(sut.get_lines().0, vec!["abf".to_owned()]);
+        }
+    }
+
+    mod test_undo_redo {
+        use super::*;
+
+        fn storage(content: &[&str]) -> Storage {
+            Storage::from(content.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+        }
+
+        fn type_text(sut: &mut Storage, line: usize, column: usize, text: &str) {
+            text.chars().enumerate().for_each(|(i, c)| {
+                let actions = vec![EditAction::Insert(EditPosition::new(line, column + i), c)];
+                sut.handle_actions(actions);
+            });
+        }
+
+        #[test]
+        fn nothing_to_undo() {
+            let mut sut = storage(&["abc"]);
+
+            assert_eq!(sut.undo(), None);
+            assert_eq!(sut.redo(), None);
+            assert_eq!(sut.get_lines().0, vec!["abc".to_owned()]);
+        }
+
+        #[test]
+        fn typing_is_undone_one_word_at_a_time() {
+            let mut sut = Storage::default();
+            type_text(&mut sut, 0, 0, "1 + 22");
+
+            assert_eq!(sut.undo(), Some(EditPosition::new(0, 4)));
+            assert_eq!(sut.get_lines().0, vec!["1 + ".to_owned()]);
+            assert_eq!(sut.undo(), Some(EditPosition::new(0, 2)));
+            assert_eq!(sut.get_lines().0, vec!["1 ".to_owned()]);
+            assert_eq!(sut.undo(), Some(EditPosition::new(0, 0)));
+            assert_eq!(sut.get_lines().0, vec!["".to_owned()]);
+
+            assert_eq!(sut.redo(), Some(EditPosition::new(0, 2)));
+            assert_eq!(sut.redo(), Some(EditPosition::new(0, 4)));
+            assert_eq!(sut.redo(), Some(EditPosition::new(0, 6)));
+            assert_eq!(sut.get_lines().0, vec!["1 + 22".to_owned()]);
+        }
+
+        #[test]
+        fn backspaces_are_grouped() {
+            let mut sut = storage(&["abc"]);
+            (1..=3).rev().for_each(|column| {
+                sut.handle_actions(vec![EditAction::Delete {
+                    selection_start: EditPosition::new(0, column),
+                    selection_end: None,
+                }]);
+            });
+
+            assert_eq!(sut.get_lines().0, vec!["".to_owned()]);
+            assert_eq!(sut.undo(), Some(EditPosition::new(0, 3)));
+            assert_eq!(sut.get_lines().0, vec!["abc".to_owned()]);
+        }
+
+        #[test]
+        fn newline_and_joining_lines() {
+            let mut sut = storage(&["abcd"]);
+            sut.handle_actions(vec![EditAction::NewLine(EditPosition::new(0, 2))]);
+            sut.handle_actions(vec![EditAction::Delete {
+                selection_start: EditPosition::new(1, 0),
+                selection_end: None,
+            }]);
+            sut.handle_actions(vec![EditAction::NewLine(EditPosition::new(0, 4))]);
+
+            assert_eq!(sut.get_lines().0, vec!["abcd".to_owned(), "".to_owned()]);
+            sut.undo();
+            assert_eq!(sut.get_lines().0, vec!["abcd".to_owned()]);
+            assert_eq!(sut.undo(), Some(EditPosition::new(1, 0)));
+            assert_eq!(sut.get_lines().0, vec!["ab".to_owned(), "cd".to_owned()]);
+            assert_eq!(sut.get_lines().1.len(), 2);
+            assert_eq!(sut.undo(), Some(EditPosition::new(0, 2)));
+            assert_eq!(sut.get_lines().0, vec!["abcd".to_owned()]);
+        }
+
+        #[test]
+        fn delete_multi_line_selection_and_forward() {
+            let mut sut = storage(&["abc", "def", "ghi"]);
+            sut.handle_actions(vec![EditAction::Delete {
+                selection_start: EditPosition::new(0, 1),
+                selection_end: Some(EditPosition::new(2, 1)),
+            }]);
+            sut.handle_actions(vec![EditAction::DeleteForward(EditPosition::new(0, 0))]);
+
+            assert_eq!(sut.get_lines().0, vec!["hi".to_owned()]);
+            assert_eq!(sut.undo(), Some(EditPosition::new(0, 0)));
+            assert_eq!(sut.get_lines().0, vec!["ahi".to_owned()]);
+            assert_eq!(sut.undo(), Some(EditPosition::new(2, 1)));
+            assert_eq!(
+                sut.get_lines().0,
+                vec!["abc".to_owned(), "def".to_owned(), "ghi".to_owned()]
+            );
+        }
+
+        #[test]
+        fn paste_over_selection() {
+            let mut sut = storage(&["abc"]);
+            sut.handle_actions(vec![
+                EditAction::Delete {
+                    selection_start: EditPosition::new(0, 1),
+                    selection_end: Some(EditPosition::new(0, 2)),
+                },
+                EditAction::InsertMultiple(EditPosition::new(0, 1), "x\ny\nz".to_owned()),
+            ]);
+
+            assert_eq!(
+                sut.get_lines().0,
+                vec!["ax".to_owned(), "y".to_owned(), "zc".to_owned()]
+            );
+            assert_eq!(sut.undo(), Some(EditPosition::new(0, 2)));
+            assert_eq!(sut.get_lines().0, vec!["abc".to_owned()]);
+            assert_eq!(sut.redo(), Some(EditPosition::new(2, 1)));
+            assert_eq!(
+                sut.get_lines().0,
+                vec!["ax".to_owned(), "y".to_owned(), "zc".to_owned()]
+            );
+        }
+
+        #[test]
+        fn replace_all() {
+            let mut sut = storage(&["1 + 2", "3"]);
+            sut.replace_all(vec!["a: 5".to_owned()]);
+
+            assert_eq!(sut.get_lines().0, vec!["a: 5".to_owned()]);
+            assert_eq!(sut.undo(), Some(EditPosition::new(0, 0)));
+            assert_eq!(sut.get_lines().0, vec!["1 + 2".to_owned(), "3".to_owned()]);
+            assert_eq!(sut.redo(), Some(EditPosition::new(0, 4)));
+            assert_eq!(sut.get_lines().0, vec!["a: 5".to_owned()]);
+        }
+
+        #[test]
+        fn line_references_follow_inserted_lines() {
+            let mut sut = storage(&["5", "#1 * 2", "line2 + #1"]);
+            sut.handle_actions(vec![EditAction::NewLine(EditPosition::new(0, 0))]);
+
+            assert_eq!(
+                sut.get_lines().0,
+                vec![
+                    "".to_owned(),
+                    "5".to_owned(),
+                    "#2 * 2".to_owned(),
+                    "line3 + #2".to_owned()
+                ]
+            );
+            assert_eq!(sut.undo(), Some(EditPosition::new(0, 0)));
+            assert_eq!(
+                sut.get_lines().0,
+                vec!["5".to_owned(), "#1 * 2".to_owned(), "line2 + #1".to_owned()]
+            );
+        }
+
+        #[test]
+        fn line_references_to_removed_lines_are_flagged() {
+            let mut sut = storage(&["5", "6", "#2 + #3", "#1"]);
+            sut.handle_actions(vec![EditAction::Delete {
+                selection_start: EditPosition::new(0, 1),
+                selection_end: Some(EditPosition::new(1, 1)),
+            }]);
+
+            assert_eq!(
+                sut.get_lines().0,
+                vec!["5".to_owned(), "#? + #2".to_owned(), "#1".to_owned()]
+            );
+        }
+
+        #[test]
+        fn line_references_in_comments_and_headings_are_left_alone() {
+            let mut sut = storage(&[
+                "Invoice #12:",
+                "# order #7 from supplier, see line2",
+                "5",
+                "#3 * 2 // like line3",
+            ]);
+            sut.handle_actions(vec![EditAction::NewLine(EditPosition::new(0, 0))]);
+
+            assert_eq!(
+                sut.get_lines().0,
+                vec![
+                    "".to_owned(),
+                    "Invoice #12:".to_owned(),
+                    "# order #7 from supplier, see line2".to_owned(),
+                    "5".to_owned(),
+                    "#4 * 2 // like line3".to_owned(),
+                ]
+            );
+        }
+
+        #[test]
+        fn pasted_line_references_are_kept() {
+            let mut sut = storage(&["5", "#1"]);
+            sut.handle_actions(vec![EditAction::InsertMultiple(
+                EditPosition::new(0, 0),
+                "1\n#1 + ".to_owned(),
+            )]);
+
+            assert_eq!(
+                sut.get_lines().0,
+                vec!["1".to_owned(), "#1 + 5".to_owned(), "#2".to_owned()]
+            );
+        }
+    }
+
+    mod test_load_and_save {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("sheet.txt");
+            let sut = Storage {
+                lines: Lines::from(vec!["a: 5".to_owned(), "".to_owned(), "a * 2".to_owned()]),
+                ..Default::default()
+            };
+
+            sut.save(&path).unwrap();
+            let actual = Storage::load(&path).unwrap();
+
+            assert_eq!(fs::read_to_string(&path).unwrap(), "a: 5\n\na * 2\n");
+            assert_eq!(actual.get_lines().0, sut.get_lines().0);
+            assert_eq!(actual.get_lines().1, vec!["".to_owned(); 3]);
+        }
+
+        #[test]
+        fn save_replaces_the_file_without_leaving_a_temporary_file() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("sheet.txt");
+            fs::write(&path, "old content\nthat is longer\n").unwrap();
+            let sut = Storage::from(vec!["1 + 2".to_owned()]);
+
+            sut.save(&path).unwrap();
+
+            assert_eq!(fs::read_to_string(&path).unwrap(), "1 + 2\n");
+            assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        }
+
+        #[test]
+        fn failed_save_keeps_the_old_file() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("sheet.txt");
+            fs::write(&path, "1 + 2\n").unwrap();
+            // A directory where the temporary file would go makes the save fail
+            fs::create_dir(dir.path().join(".sheet.txt.tmp")).unwrap();
+            let sut = Storage::from(vec!["3 * 4".to_owned()]);
+
+            assert!(sut.save(&path).is_err());
+            assert_eq!(fs::read_to_string(&path).unwrap(), "1 + 2\n");
+        }
+
+        #[test]
+        fn empty_file() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("sheet.txt");
+            fs::write(&path, "").unwrap();
+
+            let actual = Storage::load(&path).unwrap();
+
+            assert_eq!(actual.get_lines().0, vec!["".to_owned()]);
+            assert_eq!(actual.get_lines().1, vec!["".to_owned()]);
+        }
+
+        #[test]
+        fn windows_line_endings() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("sheet.txt");
+            fs::write(&path, "1 + 2\r\n3 * 4\r\n").unwrap();
+
+            let actual = Storage::load(&path).unwrap();
+
+            assert_eq!(
+                actual.get_lines().0,
+                vec!["1 + 2".to_owned(), "3 * 4".to_owned()]
+            );
+        }
+
+        #[test]
+        fn missing_file() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("missing.txt");
+
+            let actual = Storage::load(&path);
+
+            assert!(matches!(actual, Err(CommonError::Io(_))));
         }
     }
 
@@ -172,11 +538,11 @@ mod tests {
 
         #[test]
         fn origin_line_out_of_bounds() {
-            let lines = Lines {
-                content: vec!["abc".to_owned(), "def".to_owned(), "ghi".to_owned()],
-                results: vec![],
+            let lines = Lines::from(vec!["abc".to_owned(), "def".to_owned(), "ghi".to_owned()]);
+            let storage = Storage {
+                lines,
+                ..Default::default()
             };
-            let storage = Storage { lines };
 
             let origin = EditPosition::new(3, 2);
             let actual = storage.get_word_boundaries(origin, BoundaryPriority::None);
@@ -186,11 +552,11 @@ mod tests {
 
         #[test]
         fn normal_usage() {
-            let lines = Lines {
-                content: vec!["abc".to_owned(), "def".to_owned(), "ghi".to_owned()],
-                results: vec![],
+            let lines = Lines::from(vec!["abc".to_owned(), "def".to_owned(), "ghi".to_owned()]);
+            let storage = Storage {
+                lines,
+                ..Default::default()
             };
-            let storage = Storage { lines };
 
             let origin = EditPosition::new(1, 2);
             let actual = storage.get_word_boundaries(origin, BoundaryPriority::None);
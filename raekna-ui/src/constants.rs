@@ -5,6 +5,8 @@ pub const BACKGROUND_COLOR: wgpu::Color = wgpu::Color {
     a: 1.0,
 };
 pub const TEXT_COLOR: [f32; 4] = [0.05, 0.05, 0.05, 1.0];
+pub const COMMENT_COLOR: [f32; 4] = [0.45, 0.45, 0.45, 1.0];
+pub const HEADING_COLOR: [f32; 4] = [0.1, 0.3, 0.6, 1.0];
 pub const SEPARATOR_COLOR: [f32; 3] = [0.6, 0.6, 0.6];
 
 pub const SCROLLBAR_WIDTH_MULTIPLIER: f32 = 1.5;
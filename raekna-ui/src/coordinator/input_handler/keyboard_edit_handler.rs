@@ -1,11 +1,13 @@
+use std::path::Path;
+
 use copypasta::{ClipboardContext, ClipboardProvider};
-use raekna_common::{EditAction, EditPosition};
+use raekna_common::{EditAction, EditPosition, RCalculator};
 
 use crate::{
     constants::TEXT_PADDING,
     coordinator::{
         active_modifiers::ActiveModifiers, content::Content, dimensions::Dimensions,
-        user_input::KeyboardEdit,
+        selection::Selection, user_input::KeyboardEdit,
     },
     graphics::controls::caret_position::CaretPosition,
 };
@@ -223,6 +225,34 @@ impl KeyboardEditHandler {
                 }
                 Self::maybe_hide_selection(content, selection_start.line, selection_start.column);
             }
+            KeyboardEdit::Undo | KeyboardEdit::Redo => {
+                let mut position = None;
+                Self::update_calculator(content, dimensions, |calculator| {
+                    position = match edit {
+                        KeyboardEdit::Undo => calculator.undo(),
+                        _ => calculator.redo(),
+                    };
+                });
+                if let Some(EditPosition { line, column }) = position {
+                    let caret_position = CaretPosition {
+                        line,
+                        column,
+                        actual_column: column,
+                    };
+                    content.handle_selection(dimensions, Selection::None(caret_position));
+                }
+            }
+            KeyboardEdit::Save => Self::save(content),
+            KeyboardEdit::RestoreSession => {
+                if content.calculator.has_recovered_session() {
+                    Self::update_calculator(content, dimensions, |calculator| {
+                        calculator.restore_recovered_session()
+                    });
+                    let caret_position = CaretPosition::new(content.text_buffer.line_widths());
+                    content.handle_selection(dimensions, Selection::None(caret_position));
+                }
+            }
+            KeyboardEdit::DismissSession => content.calculator.dismiss_recovered_session(),
         }
         content.update_caret_position(dimensions);
     }
@@ -232,8 +262,17 @@ impl KeyboardEditHandler {
         actions: Vec<EditAction>,
         dimensions: &mut Dimensions,
     ) {
+        Self::update_calculator(content, dimensions, |calculator| {
+            calculator.update_line(actions)
+        });
+    }
+
+    fn update_calculator<F>(content: &mut Content, dimensions: &mut Dimensions, op: F)
+    where
+        F: FnOnce(&mut dyn RCalculator),
+    {
         let line_count_before = content.text_buffer.line_widths().len();
-        content.calculator.update_line(actions);
+        op(content.calculator.as_mut());
         content.handle_line_updates(dimensions);
         let line_count_after = content.text_buffer.line_widths().len();
 
@@ -249,6 +288,16 @@ impl KeyboardEditHandler {
         }
     }
 
+    fn save(content: &mut Content) {
+        let Some(path) = content.calculator.file_path().map(Path::to_owned) else {
+            log::warn!("No file to save to, start raekna with a file path to enable saving");
+            return;
+        };
+        if let Err(e) = content.calculator.save(&path) {
+            log::error!("Could not save to {}: {}", path.display(), e);
+        }
+    }
+
     fn delete_selection(
         content: &mut Content,
         dimensions: &mut Dimensions,
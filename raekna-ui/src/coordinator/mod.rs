@@ -123,7 +123,25 @@ impl Coordinator {
         Window::default_attributes()
             .with_min_inner_size(self.dimensions.minimum_window_size)
             .with_inner_size(self.dimensions.window_size)
-            .with_title("raekna")
+            .with_title(self.title())
+    }
+
+    fn title(&self) -> &'static str {
+        if self.content.calculator.has_recovered_session() {
+            "raekna - recovered session: Ctrl+R restore, Ctrl+Shift+R discard"
+        } else {
+            "raekna"
+        }
+    }
+
+    fn update_title(&self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        let title = self.title();
+        if window.title() != title {
+            window.set_title(title);
+        }
     }
 
     pub fn on_resumed(&mut self, window: Window) {
@@ -232,6 +250,7 @@ impl Coordinator {
                             &mut self.dimensions,
                         );
                         self.scroll_to_caret();
+                        self.update_title();
                     }
                 }
                 true
@@ -28,6 +28,11 @@ pub enum KeyboardEdit {
     Cut,
     Copy,
     Paste,
+    Undo,
+    Redo,
+    Save,
+    RestoreSession,
+    DismissSession,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -69,6 +74,16 @@ impl UserInput {
             KeyCode::KeyX if active_modifiers.ctrl => Some(KeyboardEdit::Cut.into()),
             KeyCode::KeyC if active_modifiers.ctrl => Some(KeyboardEdit::Copy.into()),
             KeyCode::KeyV if active_modifiers.ctrl => Some(KeyboardEdit::Paste.into()),
+            KeyCode::KeyZ if active_modifiers.ctrl && active_modifiers.shift => {
+                Some(KeyboardEdit::Redo.into())
+            }
+            KeyCode::KeyZ if active_modifiers.ctrl => Some(KeyboardEdit::Undo.into()),
+            KeyCode::KeyY if active_modifiers.ctrl => Some(KeyboardEdit::Redo.into()),
+            KeyCode::KeyS if active_modifiers.ctrl => Some(KeyboardEdit::Save.into()),
+            KeyCode::KeyR if active_modifiers.ctrl && active_modifiers.shift => {
+                Some(KeyboardEdit::DismissSession.into())
+            }
+            KeyCode::KeyR if active_modifiers.ctrl => Some(KeyboardEdit::RestoreSession.into()),
             KeyCode::KeyA if active_modifiers.ctrl => Some(KeyboardMovement::SelectAll.into()),
             _ => None,
         })
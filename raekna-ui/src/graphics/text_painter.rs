@@ -1,8 +1,9 @@
+use raekna_common::LineKind;
 use wgpu::{CommandEncoder, Device, TextureFormat, TextureView, util::StagingBelt};
 use wgpu_glyph::{GlyphBrush, GlyphBrushBuilder, Section, Text, ab_glyph::FontArc};
 
 use crate::{
-    constants::{TEXT_COLOR, TEXT_PADDING, TEXT_SCALING},
+    constants::{COMMENT_COLOR, HEADING_COLOR, TEXT_COLOR, TEXT_PADDING, TEXT_SCALING},
     coordinator::{content::Content, dimensions::Dimensions, text_buffer::entry},
 };
 
@@ -68,6 +69,7 @@ impl TextPainter {
         let result_offset = (3_f32 * TEXT_PADDING)
             + (dimensions.glyph_width() * dimensions.content_columns() as f32);
         let mut output = vec![];
+        let line_kinds = content.calculator.get_line_kinds();
         content
             .text_buffer
             .entries
@@ -89,6 +91,7 @@ impl TextPainter {
                                     .expect("i cannot be out of bounds"),
                                 y_offset,
                                 result_offset,
+                                line_kinds.get(i).copied().unwrap_or_default(),
                                 c,
                                 r,
                                 dimensions,
@@ -104,6 +107,7 @@ impl TextPainter {
         text: (&'a str, &'a str),
         y_offset: f32,
         result_offset: f32,
+        kind: LineKind,
         c: &entry::Content,
         r: &entry::Result,
         dimensions: &Dimensions,
@@ -111,40 +115,57 @@ impl TextPainter {
         let (cl, rl) = text;
         let mut output = vec![];
 
+        // Comments are dimmed and headings stand out from the expressions around them
+        let comment_start = match kind {
+            LineKind::Comment => 0,
+            LineKind::Expression(Some(start)) => start,
+            _ => cl.len(),
+        };
+        let text_color = match kind {
+            LineKind::Heading => HEADING_COLOR,
+            _ => TEXT_COLOR,
+        };
         let start_offset = c.segments.first().map(|s| s.start).unwrap_or(0);
         c.segments.iter().for_each(|s| {
-            let text = &cl[s.start..s.end];
-            let x_offset =
-                TEXT_PADDING + ((s.start - start_offset) as f32 * dimensions.glyph_width());
-            output.push(Self::build_section(text, x_offset, y_offset));
+            let split = comment_start.clamp(s.start, s.end);
+            [(s.start, split, text_color), (split, s.end, COMMENT_COLOR)]
+                .into_iter()
+                .filter(|(start, end, _)| start < end)
+                .for_each(|(start, end, color)| {
+                    let text = &cl[start..end];
+                    let x_offset =
+                        TEXT_PADDING + ((start - start_offset) as f32 * dimensions.glyph_width());
+                    output.push(Self::build_section(text, x_offset, y_offset, color));
+                });
         });
         match r {
             entry::Result::None => {}
             entry::Result::Elipsis => {
                 let chars = dimensions.result_columns() - 3;
-                output.push(Self::build_section(&rl[..chars], result_offset, y_offset));
+                output.push(Self::build_section(
+                    &rl[..chars],
+                    result_offset,
+                    y_offset,
+                    TEXT_COLOR,
+                ));
                 let x_offset = result_offset + (chars as f32 * dimensions.glyph_width());
-                output.push(Self::build_section("...", x_offset, y_offset));
+                output.push(Self::build_section("...", x_offset, y_offset, TEXT_COLOR));
             }
             entry::Result::Full => {
                 let padding_chars = dimensions.result_columns() - rl.len();
                 let padding = (padding_chars as f32 * dimensions.glyph_width()) / 2_f32;
                 let x_offset = result_offset + padding;
-                output.push(Self::build_section(rl, x_offset, y_offset));
+                output.push(Self::build_section(rl, x_offset, y_offset, TEXT_COLOR));
             }
         }
 
         output
     }
 
-    fn build_section(text: &str, x_offset: f32, y_offset: f32) -> Section<'_> {
+    fn build_section(text: &str, x_offset: f32, y_offset: f32, color: [f32; 4]) -> Section<'_> {
         Section {
             screen_position: (x_offset, y_offset),
-            text: vec![
-                Text::new(text)
-                    .with_color(TEXT_COLOR)
-                    .with_scale(TEXT_SCALING),
-            ],
+            text: vec![Text::new(text).with_color(color).with_scale(TEXT_SCALING)],
             ..Section::default()
         }
     }
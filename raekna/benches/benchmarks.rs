@@ -1,14 +1,22 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, hint::black_box};
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use raekna_common::expression::Literal;
-use raekna_compute::evaluate;
+use criterion::{criterion_group, criterion_main, Criterion};
+use raekna::{calculator::Calculator, sheet::evaluate_sheet};
+use raekna_common::{expression::Literal, EditAction, EditPosition, RCalculator};
+use raekna_compute::{evaluate, Context, LinesAbove};
 use raekna_parser::parse;
 
 fn parse_and_evaluate(input: &str, variables: &mut HashMap<String, Literal>) {
     let ast = parse(input);
     let ast = ast.unwrap();
-    evaluate(ast, variables).unwrap();
+    evaluate(
+        ast,
+        variables,
+        &mut HashMap::new(),
+        LinesAbove::default(),
+        &Context::default(),
+    )
+    .unwrap();
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
@@ -24,5 +32,56 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, criterion_benchmark);
+fn sheet() -> Vec<String> {
+    let mut lines = vec!["fib(n): if(n < 2, n, fib(n - 1) + fib(n - 2))".to_owned()];
+    for i in 0..50 {
+        lines.extend([
+            format!("Block {i}:"),
+            format!("price_{i}: {i} EUR * 1.25 + fib(12) EUR"),
+            format!("count_{i}: sqrt({i} + 16) * pow(2, 10) / 3"),
+            format!("price_{i} * count_{i} in USD"),
+            "prev / 100 + #2 * 0".to_owned(),
+            "sum".to_owned(),
+        ]);
+    }
+    lines
+}
+
+/// Evaluating every line after an edit against only the affected ones
+fn sheet_benchmark(c: &mut Criterion) {
+    let lines = sheet();
+    let mut group = c.benchmark_group("edit sheet");
+    group.bench_function("evaluate every line", |b| {
+        b.iter(|| evaluate_sheet(black_box(&lines), &Context::default()))
+    });
+
+    let mut calculator = Calculator::default();
+    calculator.update_line(vec![EditAction::InsertMultiple(
+        EditPosition::new(0, 0),
+        lines.join("\n"),
+    )]);
+    // Types a character at the end of a line and removes it again
+    let mut bench_edit = |name: &str, line: usize, c: char| {
+        let end = EditPosition::new(line, lines[line].len());
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                calculator.update_line(vec![EditAction::Insert(black_box(end), c)]);
+                calculator.update_line(vec![EditAction::Delete {
+                    selection_start: EditPosition::new(end.line, end.column + 1),
+                    selection_end: None,
+                }]);
+            })
+        });
+    };
+    // `sum` at the end, nothing reads it
+    bench_edit("evaluate affected lines", lines.len() - 1, ' ');
+    // `count_25`, the digit changes its value and so the rest of its block
+    bench_edit("evaluate affected lines, middle line", 1 + 25 * 6 + 2, '1');
+    // `fib`, a function gets a new version whenever its line changes, so every line that calls it
+    // is evaluated again
+    bench_edit("evaluate affected lines, first line", 0, ' ');
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark, sheet_benchmark);
 criterion_main!(benches);
@@ -0,0 +1,73 @@
+use std::{
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use env_logger::Env;
+use raekna::{
+    context::load_context,
+    sheet::{LineResult, Sheet},
+};
+use raekna_parser::parse;
+
+const USAGE: &str = "Usage: raekna-cli [FILE]
+
+Evaluates every line of FILE and prints each line next to its result, warnings are printed to
+standard error. Reads from standard input if FILE is omitted or is -.";
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+    let path = std::env::args().nth(1);
+    if matches!(path.as_deref(), Some("-h" | "--help")) {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let input = match path.as_deref() {
+        Some(path) if path != "-" => std::fs::read_to_string(path),
+        _ => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).map(|_| input)
+        }
+    };
+    let input = match input {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("Could not read input: {e}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let lines = input.lines().collect::<Vec<_>>();
+    let parsed = lines.iter().map(|line| parse(line)).collect::<Vec<_>>();
+    let mut sheet = Sheet::default();
+    let results = sheet.evaluate(lines.iter().copied().zip(&parsed), &load_context());
+    print!("{}", format_sheet(&lines, &results));
+    for (index, warning) in sheet.warnings().iter().enumerate() {
+        if let Some(warning) = warning {
+            eprintln!("warning on line {}: {warning}", index + 1);
+        }
+    }
+
+    if results.iter().any(LineResult::is_error) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn format_sheet(lines: &[&str], results: &[LineResult]) -> String {
+    let results = results
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+    let content_width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let result_width = results.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+    lines
+        .iter()
+        .zip(results.iter())
+        .map(|(line, result)| {
+            let row = format!("{line:<content_width$} | {result:>result_width$}");
+            format!("{}\n", row.trim_end())
+        })
+        .collect()
+}
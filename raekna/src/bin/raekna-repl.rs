@@ -0,0 +1,58 @@
+use std::process::ExitCode;
+
+use env_logger::Env;
+use raekna::{
+    context::load_context,
+    repl::{Repl, ReplOutput},
+};
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Could not start the terminal editor: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let history_path = dirs::data_dir().map(|dir| dir.join("raekna").join("repl_history.txt"));
+    if let Some(path) = &history_path {
+        // There is no history the first time the REPL is started
+        let _ = editor.load_history(path);
+    }
+
+    println!("raekna {} - type :help for help", env!("CARGO_PKG_VERSION"));
+    let mut repl = Repl::new(load_context());
+    loop {
+        match editor.readline("> ") {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    let _ = editor.add_history_entry(line.as_str());
+                }
+                match repl.handle_input(&line) {
+                    ReplOutput::None => {}
+                    ReplOutput::Text(text) => println!("{text}"),
+                    ReplOutput::Quit => break,
+                }
+            }
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("Could not read input: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if let Some(path) = &history_path {
+        let saved = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(ReadlineError::from)
+            .and_then(|_| editor.save_history(path));
+        if let Err(e) = saved {
+            eprintln!("Could not save history: {e}");
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -1,15 +1,96 @@
-use std::collections::HashMap;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use raekna_common::{
-    errors::CommonResult, BoundaryPriority, EditAction, EditPosition, RCalculator,
+    errors::CommonResult, BoundaryPriority, EditAction, EditPosition, LineKind, RCalculator,
+    Variable,
+};
+use raekna_compute::Context;
+use raekna_parser::line_kind;
+use raekna_storage::{
+    autosave::{Autosave, Recovered},
+    storage::Storage,
 };
-use raekna_compute::evaluate;
-use raekna_parser::parse;
-use raekna_storage::storage::Storage;
+
+use crate::sheet::Sheet;
+
+const AUTOSAVE_DELAY: Duration = Duration::from_secs(2);
+const AUTOSAVE_MAX_DELAY: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Default)]
 pub struct Calculator {
     storage: Storage,
+    file_path: Option<PathBuf>,
+    autosave: Option<Autosave>,
+    recovered: Option<Recovered>,
+    context: Context,
+    line_kinds: Vec<LineKind>,
+    line_warnings: Vec<Option<String>>,
+    sheet: Sheet,
+}
+
+impl Calculator {
+    /// Starts an empty sheet if there is no file at `path` yet
+    pub fn with_file(path: PathBuf) -> CommonResult<Self> {
+        let mut calculator = Self::default();
+        if path.exists() {
+            calculator.load(&path)?;
+        } else {
+            calculator.file_path = Some(path);
+        }
+        Ok(calculator)
+    }
+
+    /// Keeps a copy of the sheet in `recovery_dir` and picks up a session left by a crash
+    pub fn enable_autosave(&mut self, recovery_dir: &Path) {
+        self.recovered = Autosave::recover(recovery_dir);
+        match Autosave::new(recovery_dir, AUTOSAVE_DELAY, AUTOSAVE_MAX_DELAY) {
+            Ok(mut autosave) => {
+                autosave.set_file_path(self.file_path.as_deref());
+                self.autosave = Some(autosave);
+            }
+            Err(e) => log::error!("Could not enable autosave: {e}"),
+        }
+    }
+
+    pub fn set_context(&mut self, context: Context) {
+        self.context = context;
+        self.sheet = Sheet::default();
+        self.evaluate_lines();
+    }
+
+    fn set_file_path(&mut self, path: Option<PathBuf>) {
+        if let Some(autosave) = &mut self.autosave {
+            autosave.set_file_path(path.as_deref());
+        }
+        self.file_path = path;
+    }
+
+    fn schedule_autosave(&self) {
+        if let Some(autosave) = &self.autosave {
+            autosave.schedule(self.storage.get_lines().0.to_vec());
+        }
+    }
+
+    fn evaluate_lines(&mut self) {
+        let line_results = self
+            .sheet
+            .evaluate(self.storage.parse_lines(), &self.context);
+        let (contents, results) = self.storage.get_lines_mut();
+        self.line_kinds = contents.iter().map(|line| line_kind(line)).collect();
+        results
+            .iter_mut()
+            .zip(line_results)
+            .for_each(|(r, line_result)| *r = line_result.to_string());
+        self.line_warnings = self
+            .sheet
+            .warnings()
+            .iter()
+            .map(|warning| warning.as_ref().map(ToString::to_string))
+            .collect();
+    }
 }
 
 impl RCalculator for Calculator {
@@ -21,19 +102,36 @@ impl RCalculator for Calculator {
         self.storage.get_line(index)
     }
 
+    fn get_line_kinds(&self) -> &[LineKind] {
+        &self.line_kinds
+    }
+
+    fn get_line_warnings(&self) -> &[Option<String>] {
+        &self.line_warnings
+    }
+
+    fn get_variables(&self) -> &[Variable] {
+        self.sheet.variables()
+    }
+
     fn update_line(&mut self, actions: Vec<EditAction>) {
         self.storage.handle_actions(actions);
-        let mut variables = HashMap::new();
-        let (contents, results) = self.storage.get_lines_mut();
-        results.iter_mut().zip(contents.iter()).for_each(|(r, c)| {
-            let ast = parse(c);
-            if let Ok(ast) = ast {
-                *r = match evaluate(ast, &mut variables) {
-                    Ok(res) => res.to_string(),
-                    _ => "Error".to_owned(),
-                }
-            }
-        });
+        self.evaluate_lines();
+        self.schedule_autosave();
+    }
+
+    fn undo(&mut self) -> Option<EditPosition> {
+        let position = self.storage.undo()?;
+        self.evaluate_lines();
+        self.schedule_autosave();
+        Some(position)
+    }
+
+    fn redo(&mut self) -> Option<EditPosition> {
+        let position = self.storage.redo()?;
+        self.evaluate_lines();
+        self.schedule_autosave();
+        Some(position)
     }
 
     fn get_selection(&self, selection_start: EditPosition, selection_end: EditPosition) -> String {
@@ -47,4 +145,44 @@ impl RCalculator for Calculator {
     ) -> Option<(EditPosition, EditPosition)> {
         self.storage.get_word_boundaries(origin, priority)
     }
+
+    fn load(&mut self, path: &Path) -> CommonResult<()> {
+        self.storage = Storage::load(path)?;
+        self.set_file_path(Some(path.to_owned()));
+        self.evaluate_lines();
+        Ok(())
+    }
+
+    fn save(&mut self, path: &Path) -> CommonResult<()> {
+        self.storage.save(path)?;
+        self.set_file_path(Some(path.to_owned()));
+        Ok(())
+    }
+
+    fn file_path(&self) -> Option<&Path> {
+        self.file_path.as_deref()
+    }
+
+    fn has_recovered_session(&self) -> bool {
+        self.recovered.is_some()
+    }
+
+    /// Unties the sheet from its file unless the recovered lines belong to the same one
+    fn restore_recovered_session(&mut self) {
+        if let Some(recovered) = self.recovered.take() {
+            self.storage.replace_all(recovered.lines.clone());
+            if self.file_path != recovered.file_path {
+                self.set_file_path(None);
+            }
+            recovered.discard();
+            self.evaluate_lines();
+            self.schedule_autosave();
+        }
+    }
+
+    fn dismiss_recovered_session(&mut self) {
+        if let Some(recovered) = self.recovered.take() {
+            recovered.discard();
+        }
+    }
 }
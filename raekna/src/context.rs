@@ -0,0 +1,33 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use raekna_common::{currency::ExchangeRates, errors::CommonError};
+use raekna_compute::Context;
+
+pub fn rates_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("raekna").join("rates.txt"))
+}
+
+/// Keeps why an invalid rates file couldn't be loaded so conversions can say so
+pub fn load_context() -> Context {
+    let (rates, rates_error) = match rates_path().map(|path| ExchangeRates::load(&path)) {
+        Some(Ok(rates)) => {
+            for (code, line) in rates.unknown_currencies() {
+                log::warn!(
+                    "Skipped line {line} of the exchange rates, `{code}` isn't a known currency"
+                );
+            }
+            (rates, None)
+        }
+        Some(Err(CommonError::Io(e))) if e.kind() == ErrorKind::NotFound => Default::default(),
+        Some(Err(e)) => {
+            log::warn!("Could not load exchange rates: {e}");
+            (Default::default(), Some(e.to_string()))
+        }
+        None => Default::default(),
+    };
+    Context {
+        rates,
+        rates_error,
+        ..Default::default()
+    }
+}
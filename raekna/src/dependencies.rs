@@ -0,0 +1,125 @@
+use std::collections::BTreeSet;
+
+use raekna_common::expression::{Expression, LineReference};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Definition {
+    Variable(String),
+    Function(String),
+}
+
+impl Definition {
+    pub fn of(expression: &Expression) -> Option<Self> {
+        match expression {
+            Expression::Variable(name, _) => Some(Self::Variable(name.clone())),
+            Expression::FunctionDefinition(name, _, _) => Some(Self::Function(name.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Variable(name) | Self::Function(name) => name,
+        }
+    }
+}
+
+/// What a line defines and reads, names are resolved while the sheet is evaluated
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    pub defines: Option<Definition>,
+    /// For a function definition, what the body reads apart from its parameters
+    pub names: BTreeSet<String>,
+    pub lines: Vec<LineReference>,
+}
+
+impl Dependencies {
+    pub fn of(expression: &Expression) -> Self {
+        let mut dependencies = Self {
+            defines: Definition::of(expression),
+            ..Self::default()
+        };
+        match expression {
+            Expression::Variable(_, expr) => dependencies.add_reads(expr),
+            Expression::FunctionDefinition(_, parameters, body) => {
+                dependencies.add_reads(body);
+                parameters.iter().for_each(|parameter| {
+                    dependencies.names.remove(parameter);
+                });
+            }
+            expr => dependencies.add_reads(expr),
+        }
+        dependencies
+    }
+
+    fn add_reads(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Literal(_) => {}
+            Expression::VariableRef(name) => {
+                self.names.insert(name.clone());
+            }
+            Expression::UserFunction(name, args) => {
+                self.names.insert(name.clone());
+                args.iter().for_each(|arg| self.add_reads(arg));
+            }
+            Expression::Function(_, args) => args.iter().for_each(|arg| self.add_reads(arg)),
+            Expression::Conversion(expr, _) => self.add_reads(expr),
+            Expression::LineRef(reference) => {
+                if !self.lines.contains(reference) {
+                    self.lines.push(*reference);
+                }
+            }
+            // Definitions are only valid at the top of a line
+            Expression::Variable(_, expr) | Expression::FunctionDefinition(_, _, expr) => {
+                self.add_reads(expr)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raekna_parser::parse;
+
+    use super::*;
+
+    fn dependencies(line: &str) -> Dependencies {
+        Dependencies::of(&parse(line).unwrap())
+    }
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| (*name).to_owned()).collect()
+    }
+
+    #[test]
+    fn variables() {
+        let actual = dependencies("total: price * (1 + tax) in EUR");
+
+        assert_eq!(
+            actual.defines,
+            Some(Definition::Variable("total".to_owned()))
+        );
+        assert_eq!(actual.names, names(&["price", "tax"]));
+        assert!(actual.lines.is_empty());
+    }
+
+    #[test]
+    fn functions() {
+        let actual = dependencies("net(x): x * (1 - tax) + fee(x)");
+
+        assert_eq!(actual.defines, Some(Definition::Function("net".to_owned())));
+        assert_eq!(actual.names, names(&["fee", "tax"]));
+    }
+
+    #[test]
+    fn lines() {
+        let actual = dependencies("prev + #2 * prev + sum");
+
+        assert_eq!(actual.defines, None);
+        assert_eq!(actual.names, names(&["sum"]));
+        assert_eq!(
+            actual.lines,
+            [LineReference::Previous, LineReference::Line(2)]
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod calculator;
+pub mod context;
+pub mod dependencies;
+pub mod repl;
+pub mod sheet;
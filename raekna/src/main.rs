@@ -1,10 +1,26 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use raekna_ui::run_app;
+use std::path::PathBuf;
 
-mod calculator;
+use env_logger::Env;
+use raekna::{calculator::Calculator, context::load_context};
+use raekna_ui::run_app;
 
 fn main() -> Result<(), impl std::error::Error> {
-    let calculator = Box::new(calculator::Calculator::default());
-    run_app(calculator)
+    env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+    let mut calculator = match std::env::args_os().nth(1) {
+        Some(path) => match Calculator::with_file(PathBuf::from(path)) {
+            Ok(calculator) => calculator,
+            Err(e) => {
+                eprintln!("Could not open file: {e}");
+                std::process::exit(1);
+            }
+        },
+        None => Calculator::default(),
+    };
+    calculator.set_context(load_context());
+    if let Some(data_dir) = dirs::data_dir() {
+        calculator.enable_autosave(&data_dir.join("raekna").join("recovery"));
+    }
+    run_app(Box::new(calculator))
 }
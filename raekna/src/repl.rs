@@ -0,0 +1,206 @@
+use std::{collections::HashMap, path::Path};
+
+use raekna_common::{errors::CommonResult, expression::Literal};
+use raekna_compute::{evaluate, Context, LinesAbove, UserFunction};
+use raekna_parser::{parse, ParserError};
+use raekna_storage::storage::Storage;
+
+pub const HELP: &str = "Enter an expression to evaluate it. Variables defined with `name: expression` and functions defined with `name(a, b): expression` are kept for later entries. Earlier results can be used with `ans`, or `#1` for the first entry.
+
+Commands:
+  :vars          list all variables
+  :clear         forget all variables, functions and entries
+  :save <file>   save the entries as a sheet that can be opened in raekna
+  :help          show this message
+  :quit          exit";
+
+#[derive(Debug, PartialEq)]
+pub enum ReplOutput {
+    None,
+    Text(String),
+    Quit,
+}
+
+/// Evaluates one entry at a time, like the lines of a sheet
+#[derive(Debug, Default)]
+pub struct Repl {
+    variables: HashMap<String, Literal>,
+    functions: HashMap<String, UserFunction>,
+    entries: Vec<String>,
+    /// So `prev` and `#3` refer to entries like in the saved sheet
+    results: Vec<Option<Literal>>,
+    context: Context,
+}
+
+impl Repl {
+    pub fn new(context: Context) -> Self {
+        Self {
+            context,
+            ..Default::default()
+        }
+    }
+
+    pub fn handle_input(&mut self, input: &str) -> ReplOutput {
+        let input = input.trim();
+        match input.strip_prefix(':') {
+            Some(command) => self.handle_command(command),
+            None => self.evaluate(input),
+        }
+    }
+
+    /// Writes the entries that evaluated successfully
+    pub fn save(&self, path: &Path) -> CommonResult<()> {
+        Storage::from(self.entries.clone()).save(path)
+    }
+
+    fn evaluate(&mut self, input: &str) -> ReplOutput {
+        match parse(input) {
+            Ok(ast) => match evaluate(
+                ast,
+                &mut self.variables,
+                &mut self.functions,
+                LinesAbove {
+                    results: &self.results,
+                    block_start: 0,
+                },
+                &self.context,
+            ) {
+                Ok(value) => {
+                    self.entries.push(input.to_owned());
+                    self.results.push(value.clone());
+                    match value {
+                        Some(value) => ReplOutput::Text(value.to_string()),
+                        None => ReplOutput::None,
+                    }
+                }
+                Err(e) => ReplOutput::Text(format!("Error: {e}")),
+            },
+            Err(ParserError::EmptyExpression) => ReplOutput::None,
+            Err(e) => ReplOutput::Text(format!("Error: {e}")),
+        }
+    }
+
+    fn handle_command(&mut self, command: &str) -> ReplOutput {
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+        match name {
+            "vars" => ReplOutput::Text(self.format_variables()),
+            "clear" => {
+                self.variables.clear();
+                self.functions.clear();
+                self.entries.clear();
+                self.results.clear();
+                ReplOutput::None
+            }
+            "save" if argument.is_empty() => ReplOutput::Text("Usage: :save <file>".to_owned()),
+            "save" => match self.save(Path::new(argument)) {
+                Ok(()) => ReplOutput::Text(format!("Saved to {argument}")),
+                Err(e) => ReplOutput::Text(format!("Could not save to {argument}: {e}")),
+            },
+            "help" => ReplOutput::Text(HELP.to_owned()),
+            "quit" | "q" => ReplOutput::Quit,
+            _ => ReplOutput::Text(format!("Unknown command :{name}, see :help")),
+        }
+    }
+
+    fn format_variables(&self) -> String {
+        let mut variables = self.variables.iter().collect::<Vec<_>>();
+        variables.sort_by_key(|(name, _)| *name);
+        let width = variables.iter().map(|(name, _)| name.len()).max();
+        variables
+            .into_iter()
+            .map(|(name, value)| format!("{name:<width$} = {value}", width = width.unwrap_or(0)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> ReplOutput {
+        ReplOutput::Text(s.to_owned())
+    }
+
+    #[test]
+    fn variables_persist_across_entries() {
+        let mut sut = Repl::default();
+
+        assert_eq!(sut.handle_input("width: 5"), text("5"));
+        assert_eq!(sut.handle_input("height: 2.5"), text("2.5"));
+        assert_eq!(sut.handle_input("width * height"), text("12.5"));
+        assert_eq!(sut.handle_input(":vars"), text("height = 2.5\nwidth  = 5"));
+    }
+
+    #[test]
+    fn functions_persist_across_entries() {
+        let mut sut = Repl::default();
+
+        assert_eq!(sut.handle_input("area(w, h): w * h"), ReplOutput::None);
+        assert_eq!(sut.handle_input("area(3, 4)"), text("12"));
+        assert_eq!(sut.handle_input(":clear"), ReplOutput::None);
+        assert_eq!(
+            sut.handle_input("area(3, 4)"),
+            text("Error: unknown function `area`")
+        );
+    }
+
+    #[test]
+    fn line_references_refer_to_entries() {
+        let mut sut = Repl::default();
+        sut.handle_input("12 * 3");
+        sut.handle_input("1 / 0");
+
+        assert_eq!(sut.handle_input("ans + 4"), text("40"));
+        assert_eq!(sut.handle_input("#1 + #2"), text("76"));
+        assert_eq!(
+            sut.handle_input("#5"),
+            text("Error: line 5 isn't above this line")
+        );
+    }
+
+    #[test]
+    fn clear_forgets_variables() {
+        let mut sut = Repl::default();
+        sut.handle_input("a: 5");
+
+        assert_eq!(sut.handle_input(":clear"), ReplOutput::None);
+        assert_eq!(
+            sut.handle_input("a + 1"),
+            text("Error: unknown variable `a`")
+        );
+        assert_eq!(sut.handle_input(":vars"), text(""));
+    }
+
+    #[test]
+    fn blank_input_and_commands() {
+        let mut sut = Repl::default();
+
+        assert_eq!(sut.handle_input("   "), ReplOutput::None);
+        assert_eq!(sut.handle_input(":quit"), ReplOutput::Quit);
+        assert_eq!(sut.handle_input(":save"), text("Usage: :save <file>"));
+        assert_eq!(
+            sut.handle_input(":nope"),
+            text("Unknown command :nope, see :help")
+        );
+    }
+
+    #[test]
+    fn save_writes_evaluated_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sheet.txt");
+        let mut sut = Repl::default();
+        sut.handle_input("a: 5");
+        sut.handle_input("1 / 0");
+        sut.handle_input(":vars");
+        sut.handle_input("a * 2");
+
+        sut.save(&path).unwrap();
+
+        let actual = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(actual, "a: 5\na * 2\n");
+    }
+}
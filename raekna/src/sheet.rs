@@ -0,0 +1,667 @@
+use std::collections::{HashMap, HashSet};
+
+use raekna_common::{
+    expression::{Expression, LineReference, Literal},
+    LineKind, Variable,
+};
+use raekna_compute::{
+    evaluate, is_aggregate, is_time_dependent, ComputeError, Context, LinesAbove, UserFunction,
+};
+use raekna_parser::{line_kind, parse, ParserError, ParserResult};
+
+use crate::dependencies::{Definition, Dependencies};
+
+#[derive(Clone, Debug)]
+pub enum LineResult {
+    Empty,
+    Value(Literal),
+    ParseError(ParserError),
+    ComputeError(ComputeError),
+}
+
+impl LineResult {
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::ParseError(_) | Self::ComputeError(_))
+    }
+
+    fn value(&self) -> Option<&Literal> {
+        match self {
+            Self::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for LineResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Empty => Ok(()),
+            Self::Value(value) => write!(f, "{value}"),
+            Self::ParseError(e) => write!(f, "{e}"),
+            Self::ComputeError(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Something about a line that works but might not do what was intended
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Warning {
+    /// The line defines a variable or function again that the line with this number defined
+    Redefinition(String, usize),
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Redefinition(name, line) => {
+                write!(f, "`{name}` was already defined on line {line}")
+            }
+        }
+    }
+}
+
+/// Evaluates every line of a sheet in order, each line can use what the lines above it define
+pub fn evaluate_sheet<S: AsRef<str>>(lines: &[S], context: &Context) -> Vec<LineResult> {
+    let parsed = lines
+        .iter()
+        .map(|line| parse(line.as_ref()))
+        .collect::<Vec<_>>();
+    let lines = lines.iter().map(AsRef::as_ref).zip(&parsed);
+    Sheet::default().evaluate(lines, context)
+}
+
+/// Like [`evaluate_sheet`], but only evaluates lines whose content or inputs changed since last time
+#[derive(Debug, Default)]
+pub struct Sheet {
+    cache: HashMap<String, CachedLine>,
+    /// Every function definition that is evaluated gets a new version
+    next_function_version: u64,
+    warnings: Vec<Option<Warning>>,
+    variables: Vec<Variable>,
+}
+
+#[derive(Debug)]
+struct CachedLine {
+    dependencies: Dependencies,
+    evaluations: Vec<Evaluation>,
+    /// Used by the evaluation before, dropped unless used again
+    previous: Vec<Evaluation>,
+}
+
+#[derive(Debug)]
+struct Evaluation {
+    inputs: Inputs,
+    result: LineResult,
+    binding: Option<Binding>,
+}
+
+/// A variable or function that a line makes available to the lines below it
+#[derive(Clone, Debug)]
+enum Binding {
+    Variable(String, Literal),
+    Function(String, UserFunction, u64),
+}
+
+#[derive(Debug, Default)]
+struct Scope {
+    variables: HashMap<String, Literal>,
+    functions: HashMap<String, UserFunction>,
+    function_dependencies: HashMap<String, (u64, Dependencies)>,
+    results: Vec<Option<Literal>>,
+    block_start: usize,
+    definitions: HashMap<Definition, usize>,
+}
+
+impl Scope {
+    fn line(&self, reference: LineReference) -> Option<&Option<Literal>> {
+        match reference {
+            LineReference::Previous => self.results.last(),
+            LineReference::Line(line) => line.checked_sub(1).and_then(|i| self.results.get(i)),
+            LineReference::Removed => None,
+        }
+    }
+
+    fn block(&self) -> &[Option<Literal>] {
+        &self.results[self.block_start..]
+    }
+
+    fn bind(&mut self, binding: Binding, dependencies: &Dependencies) {
+        let line = self.results.len();
+        match binding {
+            Binding::Variable(name, value) => {
+                self.definitions
+                    .insert(Definition::Variable(name.clone()), line);
+                self.variables.insert(name, value);
+            }
+            Binding::Function(name, function, version) => {
+                self.definitions
+                    .insert(Definition::Function(name.clone()), line);
+                self.function_dependencies
+                    .insert(name.clone(), (version, dependencies.clone()));
+                self.functions.insert(name, function);
+            }
+        }
+    }
+}
+
+/// The values that a line read when it was evaluated
+#[derive(Debug, Default)]
+struct Inputs {
+    /// The variable and function version of each name, including names read by called functions
+    names: Vec<(String, Option<Literal>, Option<u64>)>,
+    lines: Vec<(LineReference, Option<Option<Literal>>)>,
+    block: Option<Vec<Option<Literal>>>,
+}
+
+impl Inputs {
+    /// `None` if the line depends on the clock and has to be evaluated every time
+    fn read(dependencies: &Dependencies, scope: &Scope) -> Option<Self> {
+        // The body of a function isn't evaluated where it's defined
+        if let Some(Definition::Function(_)) = dependencies.defines {
+            return Some(Self::default());
+        }
+        let mut names = dependencies
+            .names
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let mut lines = dependencies.lines.clone();
+        let mut i = 0;
+        while i < names.len() {
+            if let Some((_, function)) = scope.function_dependencies.get(names[i]) {
+                for name in &function.names {
+                    if !names.contains(&name.as_str()) {
+                        names.push(name);
+                    }
+                }
+                for line in &function.lines {
+                    if !lines.contains(line) {
+                        lines.push(*line);
+                    }
+                }
+            }
+            i += 1;
+        }
+        if names.iter().any(|name| is_time_dependent(name)) {
+            return None;
+        }
+        let block = names
+            .iter()
+            .any(|name| is_aggregate(name))
+            .then(|| scope.block().to_vec());
+        let names = names
+            .into_iter()
+            .map(|name| {
+                let variable = scope.variables.get(name).cloned();
+                let function = scope.function_dependencies.get(name).map(|(v, _)| *v);
+                (name.to_owned(), variable, function)
+            })
+            .collect();
+        let lines = lines
+            .into_iter()
+            .map(|reference| (reference, scope.line(reference).cloned()))
+            .collect();
+        Some(Self {
+            names,
+            lines,
+            block,
+        })
+    }
+
+    fn are_current(&self, scope: &Scope) -> bool {
+        let names_are_current = self.names.iter().all(|(name, variable, function)| {
+            let version = scope.function_dependencies.get(name).map(|(v, _)| v);
+            is_same(scope.variables.get(name), variable.as_ref()) && version == function.as_ref()
+        });
+        let lines_are_current =
+            self.lines.iter().all(
+                |(reference, result)| match (scope.line(*reference), result) {
+                    (Some(current), Some(result)) => is_same(current.as_ref(), result.as_ref()),
+                    (current, result) => current.is_none() && result.is_none(),
+                },
+            );
+        let block_is_current = self.block.as_ref().is_none_or(|block| {
+            let current = scope.block();
+            current.len() == block.len()
+                && current
+                    .iter()
+                    .zip(block)
+                    .all(|(current, result)| is_same(current.as_ref(), result.as_ref()))
+        });
+        names_are_current && lines_are_current && block_is_current
+    }
+}
+
+/// Whether two values are exactly the same, unlike `==` which lets floats differ a little
+fn is_same(left: Option<&Literal>, right: Option<&Literal>) -> bool {
+    use Literal::*;
+    let (Some(left), Some(right)) = (left, right) else {
+        return left.is_none() && right.is_none();
+    };
+    match (left, right) {
+        (Float(left), Float(right)) => left.to_bits() == right.to_bits(),
+        (Complex(left), Complex(right)) => {
+            left.re.to_bits() == right.re.to_bits() && left.im.to_bits() == right.im.to_bits()
+        }
+        (Quantity(left, left_unit), Quantity(right, right_unit)) => {
+            left_unit == right_unit && is_same(Some(left), Some(right))
+        }
+        (Money(left, left_currency), Money(right, right_currency)) => {
+            left_currency == right_currency && is_same(Some(left), Some(right))
+        }
+        (Percentage(left), Percentage(right)) => is_same(Some(left), Some(right)),
+        (Formatted(left, left_format), Formatted(right, right_format)) => {
+            left_format == right_format && is_same(Some(left), Some(right))
+        }
+        _ => left == right,
+    }
+}
+
+impl Sheet {
+    pub fn evaluate<'a>(
+        &mut self,
+        lines: impl IntoIterator<Item = (&'a str, &'a ParserResult<Expression>)>,
+        context: &Context,
+    ) -> Vec<LineResult> {
+        let lines = lines.into_iter().collect::<Vec<_>>();
+        let mut previous = std::mem::take(&mut self.cache);
+        let mut scope = Scope::default();
+        self.warnings.clear();
+        let mut results = Vec::with_capacity(lines.len());
+        for &(content, parsed) in &lines {
+            if matches!(line_kind(content), LineKind::Blank | LineKind::Heading) {
+                scope.block_start = scope.results.len() + 1;
+            }
+            let warning = parsed
+                .as_ref()
+                .ok()
+                .and_then(Definition::of)
+                .and_then(|definition| {
+                    let line = scope.definitions.get(&definition)?;
+                    Some(Warning::Redefinition(
+                        definition.name().to_owned(),
+                        line + 1,
+                    ))
+                });
+            self.warnings.push(warning);
+            let result = match parsed {
+                Ok(expression) => {
+                    self.evaluate_line(content, expression, &mut previous, &mut scope, context)
+                }
+                Err(ParserError::EmptyExpression) => LineResult::Empty,
+                Err(e) => LineResult::ParseError(e.clone()),
+            };
+            scope.results.push(result.value().cloned());
+            results.push(result);
+        }
+        self.cache
+            .values_mut()
+            .for_each(|cached| cached.previous.clear());
+
+        // Evaluations are cached without looking at the lines below, so names that are only
+        // defined further down are explained here
+        let dependencies = lines
+            .iter()
+            .map(|(content, _)| self.cache.get(*content).map(|cached| &cached.dependencies))
+            .collect::<Vec<_>>();
+        for (index, result) in results.iter_mut().enumerate() {
+            if let LineResult::ComputeError(e) = result {
+                if let Some(explained) = explain_undefined(e, index, &dependencies) {
+                    *e = explained;
+                }
+            }
+        }
+
+        let mut variables = scope
+            .definitions
+            .into_iter()
+            .filter_map(|(definition, line)| match definition {
+                Definition::Variable(name) => {
+                    let value = scope.variables.get(&name)?.to_string();
+                    Some(Variable { name, value, line })
+                }
+                Definition::Function(_) => None,
+            })
+            .collect::<Vec<_>>();
+        variables.sort_by_key(|variable| variable.line);
+        self.variables = variables;
+        results
+    }
+
+    pub fn warnings(&self) -> &[Option<Warning>] {
+        &self.warnings
+    }
+
+    /// Ordered by the line that defines them
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    fn evaluate_line(
+        &mut self,
+        content: &str,
+        expression: &Expression,
+        previous: &mut HashMap<String, CachedLine>,
+        scope: &mut Scope,
+        context: &Context,
+    ) -> LineResult {
+        let cached = self.cache.entry(content.to_owned()).or_insert_with(|| {
+            match previous.remove(content) {
+                Some(cached) => CachedLine {
+                    previous: cached.evaluations,
+                    evaluations: Vec::new(),
+                    ..cached
+                },
+                None => CachedLine {
+                    dependencies: Dependencies::of(expression),
+                    evaluations: Vec::new(),
+                    previous: Vec::new(),
+                },
+            }
+        });
+        let reused = match cached
+            .evaluations
+            .iter()
+            .position(|evaluation| evaluation.inputs.are_current(scope))
+        {
+            Some(index) => Some(index),
+            None => cached
+                .previous
+                .iter()
+                .position(|evaluation| evaluation.inputs.are_current(scope))
+                .map(|index| {
+                    let evaluation = cached.previous.swap_remove(index);
+                    cached.evaluations.push(evaluation);
+                    cached.evaluations.len() - 1
+                }),
+        };
+        if let Some(index) = reused {
+            let evaluation = &cached.evaluations[index];
+            if let Some(binding) = evaluation.binding.clone() {
+                scope.bind(binding, &cached.dependencies);
+            }
+            return evaluation.result.clone();
+        }
+
+        let inputs = Inputs::read(&cached.dependencies, scope);
+        let lines_above = LinesAbove {
+            results: &scope.results,
+            block_start: scope.block_start,
+        };
+        let value = evaluate(
+            expression.clone(),
+            &mut scope.variables,
+            &mut scope.functions,
+            lines_above,
+            context,
+        );
+        let binding = match (expression, &value) {
+            (Expression::Variable(name, _), Ok(Some(value))) => {
+                Some(Binding::Variable(name.clone(), value.clone()))
+            }
+            (Expression::FunctionDefinition(name, _, _), Ok(None)) => {
+                self.next_function_version += 1;
+                let version = self.next_function_version;
+                scope
+                    .functions
+                    .get(name)
+                    .map(|function| Binding::Function(name.clone(), function.clone(), version))
+            }
+            _ => None,
+        };
+        if let Some(binding) = binding.clone() {
+            scope.bind(binding, &cached.dependencies);
+        }
+        let result = match value {
+            Ok(Some(value)) => LineResult::Value(value),
+            Ok(None) => LineResult::Empty,
+            Err(e) => LineResult::ComputeError(e),
+        };
+        if let Some(inputs) = inputs {
+            cached.evaluations.push(Evaluation {
+                inputs,
+                result: result.clone(),
+                binding,
+            });
+        }
+        result
+    }
+}
+
+/// Explains an unknown name that is defined on or below its line, or is part of a cycle
+fn explain_undefined(
+    error: &ComputeError,
+    index: usize,
+    dependencies: &[Option<&Dependencies>],
+) -> Option<ComputeError> {
+    let undefined = match error {
+        ComputeError::UnknownVariable(name) => Definition::Variable(name.clone()),
+        ComputeError::UnknownFunction(name) => Definition::Function(name.clone()),
+        _ => return None,
+    };
+    let name = undefined.name().to_owned();
+    let defines = dependencies[index].and_then(|d| d.defines.as_ref());
+    if defines == Some(&undefined) {
+        return Some(ComputeError::CircularDefinition(name, Vec::new()));
+    }
+    let line = defining_line(index, dependencies, |defines| defines == &undefined)?;
+    if let Some(defines) = defines {
+        let mut through = Vec::new();
+        let mut visited = HashSet::from([index]);
+        if reaches(
+            defines.name(),
+            line,
+            index,
+            dependencies,
+            &mut through,
+            &mut visited,
+        ) {
+            return Some(ComputeError::CircularDefinition(
+                defines.name().to_owned(),
+                through,
+            ));
+        }
+    }
+    (line > index).then(|| ComputeError::DefinedBelow(name, line + 1))
+}
+
+/// The closest matching definition below `index`, or else the closest, failed, one above it
+fn defining_line(
+    index: usize,
+    dependencies: &[Option<&Dependencies>],
+    matches: impl Fn(&Definition) -> bool,
+) -> Option<usize> {
+    let is_match =
+        |d: &Option<&Dependencies>| d.and_then(|d| d.defines.as_ref()).is_some_and(&matches);
+    let below = index + 1;
+    dependencies[below..]
+        .iter()
+        .position(is_match)
+        .map(|line| below + line)
+        .or_else(|| dependencies[..index].iter().rposition(is_match))
+}
+
+/// Whether the definition on `line` reads `name`, collecting the names in between in `through`
+fn reaches(
+    name: &str,
+    line: usize,
+    origin: usize,
+    dependencies: &[Option<&Dependencies>],
+    through: &mut Vec<String>,
+    visited: &mut HashSet<usize>,
+) -> bool {
+    let Some((read, defines)) = dependencies[line].and_then(|d| Some((d, d.defines.as_ref()?)))
+    else {
+        return false;
+    };
+    visited.insert(line);
+    through.push(defines.name().to_owned());
+    if read.names.contains(name) {
+        return true;
+    }
+    for read in &read.names {
+        let definition = defining_line(origin, dependencies, |defines| defines.name() == read);
+        if let Some(definition) = definition {
+            if !visited.contains(&definition)
+                && reaches(name, definition, origin, dependencies, through, visited)
+            {
+                return true;
+            }
+        }
+    }
+    through.pop();
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate_incrementally(sheet: &mut Sheet, lines: &[&str]) -> Vec<String> {
+        let parsed = lines.iter().map(|line| parse(line)).collect::<Vec<_>>();
+        let lines = lines.iter().copied().zip(&parsed);
+        let results = sheet.evaluate(lines, &Context::default());
+        results.iter().map(LineResult::to_string).collect()
+    }
+
+    #[test]
+    fn edits_give_the_same_results_as_evaluating_everything() {
+        let edits: [&[&str]; 7] = [
+            &[
+                "rate: 2",
+                "f(x): x * rate",
+                "f(3)",
+                "",
+                "1",
+                "prev + 1",
+                "sum",
+            ],
+            &[
+                "rate: 3",
+                "f(x): x * rate",
+                "f(3)",
+                "",
+                "1",
+                "prev + 1",
+                "sum",
+            ],
+            &[
+                "rate: 3",
+                "f(x): x + rate",
+                "f(3)",
+                "",
+                "1",
+                "prev + 1",
+                "sum",
+            ],
+            &[
+                "rate: 3",
+                "f(x): x + rate",
+                "f(3)",
+                "",
+                "2",
+                "1",
+                "prev + 1",
+                "sum",
+            ],
+            &["rate: 1e-20", "f(x): x * rate", "f(1e20)", "sum"],
+            &["rate: 5e-20", "f(x): x * rate", "f(1e20)", "sum"],
+            &["f(x): x * rate", "f(3)", "#1 + #2"],
+        ];
+        let mut sheet = Sheet::default();
+        for lines in edits {
+            let expected = evaluate_sheet(lines, &Context::default())
+                .iter()
+                .map(LineResult::to_string)
+                .collect::<Vec<_>>();
+            let actual = evaluate_incrementally(&mut sheet, lines);
+            assert_eq!(actual, expected, "lines: {lines:?}");
+        }
+    }
+
+    #[test]
+    fn unchanged_definitions_are_not_evaluated_again() {
+        let mut sheet = Sheet::default();
+        evaluate_incrementally(&mut sheet, &["f(x): x * 2", "f(2)"]);
+        evaluate_incrementally(&mut sheet, &["", "f(x): x * 2", "f(3)"]);
+        assert_eq!(sheet.next_function_version, 1);
+
+        evaluate_incrementally(&mut sheet, &["f(x): x * 3", "f(3)"]);
+        assert_eq!(sheet.next_function_version, 2);
+    }
+
+    #[test]
+    fn unused_evaluations_are_dropped() {
+        let mut sheet = Sheet::default();
+        evaluate_incrementally(&mut sheet, &["a: 1", "a + 1"]);
+        evaluate_incrementally(&mut sheet, &["a: 2", "a + 1"]);
+        evaluate_incrementally(&mut sheet, &["a: 3", "a + 1"]);
+
+        assert_eq!(sheet.cache["a + 1"].evaluations.len(), 1);
+        assert_eq!(sheet.cache.len(), 2);
+    }
+
+    #[test]
+    fn names_used_above_their_definition_are_explained() {
+        let mut sheet = Sheet::default();
+        let lines = [
+            "total: rate * 2",
+            "f(3)",
+            "a: b + 1",
+            "b: c * 2",
+            "c: a - 1",
+            "x: x + 1",
+            "rate: 5",
+            "f(n): n * rate",
+        ];
+        let expected = [
+            "`rate` isn't defined until line 7",
+            "`f` isn't defined until line 8",
+            "`a` depends on itself through `b`, `c`",
+            "`b` depends on itself through `c`, `a`",
+            "`c` depends on itself through `a`, `b`",
+            "`x` depends on itself",
+            "5",
+            "",
+        ];
+        assert_eq!(evaluate_incrementally(&mut sheet, &lines), expected);
+
+        // The cached errors are explained again once the definitions below are gone
+        let lines = ["total: rate * 2", "f(3)"];
+        let expected = ["unknown variable `rate`", "unknown function `f`"];
+        assert_eq!(evaluate_incrementally(&mut sheet, &lines), expected);
+    }
+
+    #[test]
+    fn redefinitions_are_warned_about() {
+        let mut sheet = Sheet::default();
+        let lines = [
+            "x: 1",
+            "f(n): n",
+            "x: x + 1",
+            "f(n): n * x",
+            "f(2)",
+            "y: 1 / 0",
+        ];
+        let results = evaluate_incrementally(&mut sheet, &lines);
+
+        assert_eq!(results, ["1", "", "2", "", "4", "division by zero"]);
+        assert_eq!(
+            sheet.warnings(),
+            [
+                None,
+                None,
+                Some(Warning::Redefinition("x".to_owned(), 1)),
+                Some(Warning::Redefinition("f".to_owned(), 2)),
+                None,
+                None,
+            ]
+        );
+        let variables = sheet
+            .variables()
+            .iter()
+            .map(|v| (v.name.as_str(), v.value.as_str(), v.line))
+            .collect::<Vec<_>>();
+        assert_eq!(variables, [("x", "2", 2)]);
+    }
+}
@@ -1,19 +1,39 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    io::Write,
+    process::{Command, Stdio},
+};
 
-use raekna_common::expression::Literal;
-use raekna_compute::evaluate;
+use chrono::{FixedOffset, TimeZone};
+use raekna::{
+    calculator::Calculator,
+    sheet::{evaluate_sheet, LineResult},
+};
+use raekna_common::{
+    currency::ExchangeRates, expression::Literal, EditAction, EditPosition, LineKind, RCalculator,
+    Variable,
+};
+use raekna_compute::{evaluate, Clock, Context, LinesAbove};
 use raekna_parser::parse;
 
 #[test]
 fn test_simple_literal() {
     let mut variables = HashMap::new();
+    let mut functions = HashMap::new();
 
     let input = "123";
 
-    let expected = Literal::Integer(123);
+    let expected = Some(Literal::Integer(123));
     let actual = {
         let parsed = parse(input).unwrap();
-        evaluate(parsed, &mut variables).unwrap()
+        evaluate(
+            parsed,
+            &mut variables,
+            &mut functions,
+            LinesAbove::default(),
+            &Context::default(),
+        )
+        .unwrap()
     };
 
     assert_eq!(actual, expected);
@@ -22,14 +42,737 @@ fn test_simple_literal() {
 #[test]
 fn test_function_with_negative_argument() {
     let mut variables = HashMap::new();
+    let mut functions = HashMap::new();
 
     let input = "add(-1, -2)";
 
-    let expected = Literal::Integer(-3);
+    let expected = Some(Literal::Integer(-3));
     let actual = {
         let parsed = parse(input).unwrap();
-        evaluate(parsed, &mut variables).unwrap()
+        evaluate(
+            parsed,
+            &mut variables,
+            &mut functions,
+            LinesAbove::default(),
+            &Context::default(),
+        )
+        .unwrap()
     };
 
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn test_big_integers_are_exact() {
+    let lines = ["factorial(50)", "2^200", "2^200 - 2^200 + 1"];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "30414093201713378043612608166064768844377641568960512000000000000",
+            "1606938044258990275541962092341162602522202993782792835301376",
+            "1",
+        ]
+    );
+}
+
+#[test]
+fn test_decimals_and_fractions_are_exact() {
+    let lines = ["0.1 + 0.2", "1/3 * 3", "1/3", "frac(0.75)", "frac(1/3) * 2"];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "0.3",
+            "1",
+            "0.3333333333333333",
+            "3/4",
+            "0.6666666666666667"
+        ]
+    );
+}
+
+#[test]
+fn test_complex_numbers() {
+    let lines = [
+        "sqrt(-4)",
+        "(1 + 2 * i) * (3 - i)",
+        "i^2",
+        "conj(2 + 3 * i)",
+        "abs(3 + 4 * i)",
+    ];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(actual, ["2i", "5 + 5i", "-1", "2 - 3i", "5"]);
+}
+
+#[test]
+fn test_units() {
+    let lines = [
+        "5 km + 300 m",
+        "60 mph in m/s",
+        "2 GiB / 100 MB/s",
+        "speed: 90 km/h",
+        "speed * 30 min to km",
+        "5 km + 3 s",
+    ];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "5.3 km",
+            "26.8224 m/s",
+            "21.47483648 s",
+            "90 km/h",
+            "45 km",
+            "can't convert `s` to `km`",
+        ]
+    );
+}
+
+#[test]
+fn test_units_after_numbers_take_precedence_over_variables() {
+    let lines = [
+        "m: 5", "2m", "2 m", "2 * m", "2(m)", "h: 3", "2 hours", "x: 4", "2x",
+    ];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        ["5", "2 m", "2 m", "10", "10", "3", "2 h", "4", "8"]
+    );
+}
+
+#[test]
+fn test_currencies() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rates.txt");
+    std::fs::write(
+        &path,
+        "# Rates in SEK\nbase = SEK\nUSD = 10.52\nEUR = 11.43\n",
+    )
+    .unwrap();
+    let context = Context {
+        rates: ExchangeRates::load(&path).unwrap(),
+        ..Default::default()
+    };
+    let lines = [
+        "100 USD in SEK",
+        "€20 + 5 USD",
+        "rent: 12000 SEK",
+        "rent / 3 in EUR",
+        "100 USD in GBP",
+        "100 USD + 5",
+    ];
+
+    let actual = evaluate_sheet(&lines, &context)
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "1052 SEK",
+            "24.6 EUR",
+            "12000 SEK",
+            "349.96 EUR",
+            "no exchange rate for GBP",
+            "expected an amount in USD but got a plain number",
+        ]
+    );
+}
+
+#[test]
+fn test_currencies_with_an_invalid_rates_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rates.txt");
+    std::fs::write(&path, "base = SEK\nUSD = 10.52\nEUR 11.43\n").unwrap();
+    let error = ExchangeRates::load(&path).unwrap_err();
+    let context = Context {
+        rates_error: Some(error.to_string()),
+        ..Default::default()
+    };
+    let lines = ["100 USD in SEK", "5 EUR + 2 USD", "5 EUR + 2 EUR"];
+
+    let actual = evaluate_sheet(&lines, &context)
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "no exchange rate for USD, the rates file couldn't be loaded: line 3 isn't a valid exchange rate",
+            "no exchange rate for USD, the rates file couldn't be loaded: line 3 isn't a valid exchange rate",
+            "7 EUR",
+        ]
+    );
+}
+
+#[test]
+fn test_bases() {
+    let lines = [
+        "0xFF + 0b1010",
+        "255 in hex",
+        "mask: 0o777",
+        "to_bin(mask)",
+        "hex(-16) * 2",
+        "1.5 in bin",
+    ];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "265",
+            "0xFF",
+            "511",
+            "0b111111111",
+            "-32",
+            "only integers can be shown in another base",
+        ]
+    );
+}
+
+#[test]
+fn test_bitwise() {
+    let lines = [
+        "flags: 0b1100 | 0b0011",
+        "flags & ~0b0100 in bin",
+        "1 << 4 + 1",
+        "0xFF xor 0x0F in hex",
+        "u8(~5)",
+        "i8(200)",
+        "popcount(flags)",
+        "bit(flags, 2)",
+        "1.5 >> 1",
+    ];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "15",
+            "0b1011",
+            "32",
+            "0xF0",
+            "250",
+            "-56",
+            "4",
+            "1",
+            "`shr` only supports integers",
+        ]
+    );
+}
+
+#[test]
+fn test_comparisons() {
+    let lines = [
+        "budget: 1200 USD",
+        "spent: 1350 USD",
+        "budget_left: budget - spent",
+        "budget_left > 0",
+        "not budget_left > 0 and spent < 2000 USD",
+        "1 km >= 999 m",
+        "1/3 == 0.5 - 1/6",
+        "today != today",
+        "true or 0",
+        "true + 1",
+    ];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "1200 USD",
+            "1350 USD",
+            "-150 USD",
+            "false",
+            "true",
+            "true",
+            "true",
+            "false",
+            "true",
+            "`add` doesn't support booleans",
+        ]
+    );
+}
+
+#[test]
+fn test_conditionals() {
+    let lines = [
+        "income: 52000",
+        "tax: if(income > 45000, 9000 + (income - 45000) * 30%, if(income > 18000, (income - 18000) * 20%, 0))",
+        "units: 0",
+        "if(units, 100 / units, 0)",
+        "if(units == 0, 0 USD, 1 USD) + 2 USD",
+        "if(1 m, 1, 2)",
+        "if(1, 2)",
+    ];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "52000",
+            "11100",
+            "0",
+            "0",
+            "2 USD",
+            "expected a condition like `x > 0` but got `1 m`",
+            "`if` takes 3 arguments but got 2",
+        ]
+    );
+}
+
+#[test]
+fn test_user_functions() {
+    let lines = [
+        "area(w, h): w * h",
+        "area(3 m, 4 m)",
+        "w: 10",
+        "area(w, 2) + w",
+        "factorial_of(n): if(n <= 1, 1, n * factorial_of(n - 1))",
+        "factorial_of(10)",
+        "forever(n): forever(n + 1)",
+        "forever(0)",
+        "area(1)",
+        "volume(1, 2, 3)",
+        "sqrt(x): x",
+    ];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "",
+            "12 m^2",
+            "10",
+            "30",
+            "",
+            "3628800",
+            "",
+            "too many nested calls of `forever`, at most 100 are allowed",
+            "`area` takes 2 arguments but got 1",
+            "unknown function `volume`",
+            "`sqrt` is a built-in function",
+        ]
+    );
+}
+
+#[test]
+fn test_percentages() {
+    let lines = [
+        "200 + 15%",
+        "20% of 80",
+        "50 as % of 200",
+        "10% off 120",
+        "7 % 3",
+        "tax: 25%",
+        "80 USD + tax",
+        "10 off 120",
+    ];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "230",
+            "16",
+            "25%",
+            "108",
+            "1",
+            "25%",
+            "100 USD",
+            "`off` needs a percentage on the left, like `10% off 120`",
+        ]
+    );
+}
+
+#[test]
+fn test_dates() {
+    let now = FixedOffset::east_opt(2 * 3600)
+        .unwrap()
+        .with_ymd_and_hms(2026, 10, 17, 14, 30, 0)
+        .unwrap();
+    let context = Context {
+        clock: Clock::Fixed(now),
+        ..Default::default()
+    };
+    let lines = [
+        "today + 3 weeks",
+        "2026-12-24 - today",
+        "3h 25min * 4",
+        "now in UTC+9",
+        "deadline: 2026-12-24 17:00",
+        "deadline - now in h",
+        "today + 5",
+    ];
+
+    let actual = evaluate_sheet(&lines, &context)
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "2026-11-07",
+            "68 days",
+            "820 min",
+            "2026-10-17 21:30 UTC+9",
+            "2026-12-24 17:00",
+            "1634.5 h",
+            "expected a duration like `3 days` but got `5`",
+        ]
+    );
+}
+
+#[test]
+fn test_sheet_variables_are_scoped_to_lines_below() {
+    let lines = ["2 * my_var", "my_var: 5", "", "2 * my_var"];
+
+    let actual = evaluate_sheet(&lines, &Context::default());
+
+    assert!(matches!(actual[0], LineResult::ComputeError(_)));
+    assert!(matches!(actual[1], LineResult::Value(Literal::Integer(5))));
+    assert!(matches!(actual[2], LineResult::Empty));
+    assert!(matches!(actual[3], LineResult::Value(Literal::Integer(10))));
+}
+
+#[test]
+fn test_calculator_shows_error_messages() {
+    let mut calculator = Calculator::default();
+    calculator.update_line(vec![EditAction::InsertMultiple(
+        EditPosition::new(0, 0),
+        "1 / 0\nmax(i, 2)\n2 * foo".to_owned(),
+    )]);
+
+    let (_, results) = calculator.get_all_lines();
+    assert_eq!(
+        results,
+        [
+            "division by zero",
+            "`max` doesn't support complex numbers",
+            "unknown variable `foo`"
+        ]
+    );
+}
+
+#[test]
+fn test_calculator_clears_stale_result_on_parse_error() {
+    let mut calculator = Calculator::default();
+    calculator.update_line(vec![EditAction::InsertMultiple(
+        EditPosition::new(0, 0),
+        "2 +".to_owned(),
+    )]);
+    assert_eq!(calculator.get_line(0).unwrap(), ("2 +", "missing operand"));
+
+    calculator.update_line(vec![EditAction::Insert(EditPosition::new(0, 3), '3')]);
+    assert_eq!(calculator.get_line(0).unwrap(), ("2 +3", "5"));
+
+    calculator.update_line(vec![EditAction::Insert(EditPosition::new(0, 4), '*')]);
+    assert_eq!(
+        calculator.get_line(0).unwrap(),
+        ("2 +3*", "missing operand")
+    );
+}
+
+#[test]
+fn test_calculator_skips_comments_and_headings() {
+    let mut calculator = Calculator::default();
+    calculator.update_line(vec![EditAction::InsertMultiple(
+        EditPosition::new(0, 0),
+        "Groceries:\n# prices include VAT\n3 * 4.5 // apples\n\n2 +".to_owned(),
+    )]);
+
+    let (_, results) = calculator.get_all_lines();
+    assert_eq!(results, ["", "", "13.5", "", "missing operand"]);
+    assert_eq!(
+        calculator.get_line_kinds(),
+        [
+            LineKind::Heading,
+            LineKind::Comment,
+            LineKind::Expression(Some(8)),
+            LineKind::Blank,
+            LineKind::Expression(None),
+        ]
+    );
+}
+
+#[test]
+fn test_line_references() {
+    let lines = [
+        "1200",
+        "prev * 12",
+        "# yearly",
+        "#2 - line1",
+        "ans / 2",
+        "prev",
+        "#9",
+        "#?",
+        "prev: 5",
+    ];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "1200",
+            "14400",
+            "",
+            "13200",
+            "6600",
+            "6600",
+            "line 9 isn't above this line",
+            "the referenced line was removed",
+            "`prev` refers to a line and can't be defined",
+        ]
+    );
+}
+
+#[test]
+fn test_aggregates() {
+    let lines = [
+        "Invoice:",
+        "design: 1200 EUR",
+        "hosting: 15 EUR * 12",
+        "# travel is billed separately",
+        "total",
+        "",
+        "3 km",
+        "1500 m",
+        "true",
+        "avg",
+        "",
+        "2",
+        "4",
+        "sum / count",
+        "Empty:",
+        "avg",
+    ];
+
+    let actual = evaluate_sheet(&lines, &Context::default())
+        .iter()
+        .map(LineResult::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        [
+            "",
+            "1200 EUR",
+            "180 EUR",
+            "",
+            "1380 EUR",
+            "",
+            "3 km",
+            "1500 m",
+            "true",
+            "2.25 km",
+            "",
+            "2",
+            "4",
+            "3",
+            "",
+            "there are no results above to average",
+        ]
+    );
+}
+
+#[test]
+fn test_calculator_keeps_line_references_when_lines_move() {
+    let mut calculator = Calculator::default();
+    calculator.update_line(vec![EditAction::InsertMultiple(
+        EditPosition::new(0, 0),
+        "40\n2\n#1 + #2".to_owned(),
+    )]);
+    calculator.update_line(vec![EditAction::NewLine(EditPosition::new(0, 0))]);
+
+    let (lines, results) = calculator.get_all_lines();
+    assert_eq!(lines, ["", "40", "2", "#2 + #3"]);
+    assert_eq!(results, ["", "40", "2", "42"]);
+
+    calculator.update_line(vec![EditAction::Delete {
+        selection_start: EditPosition::new(1, 2),
+        selection_end: Some(EditPosition::new(2, 1)),
+    }]);
+
+    let (lines, results) = calculator.get_all_lines();
+    assert_eq!(lines, ["", "40", "#2 + #?"]);
+    assert_eq!(results, ["", "40", "the referenced line was removed"]);
+}
+
+#[test]
+fn test_calculator_lists_variables_and_redefinitions() {
+    let mut calculator = Calculator::default();
+    calculator.update_line(vec![EditAction::InsertMultiple(
+        EditPosition::new(0, 0),
+        "price: tax * 10\ntax: 1.25\nprice: tax * 10\ncount: 3".to_owned(),
+    )]);
+
+    let (_, results) = calculator.get_all_lines();
+    assert_eq!(
+        results,
+        ["`tax` isn't defined until line 2", "1.25", "12.5", "3"]
+    );
+    assert_eq!(calculator.get_line_warnings(), [None, None, None, None]);
+    let variable = |name: &str, value: &str, line| Variable {
+        name: name.to_owned(),
+        value: value.to_owned(),
+        line,
+    };
+    assert_eq!(
+        calculator.get_variables(),
+        [
+            variable("tax", "1.25", 1),
+            variable("price", "12.5", 2),
+            variable("count", "3", 3),
+        ]
+    );
+
+    calculator.update_line(vec![EditAction::Delete {
+        selection_start: EditPosition::new(0, 0),
+        selection_end: Some(EditPosition::new(0, 15)),
+    }]);
+    calculator.update_line(vec![EditAction::InsertMultiple(
+        EditPosition::new(0, 0),
+        "price: 5".to_owned(),
+    )]);
+
+    let (_, results) = calculator.get_all_lines();
+    assert_eq!(results, ["5", "1.25", "12.5", "3"]);
+    assert_eq!(
+        calculator.get_line_warnings(),
+        [
+            None,
+            None,
+            Some("`price` was already defined on line 1".to_owned()),
+            None
+        ]
+    );
+}
+
+#[test]
+fn test_calculator_restores_a_recovered_session_as_an_edit() {
+    let dir = tempfile::tempdir().unwrap();
+    let recovery_dir = dir.path().join("recovery");
+    std::fs::create_dir(&recovery_dir).unwrap();
+    std::fs::write(recovery_dir.join("1.session"), "/elsewhere/other.txt").unwrap();
+    std::fs::write(recovery_dir.join("1.txt"), "other: 1\n").unwrap();
+    let path = dir.path().join("budget.txt");
+    std::fs::write(&path, "rent: 900\n").unwrap();
+
+    let mut calculator = Calculator::with_file(path.clone()).unwrap();
+    calculator.enable_autosave(&recovery_dir);
+    calculator.update_line(vec![EditAction::Insert(EditPosition::new(0, 9), '0')]);
+    assert!(calculator.has_recovered_session());
+
+    calculator.restore_recovered_session();
+    assert_eq!(calculator.get_line(0).unwrap(), ("other: 1", "1"));
+    assert!(!calculator.has_recovered_session());
+    assert_eq!(calculator.file_path(), None);
+    assert!(!recovery_dir.join("1.txt").exists());
+
+    assert!(calculator.undo().is_some());
+    assert_eq!(calculator.get_line(0).unwrap(), ("rent: 9000", "9000"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "rent: 900\n");
+}
+
+fn run_cli(input: &str) -> (String, Option<i32>) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_raekna-cli"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        output.status.code(),
+    )
+}
+
+#[test]
+fn test_cli_prints_aligned_columns() {
+    let (stdout, code) = run_cli("my_var: 5\n\n2 * my_var + 100\n");
+
+    let expected = "my_var: 5        |   5\n                 |\n2 * my_var + 100 | 110\n";
+    assert_eq!(stdout, expected);
+    assert_eq!(code, Some(0));
+}
+
+#[test]
+fn test_cli_applies_every_bitwise_not() {
+    let (stdout, code) = run_cli("x: 5\n-~1\n2 * -~x\n~~1.5\n");
+
+    let expected = "x: 5    |                               5\n-~1     |                               2\n2 * -~x |                              12\n~~1.5   | `bitnot` only supports integers\n";
+    assert_eq!(stdout, expected);
+    assert_eq!(code, Some(1));
+}
+
+#[test]
+fn test_cli_fails_when_a_line_errors() {
+    let (stdout, code) = run_cli("1 / 0\n2 + 2\n");
+
+    let expected = "1 / 0 | division by zero\n2 + 2 |                4\n";
+    assert_eq!(stdout, expected);
+    assert_eq!(code, Some(1));
+}